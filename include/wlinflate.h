@@ -0,0 +1,68 @@
+/* Generated with cbindgen; regenerate with
+ *   cbindgen --config cbindgen.toml --output include/wlinflate.h
+ * after changing src/ffi.rs. */
+
+#ifndef WLINFLATE_H
+#define WLINFLATE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Opaque handle owning a wordlist and the last candidate returned.
+ */
+typedef struct WlinflateWordlist WlinflateWordlist;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create a wordlist from a JSON spec such as
+ * `{"wordlists": ["words.txt"], "prepend": ["dev-"]}`.
+ *
+ * Returns null if the spec is invalid or a wordlist cannot be read.
+ *
+ * # Safety
+ *
+ * `spec_json` must be a valid NUL-terminated string.
+ */
+WlinflateWordlist *wlinflate_new(const char *spec_json);
+
+/**
+ * The next candidate, or null at the end.
+ *
+ * The string is owned by the handle and stays valid until the next call
+ * on it. Candidates containing a NUL byte are skipped.
+ *
+ * # Safety
+ *
+ * `wl` must come from [`wlinflate_new`] and not have been freed.
+ */
+const char *wlinflate_next(WlinflateWordlist *wl);
+
+/**
+ * Number of candidates, saturating at `UINT64_MAX`.
+ *
+ * # Safety
+ *
+ * `wl` must come from [`wlinflate_new`] and not have been freed.
+ */
+uint64_t wlinflate_keyspace(const WlinflateWordlist *wl);
+
+/**
+ * Release a wordlist. Null is ignored.
+ *
+ * # Safety
+ *
+ * `wl` must come from [`wlinflate_new`] and not be used afterwards.
+ */
+void wlinflate_free(WlinflateWordlist *wl);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* WLINFLATE_H */
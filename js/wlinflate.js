@@ -0,0 +1,31 @@
+// Thin wrapper over the wasm-pack output in ../pkg, built with
+//   wasm-pack build --target web --features wasm
+import init, { Wordlist } from "../pkg/wlinflate.js";
+
+let ready;
+
+/**
+ * Inflate `lines` (an array or any iterable of strings) with `options`,
+ * which take the same fields as the library's WordlistSpec, e.g.
+ * `{ prepend: ["dev-"], extensions: [".bak"] }`.
+ *
+ * Resolves to an iterable of candidates that also reports the keyspace.
+ */
+export async function inflate(lines, options = {}) {
+  ready ??= init();
+  await ready;
+  const wordlist = new Wordlist(Array.from(lines), JSON.stringify(options));
+  return {
+    keyspace: wordlist.keyspace(),
+    *[Symbol.iterator]() {
+      try {
+        let batch;
+        while ((batch = wordlist.nextBatch(4096)).length > 0) {
+          yield* batch;
+        }
+      } finally {
+        wordlist.free();
+      }
+    },
+  };
+}
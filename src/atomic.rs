@@ -0,0 +1,117 @@
+//! Output files written under a temporary name and moved into place once
+//! complete, so nothing reading them sees half a list.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// An output file being written next to its destination. Dropping it
+/// before [`Staged::commit`] removes what was written.
+pub struct Staged {
+    temp: PathBuf,
+    dest: PathBuf,
+    committed: bool,
+}
+
+impl Staged {
+    /// Create the temporary file for `dest` in the same directory, so it
+    /// can be renamed over `dest` without copying.
+    pub fn create(dest: &Path) -> io::Result<(Self, File)> {
+        let name = dest.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "output is not a file name")
+        })?;
+        let temp = dest.with_file_name(format!(
+            ".{}.{}.partial",
+            name.to_string_lossy(),
+            process::id()
+        ));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        // a replaced file keeps its permissions
+        if let Ok(metadata) = fs::metadata(dest) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        Ok((
+            Self {
+                temp,
+                dest: dest.to_path_buf(),
+                committed: false,
+            },
+            file,
+        ))
+    }
+
+    /// Whether output to `dest` can be staged: anything but an existing
+    /// special file, such as a named pipe, or a symlink, which renaming
+    /// would replace rather than write through, as with `/dev/stdout`.
+    pub fn supported(dest: &Path) -> bool {
+        match fs::symlink_metadata(dest) {
+            Ok(metadata) => metadata.is_file(),
+            Err(_) => true,
+        }
+    }
+
+    /// Move the finished file into place, replacing any file already there.
+    /// It is synced first, so a crash can't leave a renamed but empty file.
+    pub fn commit(mut self) -> io::Result<()> {
+        File::open(&self.temp)?.sync_all()?;
+        fs::rename(&self.temp, &self.dest)?;
+        self.committed = true;
+        Ok(())
+    }
+
+    /// Leave what was written under its temporary name, returning it, for
+    /// a run that stopped before finishing.
+    pub fn keep(mut self) -> PathBuf {
+        self.committed = true;
+        self.temp.clone()
+    }
+}
+
+impl Drop for Staged {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_staged() {
+        let dir =
+            std::env::temp_dir().join(format!("wlinflate_test_atomic_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("out.txt");
+        fs::write(&dest, "old\n").unwrap();
+
+        let (staged, mut file) = Staged::create(&dest).unwrap();
+        file.write_all(b"new\n").unwrap();
+        assert!(fs::read_to_string(&dest).unwrap() == "old\n");
+        drop(staged);
+        assert!(fs::read_to_string(&dest).unwrap() == "old\n");
+        assert!(fs::read_dir(&dir).unwrap().count() == 1);
+
+        let (staged, mut file) = Staged::create(&dest).unwrap();
+        file.write_all(b"new\n").unwrap();
+        staged.commit().unwrap();
+        assert!(fs::read_to_string(&dest).unwrap() == "new\n");
+        assert!(fs::read_dir(&dir).unwrap().count() == 1);
+
+        let (staged, mut file) = Staged::create(&dest).unwrap();
+        file.write_all(b"partial\n").unwrap();
+        let kept = staged.keep();
+        assert!(fs::read_to_string(&kept).unwrap() == "partial\n");
+        assert!(fs::read_to_string(&dest).unwrap() == "new\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
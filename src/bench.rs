@@ -0,0 +1,120 @@
+use std::{
+    env, fs,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use log::info;
+use structopt::StructOpt;
+
+use crate::{error::Error, Args, DEFAULT_BUFFER_SIZE, INTERRUPTED};
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct BenchArgs {
+    #[structopt(
+        short = "d",
+        long = "duration",
+        default_value = "5",
+        help = "seconds to generate for"
+    )]
+    duration: f64,
+    #[structopt(
+        long = "synthetic",
+        default_value = "100000",
+        help = "size of the generated input when no --wordlist is given"
+    )]
+    synthetic: usize,
+}
+
+/// Temporary synthetic wordlist, removed on drop.
+struct Synthetic(PathBuf);
+
+impl Synthetic {
+    fn create(words: usize) -> io::Result<Self> {
+        let path = env::temp_dir().join(format!("wlinflate_bench_{}.txt", std::process::id()));
+        let mut writer = BufWriter::new(fs::File::create(&path)?);
+        for i in 0..words {
+            // every tenth line carries a swap token so -s is exercised too
+            if i % 10 == 0 {
+                writeln!(writer, "{{SWAP}}-word{:06}", i)?;
+            } else {
+                writeln!(writer, "word{:06}", i)?;
+            }
+        }
+        writer.flush()?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for Synthetic {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Generate into a sink for a fixed duration and report throughput.
+pub fn run(args: &Args, bench: &BenchArgs) -> Result<(), Error> {
+    if !bench.duration.is_finite() || bench.duration <= 0.0 {
+        return Err(Error::InvalidConfig(
+            "bench duration must be positive".to_string(),
+        ));
+    }
+
+    let synthetic;
+    let inputs = if args.wordlist.is_empty() {
+        synthetic = Synthetic::create(bench.synthetic)?;
+        vec![synthetic.0.clone()]
+    } else {
+        args.wordlist.clone()
+    };
+    if let Some(missing) = inputs.iter().find(|path| !path.is_file()) {
+        return Err(Error::InputNotFound(missing.clone()));
+    }
+
+    let buffer_size = args.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
+    let mut sink = BufWriter::with_capacity(buffer_size, io::sink());
+    let limit = Duration::from_secs_f64(bench.duration);
+
+    info!(
+        "Benchmarking {} wordlist(s) for {:.1}s",
+        inputs.len(),
+        bench.duration
+    );
+
+    let started = Instant::now();
+    let mut words: u64 = 0;
+    let mut bytes: u64 = 0;
+    let mut passes: u64 = 0;
+    'outer: loop {
+        for word in args.wordlist_at(&inputs)? {
+            sink.write_all(word.as_bytes())?;
+            sink.write_all(b"\n")?;
+            words += 1;
+            bytes += word.len() as u64 + 1;
+            if words.is_multiple_of(4096)
+                && (started.elapsed() >= limit || INTERRUPTED.load(Ordering::Relaxed))
+            {
+                break 'outer;
+            }
+        }
+        passes += 1;
+        if started.elapsed() >= limit || INTERRUPTED.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+    sink.flush()?;
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let mut out = io::stdout().lock();
+    writeln!(out, "elapsed:   {:.2}s ({} full passes)", elapsed, passes)?;
+    writeln!(out, "words:     {}", words)?;
+    writeln!(out, "words/sec: {:.0}", words as f64 / elapsed)?;
+    writeln!(
+        out,
+        "MB/sec:    {:.2}",
+        bytes as f64 / elapsed / 1_000_000.0
+    )?;
+    Ok(())
+}
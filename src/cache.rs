@@ -0,0 +1,295 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    hash::Hasher,
+    io::{self, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use log::warn;
+use wlinflate::hash::Fnv64;
+
+use crate::error::Error;
+
+/// On-disk cache of fully inflated outputs.
+///
+/// Entries are keyed by a hash of the input wordlist contents combined with a
+/// description of the pipeline configuration, so any change to either
+/// produces a new entry.
+pub struct Cache {
+    entry: PathBuf,
+    partial: PathBuf,
+}
+
+/// Hash of the input contents and a pipeline description, identifying the
+/// output they produce. Each input is named by the option it was given to,
+/// as the same file means something else as a wordlist than as templates.
+pub fn fingerprint(inputs: &[(&str, PathBuf)], config: &str) -> Result<u64, Error> {
+    let mut hasher = Fnv64::default();
+    for (option, input) in inputs {
+        hasher.write(option.as_bytes());
+        hasher.write(b"\0");
+        let reading = |e| Error::Input(input.clone(), e);
+        let mut reader = BufReader::new(File::open(input).map_err(reading)?);
+        let mut buf = [0u8; 64 * 1024];
+        let mut len = 0u64;
+        loop {
+            let n = reader.read(&mut buf).map_err(reading)?;
+            if n == 0 {
+                break;
+            }
+            hasher.write(&buf[..n]);
+            len += n as u64;
+        }
+        // the length ends the contents, which may hold any byte
+        hasher.write(&len.to_le_bytes());
+    }
+    hasher.write(config.as_bytes());
+    Ok(hasher.finish())
+}
+
+/// Bytes after an entry's words: their length and hash, so an entry cut
+/// short or changed on disk is noticed before it is replayed.
+const TRAILER_LEN: u64 = 16;
+
+/// Length and hash of the next `limit` bytes of `reader`.
+fn digest(reader: impl Read, limit: u64) -> io::Result<(u64, u64)> {
+    let mut reader = BufReader::new(reader.take(limit));
+    let mut hasher = Fnv64::default();
+    let mut len = 0u64;
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok((len, hasher.finish()));
+        }
+        hasher.write(&buf[..n]);
+        len += n as u64;
+    }
+}
+
+impl Cache {
+    pub fn new(dir: &Path, inputs: &[(&str, PathBuf)], config: &str) -> Result<Self, Error> {
+        fs::create_dir_all(dir)?;
+
+        let name = format!("{:016x}", fingerprint(inputs, config)?);
+        Ok(Self {
+            entry: dir.join(format!("{}.wl", name)),
+            // per process, so runs recording the same entry don't mix
+            partial: dir.join(format!("{}.wl.{}.partial", name, process::id())),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.entry
+    }
+
+    /// Whether there is an intact entry to replay. A damaged one is
+    /// removed, so the run records it afresh.
+    pub fn is_hit(&self) -> bool {
+        match self.check() {
+            Ok(intact) if intact => true,
+            Ok(_) => {
+                warn!("Discarding damaged cache entry {}", self.entry.display());
+                let _ = fs::remove_file(&self.entry);
+                false
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Whether the entry's words match its trailer.
+    fn check(&self) -> io::Result<bool> {
+        let mut file = File::open(&self.entry)?;
+        let size = file.metadata()?.len();
+        if size < TRAILER_LEN {
+            return Ok(false);
+        }
+        let found = digest(&mut file, size - TRAILER_LEN)?;
+        let mut trailer = [0u8; TRAILER_LEN as usize];
+        file.read_exact(&mut trailer)?;
+        let (len, hash) = trailer.split_at(8);
+        let expected = (
+            u64::from_le_bytes(len.try_into().unwrap()),
+            u64::from_le_bytes(hash.try_into().unwrap()),
+        );
+        Ok(found == expected)
+    }
+
+    /// Copy a cached entry into `writer`, returning the number of words.
+    pub fn replay<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let file = File::open(&self.entry)?;
+        let size = file.metadata()?.len();
+        let mut reader = BufReader::new(file.take(size.saturating_sub(TRAILER_LEN)));
+        let mut count = 0;
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            count += buf[..n].iter().filter(|b| **b == b'\n').count();
+            writer.write_all(&buf[..n])?;
+        }
+        Ok(count)
+    }
+
+    /// Open the partial entry that a fresh run is recorded into. It is
+    /// removed when the cache is dropped, unless committed first.
+    pub fn record(&self) -> io::Result<File> {
+        File::create(&self.partial)
+    }
+
+    /// Promote the partial entry once the run completed successfully.
+    pub fn commit(&self) -> io::Result<()> {
+        let (len, hash) = digest(File::open(&self.partial)?, u64::MAX)?;
+        let mut file = OpenOptions::new().append(true).open(&self.partial)?;
+        file.write_all(&len.to_le_bytes())?;
+        file.write_all(&hash.to_le_bytes())?;
+        file.sync_all()?;
+        fs::rename(&self.partial, &self.entry)
+    }
+}
+
+impl Drop for Cache {
+    fn drop(&mut self) {
+        // gone once committed; otherwise what a failed run recorded
+        let _ = fs::remove_file(&self.partial);
+    }
+}
+
+/// Writer that duplicates everything into a second sink.
+pub struct Tee<A: Write, B: Write> {
+    primary: A,
+    copy: B,
+}
+
+impl<A: Write, B: Write> Tee<A, B> {
+    pub fn new(primary: A, copy: B) -> Self {
+        Self { primary, copy }
+    }
+}
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.primary.write(buf)?;
+        self.copy.write_all(&buf[..n])?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.primary.flush()?;
+        self.copy.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structopt::StructOpt;
+
+    #[test]
+    fn test_fingerprint_roles() {
+        let dir = std::env::temp_dir().join(format!("wlinflate_test_cache_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (words, templates) = (dir.join("a.txt"), dir.join("t.txt"));
+        fs::write(&words, "foo\n").unwrap();
+        fs::write(&templates, "{WORD}-x\n").unwrap();
+        let (words, templates) = (words.to_str().unwrap(), templates.to_str().unwrap());
+        let key = |command: &[&str]| {
+            let args = crate::Args::from_iter(command);
+            fingerprint(&args.inputs(), &args.pipeline_repr()).unwrap()
+        };
+        let both = key(&["wlinflate", "-w", words, "-w", templates]);
+        let templated = key(&["wlinflate", "-w", words, "--templates", templates]);
+        assert!(both != templated);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fingerprint_stable() {
+        let dir =
+            std::env::temp_dir().join(format!("wlinflate_test_cache_key_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let words = dir.join("a.txt");
+        fs::write(&words, "foo\n").unwrap();
+        let inputs = [("wordlist", words.clone())];
+        let key = fingerprint(&inputs, "upper").unwrap();
+        assert_eq!(key, fingerprint(&inputs, "upper").unwrap());
+        assert!(key != fingerprint(&inputs, "lower").unwrap());
+        fs::write(&words, "bar\n").unwrap();
+        assert!(key != fingerprint(&inputs, "upper").unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir =
+            std::env::temp_dir().join(format!("wlinflate_test_cache_trip_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let words = dir.join("a.txt");
+        fs::write(&words, "foo\n").unwrap();
+        let inputs = [("wordlist", words)];
+
+        let cache = Cache::new(&dir, &inputs, "upper").unwrap();
+        assert!(!cache.is_hit());
+        cache.record().unwrap().write_all(b"FOO\nBAR\n").unwrap();
+        cache.commit().unwrap();
+        drop(cache);
+
+        let cache = Cache::new(&dir, &inputs, "upper").unwrap();
+        assert!(cache.is_hit());
+        let mut out = Vec::new();
+        assert_eq!(cache.replay(&mut out).unwrap(), 2);
+        assert_eq!(out, b"FOO\nBAR\n");
+        // another configuration is a different entry
+        assert!(!Cache::new(&dir, &inputs, "lower").unwrap().is_hit());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_damaged_entry() {
+        let dir = std::env::temp_dir().join(format!(
+            "wlinflate_test_cache_damaged_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let words = dir.join("a.txt");
+        fs::write(&words, "foo\n").unwrap();
+        let inputs = [("wordlist", words)];
+        let cache = Cache::new(&dir, &inputs, "upper").unwrap();
+
+        cache.record().unwrap().write_all(b"FOO\nBAR\n").unwrap();
+        cache.commit().unwrap();
+        let mut entry = fs::read(cache.path()).unwrap();
+        entry[0] = b'X';
+        fs::write(cache.path(), &entry).unwrap();
+        assert!(!cache.is_hit());
+        assert!(!cache.path().exists());
+
+        // cut short, as by a crash partway through copying
+        cache.record().unwrap().write_all(b"FOO\nBAR\n").unwrap();
+        cache.commit().unwrap();
+        let entry = fs::read(cache.path()).unwrap();
+        fs::write(cache.path(), &entry[..4]).unwrap();
+        assert!(!cache.is_hit());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_partial_removed() {
+        let dir = std::env::temp_dir().join(format!(
+            "wlinflate_test_cache_partial_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let words = dir.join("a.txt");
+        fs::write(&words, "foo\n").unwrap();
+        let cache = Cache::new(&dir, &[("wordlist", words)], "upper").unwrap();
+        cache.record().unwrap().write_all(b"FOO\n").unwrap();
+        drop(cache);
+        // only the input is left, no partial or entry
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::error::Error;
+
+/// Defaults read from `~/.config/wlinflate/config.toml`.
+///
+/// Every key is optional and only fills in options that were not given on
+/// the command line or through a `WLINFLATE_*` environment variable.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub prepend: Option<String>,
+    pub append: Option<String>,
+    pub extensions: Option<String>,
+    pub swap: Option<String>,
+    pub ext_preset: Vec<String>,
+    pub buffer_size: Option<usize>,
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// `~/.config/wlinflate`, or under `XDG_CONFIG_HOME` if it is set.
+pub fn dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("wlinflate"))
+}
+
+impl Config {
+    /// Load the config file, returning empty defaults if there is none.
+    ///
+    /// `WLINFLATE_CONFIG` overrides the location; a missing file at an
+    /// explicitly requested location is an error.
+    pub fn load() -> Result<Self, Error> {
+        if let Some(path) = env::var_os("WLINFLATE_CONFIG") {
+            return Self::from_file(Path::new(&path));
+        }
+        match Self::default_path() {
+            Some(path) if path.is_file() => Self::from_file(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn default_path() -> Option<PathBuf> {
+        Some(dir()?.join("config.toml"))
+    }
+
+    fn from_file(path: &Path) -> Result<Self, Error> {
+        let text = fs::read_to_string(path).map_err(|e| {
+            Error::InvalidConfig(format!("failed to read {}: {}", path.display(), e))
+        })?;
+        toml::from_str(&text)
+            .map_err(|e| Error::InvalidConfig(format!("{}: {}", path.display(), e)))
+    }
+}
@@ -0,0 +1,726 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{debug, info, warn};
+use serde::Serialize;
+use structopt::StructOpt;
+
+use crate::{
+    atomic::Staged, error::Error, human, plan, validate, worker, Args, DEFAULT_BUFFER_SIZE,
+    INTERRUPTED,
+};
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct CoordinateArgs {
+    #[structopt(
+        long = "listen",
+        default_value = "127.0.0.1:7878",
+        help = "address to accept workers on"
+    )]
+    listen: String,
+    #[structopt(
+        long = "chunk-size",
+        default_value = "1M",
+        parse(try_from_str = human::parse_count),
+        help = "candidates per chunk handed to a worker (e.g. 500K, 10M)"
+    )]
+    chunk_size: usize,
+    #[structopt(
+        long = "http",
+        help = "serve chunks over an HTTP API instead of to workers"
+    )]
+    http: bool,
+    #[structopt(
+        long = "lease-timeout",
+        requires = "http",
+        help = "seconds before an uncompleted HTTP lease is handed out again [default: 600]"
+    )]
+    lease_timeout: Option<u64>,
+}
+
+const DEFAULT_LEASE_TIMEOUT: u64 = 600;
+
+/// Most bytes a worker may forward in one `DATA` message.
+pub const MAX_DATA: usize = 16 << 20;
+/// Most bytes a worker may forward for one chunk, held until it completes.
+const MAX_CHUNK_DATA: usize = 1 << 30;
+/// How long an HTTP client may leave a read or write hanging.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest protocol line, HTTP request line or header accepted.
+const MAX_LINE: usize = 8 << 10;
+/// Most connections served at once, each on its own thread.
+const MAX_CONNECTIONS: usize = 256;
+
+/// Candidate ranges still to be handed out, and those out with workers.
+pub struct Chunks {
+    next: usize,
+    end: usize,
+    /// Ranges given back by workers that went away, handed out first.
+    returned: VecDeque<(usize, usize)>,
+    /// Ranges out with a worker, and when they were handed out.
+    leased: HashMap<(usize, usize), Instant>,
+    pub completed: usize,
+}
+
+impl Chunks {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self {
+            next: start,
+            end,
+            returned: VecDeque::new(),
+            leased: HashMap::new(),
+            completed: 0,
+        }
+    }
+
+    /// Lease the next range `start..end` of at most `size` candidates, if
+    /// any is left.
+    pub fn take(&mut self, size: usize) -> Option<(usize, usize)> {
+        let chunk = match self.returned.pop_front() {
+            Some((start, end)) if end - start > size => {
+                self.returned.push_front((start + size, end));
+                (start, start + size)
+            }
+            Some(chunk) => chunk,
+            None if self.next >= self.end => return None,
+            None => {
+                let start = self.next;
+                self.next = start.saturating_add(size).min(self.end);
+                (start, self.next)
+            }
+        };
+        self.leased.insert(chunk, Instant::now());
+        Some(chunk)
+    }
+
+    /// Mark a leased range as done, returning `false` if it was not leased.
+    pub fn complete(&mut self, chunk: (usize, usize)) -> bool {
+        if self.leased.remove(&chunk).is_none() {
+            return false;
+        }
+        self.completed += 1;
+        true
+    }
+
+    /// Put a leased range back to be handed out again.
+    pub fn give_back(&mut self, chunk: (usize, usize)) {
+        if self.leased.remove(&chunk).is_some() {
+            self.returned.push_back(chunk);
+        }
+    }
+
+    /// Give back every range leased for at least `timeout`, returning them.
+    pub fn expire(&mut self, timeout: Duration) -> Vec<(usize, usize)> {
+        let expired: Vec<_> = self
+            .leased
+            .iter()
+            .filter(|(_, leased_at)| leased_at.elapsed() >= timeout)
+            .map(|(&chunk, _)| chunk)
+            .collect();
+        for &chunk in &expired {
+            self.give_back(chunk);
+        }
+        expired
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.end && self.returned.is_empty() && self.leased.is_empty()
+    }
+}
+
+type Output = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// Read one line into `line` like [`BufRead::read_line`], failing rather
+/// than buffering without end when no newline comes within [`MAX_LINE`].
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> io::Result<usize> {
+    let read = reader.take(MAX_LINE as u64).read_line(line)?;
+    if read == MAX_LINE && !line.ends_with('\n') {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+    }
+    Ok(read)
+}
+
+/// A connection counted against [`MAX_CONNECTIONS`] until dropped.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    fn claim(active: &Arc<AtomicUsize>) -> Option<Self> {
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Self(active.clone()))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// What every connection needs to know about the run being coordinated.
+struct Session {
+    args: Args,
+    fingerprint: u64,
+    seed: u64,
+    chunk_size: usize,
+    lease_timeout: Duration,
+}
+
+/// Line protocol spoken with one worker:
+///
+/// ```text
+/// HELLO <fingerprint>     -> OK <seed> | ERR <reason>
+/// NEXT                    -> CHUNK <start> <end> | WAIT | DONE
+/// DATA <len>\n<bytes>     (forwarded candidates, no reply unless ERR)
+/// COMPLETE <start> <end>  -> OK
+/// ```
+///
+/// Forwarded candidates are held until their chunk completes, so a worker
+/// that leaves part way through writes nothing.
+fn converse(
+    stream: &TcpStream,
+    chunks: &Mutex<Chunks>,
+    output: &Output,
+    session: &Session,
+    held: &mut Option<(usize, usize)>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut greeted = false;
+    let mut pending = vec![];
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if read_line(&mut reader, &mut line)? == 0 {
+            return Ok(());
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["HELLO", theirs] => {
+                if u64::from_str_radix(theirs, 16).ok() != Some(session.fingerprint) {
+                    writeln!(writer, "ERR configuration differs from the coordinator")?;
+                    return Ok(());
+                }
+                greeted = true;
+                // workers share the coordinator's seed so random tokens
+                // come out the same in every chunk
+                writeln!(writer, "OK {:x}", session.seed)?;
+            }
+            _ if !greeted => {
+                writeln!(writer, "ERR expected HELLO")?;
+                return Ok(());
+            }
+            ["NEXT"] if held.is_none() => {
+                let mut chunks = chunks.lock().unwrap();
+                match chunks.take(session.chunk_size) {
+                    Some((start, end)) => {
+                        *held = Some((start, end));
+                        writeln!(writer, "CHUNK {} {}", start, end)?;
+                    }
+                    None if chunks.is_finished() => writeln!(writer, "DONE")?,
+                    None => writeln!(writer, "WAIT")?,
+                }
+            }
+            ["DATA", _] if held.is_none() => {
+                // the bytes that follow cannot be skipped safely
+                writeln!(writer, "ERR no chunk is held")?;
+                return Ok(());
+            }
+            ["DATA", len] => {
+                let len = match len.parse::<usize>() {
+                    Ok(len) if len <= MAX_DATA && pending.len() + len <= MAX_CHUNK_DATA => len,
+                    _ => {
+                        writeln!(writer, "ERR bad data length")?;
+                        return Ok(());
+                    }
+                };
+                if reader.by_ref().take(len as u64).read_to_end(&mut pending)? < len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            }
+            ["COMPLETE", start, end] => {
+                let chunk = (start.parse().unwrap_or(0), end.parse().unwrap_or(0));
+                if *held == Some(chunk) {
+                    output.lock().unwrap().write_all(&pending)?;
+                }
+                if *held == Some(chunk) && chunks.lock().unwrap().complete(chunk) {
+                    *held = None;
+                    pending.clear();
+                    writeln!(writer, "OK")?;
+                } else {
+                    writeln!(writer, "ERR chunk was not leased")?;
+                }
+            }
+            _ => writeln!(writer, "ERR unexpected message")?,
+        }
+    }
+}
+
+fn serve(stream: TcpStream, chunks: Arc<Mutex<Chunks>>, output: Output, session: Arc<Session>) {
+    let peer = stream
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_default();
+    debug!("Worker {} connected", peer);
+    let mut held = None;
+    if let Err(e) = converse(&stream, &chunks, &output, &session, &mut held) {
+        warn!("Worker {}: {}", peer, e);
+    }
+    if let Some((start, end)) = held {
+        warn!(
+            "Worker {} left during chunk {}..{}, it will be handed out again",
+            peer, start, end
+        );
+        chunks.lock().unwrap().give_back((start, end));
+    }
+    debug!("Worker {} disconnected", peer);
+}
+
+/// A chunk as handed out over HTTP, with everything a client needs to
+/// generate it itself.
+#[derive(Debug, Serialize)]
+struct Lease {
+    start_index: usize,
+    end_index: usize,
+    skip: usize,
+    limit: usize,
+    seed: u64,
+    fingerprint: String,
+    args: String,
+}
+
+fn respond(
+    mut stream: &TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// A response body sent with chunked transfer encoding, so a client can
+/// tell a complete body from one cut short.
+struct Chunked<W: Write>(W);
+
+impl<W: Write> Chunked<W> {
+    fn finish(mut self) -> io::Result<()> {
+        self.0.write_all(b"0\r\n\r\n")?;
+        self.0.flush()
+    }
+}
+
+impl<W: Write> Write for Chunked<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() {
+            write!(self.0, "{:x}\r\n", buf.len())?;
+            self.0.write_all(buf)?;
+            self.0.write_all(b"\r\n")?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Stream the candidates `start..end` as the response body.
+fn respond_body(mut stream: &TcpStream, args: &Args, start: usize, end: usize) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n"
+    )?;
+    let mut body = BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, Chunked(stream));
+    worker::generate(args, start, end, &mut body).map_err(|e| io::Error::other(e.to_string()))?;
+    body.into_inner().map_err(|e| e.into_error())?.finish()
+}
+
+fn respond_json<T: Serialize>(stream: &TcpStream, status: &str, value: &T) -> io::Result<()> {
+    let body = serde_json::to_vec(value).map_err(io::Error::from)?;
+    respond(stream, status, "application/json", &body)
+}
+
+/// Answer one HTTP request:
+///
+/// ```text
+/// GET  /chunk?size=10M[&format=body]  -> 200 lease as JSON, or the candidates
+///                                        400 bad size, or a body over --chunk-size
+///                                        503 all leased, retry later
+///                                        410 nothing left
+/// POST /complete?start=S&end=E        -> 200 | 409 not leased
+/// ```
+fn answer(stream: &TcpStream, chunks: &Mutex<Chunks>, session: &Session) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    read_line(&mut reader, &mut request)?;
+    // headers and any body carry nothing we need
+    let mut header = String::new();
+    while read_line(&mut reader, &mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params: HashMap<&str, &str> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .collect();
+    let error = |status: &str, message: &str| {
+        respond_json(stream, status, &serde_json::json!({ "error": message }))
+    };
+
+    match (method, path) {
+        ("GET", "/chunk") => {
+            let size = match params.get("size").map(|size| human::parse_count(size)) {
+                None => session.chunk_size,
+                Some(Ok(size)) if size > 0 => size,
+                Some(Ok(_)) => return error("400 Bad Request", "size must be at least 1"),
+                Some(Err(e)) => return error("400 Bad Request", &e),
+            };
+            let body = params.get("format") == Some(&"body");
+            // candidates are generated here, so the coordinator bounds them
+            if body && size > session.chunk_size {
+                return error("400 Bad Request", "size exceeds --chunk-size");
+            }
+            let lease = {
+                let mut chunks = chunks.lock().unwrap();
+                for (start, end) in chunks.expire(session.lease_timeout) {
+                    warn!(
+                        "Lease on chunk {}..{} expired, it will be handed out again",
+                        start, end
+                    );
+                }
+                match chunks.take(size) {
+                    Some(chunk) => chunk,
+                    None if chunks.is_finished() => return error("410 Gone", "keyspace finished"),
+                    None => return error("503 Service Unavailable", "every chunk is leased"),
+                }
+            };
+            let (start, end) = lease;
+            debug!("Leased chunk {}..{} over HTTP", start, end);
+
+            if body {
+                let sent = respond_body(stream, &session.args, start, end);
+                let mut chunks = chunks.lock().unwrap();
+                match sent {
+                    Ok(()) => chunks.complete(lease),
+                    Err(_) => {
+                        chunks.give_back(lease);
+                        false
+                    }
+                };
+                return sent;
+            }
+            respond_json(
+                stream,
+                "200 OK",
+                &Lease {
+                    start_index: start,
+                    end_index: end,
+                    skip: start,
+                    limit: end - start,
+                    seed: session.seed,
+                    fingerprint: format!("{:016x}", session.fingerprint),
+                    args: format!(
+                        "--start-index {} --end-index {} --seed {}",
+                        start, end, session.seed
+                    ),
+                },
+            )
+        }
+        ("POST", "/complete") => {
+            let chunk = match (
+                params.get("start").and_then(|start| start.parse().ok()),
+                params.get("end").and_then(|end| end.parse().ok()),
+            ) {
+                (Some(start), Some(end)) => (start, end),
+                _ => return error("400 Bad Request", "start and end are required"),
+            };
+            if chunks.lock().unwrap().complete(chunk) {
+                respond_json(stream, "200 OK", &serde_json::json!({ "completed": true }))
+            } else {
+                error("409 Conflict", "chunk was not leased")
+            }
+        }
+        _ => error("404 Not Found", "unknown endpoint"),
+    }
+}
+
+fn serve_http(stream: TcpStream, chunks: Arc<Mutex<Chunks>>, session: Arc<Session>) {
+    if let Err(e) = answer(&stream, &chunks, &session) {
+        warn!("HTTP client: {}", e);
+    }
+}
+
+/// Hand out chunks of the configured keyspace to `work` processes until
+/// every chunk is done, writing any candidates they forward.
+pub fn run(args: &Args, coordinate: &CoordinateArgs) -> Result<(), Error> {
+    validate(args)?;
+    worker::check_chunked(args)?;
+    if coordinate.chunk_size == 0 {
+        return Err(Error::InvalidConfig(
+            "--chunk-size must be at least 1".to_string(),
+        ));
+    }
+
+    let (start, end) = plan::range(args)?;
+    let session = Arc::new(Session {
+        args: args.clone(),
+        fingerprint: args.sequence_fingerprint()?,
+        seed: args.seed.unwrap_or_default(),
+        chunk_size: coordinate.chunk_size,
+        lease_timeout: Duration::from_secs(
+            coordinate.lease_timeout.unwrap_or(DEFAULT_LEASE_TIMEOUT),
+        ),
+    });
+
+    let listener = TcpListener::bind(&coordinate.listen)
+        .map_err(|e| Error::Network(coordinate.listen.clone(), e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| Error::Network(coordinate.listen.clone(), e))?;
+    info!(
+        "Coordinating candidates {}..{} in chunks of {} on {}{}",
+        start,
+        end,
+        coordinate.chunk_size,
+        coordinate.listen,
+        if coordinate.http { " over HTTP" } else { "" }
+    );
+
+    let buffer_size = args.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
+    let _lock = args.claim_output()?;
+    let mut staged = None;
+    let writer: Box<dyn Write + Send> = match &args.outfile {
+        None => Box::new(BufWriter::with_capacity(buffer_size, io::stdout())),
+        Some(path) if !args.no_atomic && Staged::supported(path) => {
+            let (output, file) = Staged::create(path)?;
+            staged = Some(output);
+            Box::new(BufWriter::with_capacity(buffer_size, file))
+        }
+        Some(path) => Box::new(BufWriter::with_capacity(buffer_size, File::create(path)?)),
+    };
+    let output: Output = Arc::new(Mutex::new(writer));
+    let chunks = Arc::new(Mutex::new(Chunks::new(start, end)));
+    let active = Arc::new(AtomicUsize::new(0));
+
+    while !chunks.lock().unwrap().is_finished() {
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            output.lock().unwrap().flush()?;
+            // chunks complete out of order, so this can't be resumed from
+            if let Some(partial) = staged.map(Staged::keep) {
+                warn!("Partial output kept in {}", partial.display());
+            }
+            return Err(Error::Interrupted);
+        }
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                stream
+                    .set_nonblocking(false)
+                    .map_err(|e| Error::Network(coordinate.listen.clone(), e))?;
+                if coordinate.http {
+                    stream
+                        .set_read_timeout(Some(HTTP_TIMEOUT))
+                        .and_then(|_| stream.set_write_timeout(Some(HTTP_TIMEOUT)))
+                        .map_err(|e| Error::Network(coordinate.listen.clone(), e))?;
+                }
+                let slot = match Slot::claim(&active) {
+                    Some(slot) => slot,
+                    None => {
+                        warn!(
+                            "Turning away {}: {} connections open",
+                            peer, MAX_CONNECTIONS
+                        );
+                        let _ = stream.set_write_timeout(Some(HTTP_TIMEOUT));
+                        let _ = if coordinate.http {
+                            respond_json(
+                                &stream,
+                                "503 Service Unavailable",
+                                &serde_json::json!({ "error": "too many connections" }),
+                            )
+                        } else {
+                            writeln!(&stream, "ERR too many connections")
+                        };
+                        continue;
+                    }
+                };
+                let (chunks, output, session) = (chunks.clone(), output.clone(), session.clone());
+                if coordinate.http {
+                    thread::spawn(move || {
+                        let _slot = slot;
+                        serve_http(stream, chunks, session)
+                    });
+                } else {
+                    thread::spawn(move || {
+                        let _slot = slot;
+                        serve(stream, chunks, output, session)
+                    });
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(100))
+            }
+            Err(e) => return Err(Error::Network(coordinate.listen.clone(), e)),
+        }
+    }
+
+    output.lock().unwrap().flush()?;
+    staged.map(Staged::commit).transpose()?;
+    info!("All {} chunks completed", chunks.lock().unwrap().completed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks() {
+        let mut chunks = Chunks::new(0, 25);
+        let first = chunks.take(10).unwrap();
+        let second = chunks.take(10).unwrap();
+        assert!(first == (0, 10) && second == (10, 20));
+
+        chunks.give_back(first);
+        assert!(chunks.take(10) == Some((0, 10)));
+        assert!(chunks.take(10) == Some((20, 25)));
+        assert!(chunks.take(10).is_none() && !chunks.is_finished());
+
+        for chunk in [(0, 10), (10, 20), (20, 25)] {
+            assert!(chunks.complete(chunk));
+        }
+        assert!(!chunks.complete((0, 10)));
+        assert!(chunks.is_finished());
+    }
+
+    #[test]
+    fn test_chunk_leases() {
+        let mut chunks = Chunks::new(0, 100);
+        assert!(chunks.take(40) == Some((0, 40)));
+        assert!(chunks.expire(Duration::from_secs(60)).is_empty());
+        assert!(chunks.expire(Duration::ZERO) == vec![(0, 40)]);
+
+        // a smaller request splits a returned range
+        assert!(chunks.take(15) == Some((0, 15)));
+        assert!(chunks.take(100) == Some((15, 40)));
+        assert!(chunks.take(100) == Some((40, 100)));
+        assert!(!chunks.complete((0, 40)));
+    }
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Play `messages` to the coordinator, returning its replies.
+    fn play(chunks: &Arc<Mutex<Chunks>>, output: &Output, messages: &[u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let session = Arc::new(Session {
+            args: Args::from_iter(["wlinflate"]),
+            fingerprint: 1,
+            seed: 0,
+            chunk_size: 10,
+            lease_timeout: Duration::ZERO,
+        });
+        let (chunks, output) = (chunks.clone(), output.clone());
+        let server = thread::spawn(move || serve(stream, chunks, output, session));
+        client.write_all(messages).unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut replies = String::new();
+        client.read_to_string(&mut replies).unwrap();
+        server.join().unwrap();
+        replies
+    }
+
+    #[test]
+    fn test_forwarded_data() {
+        let chunks = Arc::new(Mutex::new(Chunks::new(0, 20)));
+        let shared = Shared::default();
+        let output: Output = Arc::new(Mutex::new(Box::new(shared.clone())));
+
+        let replies = play(&chunks, &output, b"HELLO 1\nDATA 2\na\n");
+        assert!(replies == "OK 0\nERR no chunk is held\n");
+        let replies = play(&chunks, &output, b"HELLO 1\nNEXT\nDATA x\n");
+        assert!(replies.ends_with("ERR bad data length\n"));
+        let oversized = format!("HELLO 1\nNEXT\nDATA {}\n", MAX_DATA + 1);
+        let replies = play(&chunks, &output, oversized.as_bytes());
+        assert!(replies.ends_with("ERR bad data length\n"));
+
+        // a worker leaving before COMPLETE writes nothing
+        play(&chunks, &output, b"HELLO 1\nNEXT\nDATA 2\na\n");
+        assert!(shared.0.lock().unwrap().is_empty());
+
+        let replies = play(
+            &chunks,
+            &output,
+            b"HELLO 1\nNEXT\nDATA 2\na\nDATA 2\nb\nCOMPLETE 0 10\n",
+        );
+        assert!(replies == "OK 0\nCHUNK 0 10\nOK\n");
+        assert!(*shared.0.lock().unwrap() == b"a\nb\n");
+    }
+
+    #[test]
+    fn test_long_line() {
+        let mut reader = io::Cursor::new(format!("{}\nNEXT\n", "x".repeat(MAX_LINE * 2)));
+        let err = read_line(&mut reader, &mut String::new()).unwrap_err();
+        assert!(err.kind() == io::ErrorKind::InvalidData);
+        // the cut is at the limit, not after reading the whole line
+        assert!(reader.position() == MAX_LINE as u64);
+
+        let mut reader = io::Cursor::new(format!("{}\n", "y".repeat(MAX_LINE - 1)));
+        assert!(read_line(&mut reader, &mut String::new()).unwrap() == MAX_LINE);
+    }
+
+    #[test]
+    fn test_connection_slots() {
+        let active = Arc::new(AtomicUsize::new(0));
+        let slots: Vec<Slot> = (0..MAX_CONNECTIONS)
+            .map(|_| Slot::claim(&active).unwrap())
+            .collect();
+        assert!(Slot::claim(&active).is_none());
+        drop(slots);
+        assert!(active.load(Ordering::SeqCst) == 0 && Slot::claim(&active).is_some());
+    }
+
+    #[test]
+    fn test_chunked() {
+        let mut body = Chunked(vec![]);
+        body.write_all(b"admin\n").unwrap();
+        body.write_all(b"").unwrap();
+        body.write_all(b"x\n").unwrap();
+        let Chunked(sent) = &body;
+        assert!(sent == b"6\r\nadmin\n\r\n2\r\nx\n\r\n");
+        body.finish().unwrap();
+    }
+}
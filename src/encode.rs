@@ -0,0 +1,146 @@
+//! Encoders applied to candidates, alone or chained, e.g. URL encoding
+//! then base64.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Encoder names accepted by [`Encoder::from_str`].
+pub const ENCODERS: &[&str] = &["url", "base64", "hex", "html"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoder {
+    /// Percent-encode everything but unreserved characters (RFC 3986).
+    Url,
+    /// Standard base64 with padding.
+    Base64,
+    /// Lower case hex of the UTF-8 bytes.
+    Hex,
+    /// Escape `&`, `<`, `>`, `"` and `'` as HTML entities.
+    Html,
+}
+
+/// Which part of a candidate an encoder chain applies to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodeScope {
+    /// Each base word alone, before affixes are added, so prepends,
+    /// appends and extensions stay as given.
+    Each,
+    /// The finished candidate, affixes included.
+    #[default]
+    Final,
+}
+
+impl Encoder {
+    pub fn encode(&self, word: &str) -> String {
+        match self {
+            Encoder::Url => {
+                let mut out = String::with_capacity(word.len());
+                for b in word.bytes() {
+                    if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                        out.push(b as char);
+                    } else {
+                        out.push_str(&format!("%{:02X}", b));
+                    }
+                }
+                out
+            }
+            Encoder::Base64 => base64(word.as_bytes()),
+            Encoder::Hex => word.bytes().map(|b| format!("{:02x}", b)).collect(),
+            Encoder::Html => {
+                let mut out = String::with_capacity(word.len());
+                for c in word.chars() {
+                    match c {
+                        '&' => out.push_str("&amp;"),
+                        '<' => out.push_str("&lt;"),
+                        '>' => out.push_str("&gt;"),
+                        '"' => out.push_str("&quot;"),
+                        '\'' => out.push_str("&#39;"),
+                        c => out.push(c),
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+/// `word` put through every encoder of `chain`, left to right.
+pub fn encode_chain(chain: &[Encoder], word: &str) -> String {
+    chain
+        .iter()
+        .fold(word.to_string(), |word, encoder| encoder.encode(&word))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let n = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= group.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+impl FromStr for Encoder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "url" => Ok(Encoder::Url),
+            "base64" | "b64" => Ok(Encoder::Base64),
+            "hex" => Ok(Encoder::Hex),
+            "html" => Ok(Encoder::Html),
+            other => Err(format!(
+                "unknown encoder '{}', expected one of {}",
+                other,
+                ENCODERS.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Encoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Encoder::Url => "url",
+            Encoder::Base64 => "base64",
+            Encoder::Hex => "hex",
+            Encoder::Html => "html",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoders() {
+        assert!(Encoder::Url.encode("a b/é~") == "a%20b%2F%C3%A9~");
+        for (plain, encoded) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v")] {
+            assert!(Encoder::Base64.encode(plain) == encoded);
+        }
+        assert!(Encoder::Hex.encode("A\n") == "410a");
+        assert!(Encoder::Html.encode("<a href='x'>&") == "&lt;a href=&#39;x&#39;&gt;&amp;");
+
+        let chain: Vec<Encoder> = "url,base64"
+            .split(',')
+            .map(|name| name.parse().unwrap())
+            .collect();
+        assert!(encode_chain(&chain, "a b") == "YSUyMGI=");
+        assert!("rot13".parse::<Encoder>().is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use std::{fmt, io, path::PathBuf};
+
+use wlinflate::WlinflateError;
+
+/// Failures surfaced by the CLI, each mapped to a distinct exit code so
+/// wrapper scripts can tell them apart.
+#[derive(Debug)]
+pub enum Error {
+    InputNotFound(PathBuf),
+    Input(PathBuf, io::Error),
+    InvalidConfig(String),
+    Output(io::Error),
+    Interrupted,
+    KeyspaceTooLarge,
+    /// Talking to a coordinator or worker failed.
+    Network(String, io::Error),
+    /// `--verify` found the output differs from what was expected.
+    Verify(String),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::InvalidConfig(_) => 2,
+            Error::InputNotFound(_) | Error::Input(..) => 3,
+            Error::Output(_) => 4,
+            Error::KeyspaceTooLarge => 5,
+            Error::Network(..) => 6,
+            Error::Verify(_) => 7,
+            Error::Interrupted => 130,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InputNotFound(path) => write!(f, "Wordlist not found: {}", path.display()),
+            Error::Input(path, e) => write!(f, "Failed to read {}: {}", path.display(), e),
+            Error::InvalidConfig(msg) => write!(f, "Invalid configuration: {}", msg),
+            Error::Output(e) => write!(f, "Failed to write output: {}", e),
+            Error::Interrupted => write!(f, "Interrupted"),
+            Error::KeyspaceTooLarge => write!(f, "Keyspace is too large"),
+            Error::Network(peer, e) => write!(f, "Connection to {} failed: {}", peer, e),
+            Error::Verify(msg) => write!(f, "Verification failed: {}", msg),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Output(e)
+    }
+}
+
+impl From<WlinflateError> for Error {
+    fn from(e: WlinflateError) -> Self {
+        match e {
+            WlinflateError::MissingFile(path) => Error::InputNotFound(path),
+            WlinflateError::PermissionDenied(path) => {
+                Error::Input(path, io::Error::from(io::ErrorKind::PermissionDenied))
+            }
+            WlinflateError::Io(path, e) => Error::Input(path, e),
+            e => Error::InvalidConfig(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Candidates filtered by an external command, such as `grep` or a user's
+//! own script.
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread::{self, JoinHandle},
+};
+
+use log::debug;
+use wlinflate::Origin;
+
+use crate::error::Error;
+
+/// A long-lived child process candidates are streamed to, one per line,
+/// keeping those it prints back.
+///
+/// The command must print the candidates it keeps unchanged and in the
+/// order it was given them, as `grep` does. Its input is buffered, so it
+/// sees candidates in batches rather than one write each, and its output
+/// is read on a separate thread so neither side waits on the other.
+/// Candidates are held until the output moves past them, which takes
+/// memory for each one a command drops before it next prints.
+pub struct External {
+    command: String,
+    child: Child,
+    /// `None` once the command stopped reading.
+    stdin: Option<BufWriter<ChildStdin>>,
+    kept: Receiver<io::Result<String>>,
+    reader: Option<JoinHandle<()>>,
+    /// Candidates sent and not yet printed back or passed over.
+    pending: VecDeque<(String, Origin)>,
+}
+
+impl External {
+    /// Start `command` through the shell, with the run's seed in
+    /// `WLINFLATE_SEED` for anything random it does.
+    pub fn spawn(command: &str, buffer_size: usize, seed: u64) -> Result<Self, Error> {
+        let mut child = shell(command)
+            .env("WLINFLATE_SEED", seed.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::InvalidConfig(format!("failed to start '{}': {}", command, e)))?;
+        let stdin = child
+            .stdin
+            .take()
+            .map(|s| BufWriter::with_capacity(buffer_size, s));
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, kept) = mpsc::channel();
+        let reader = thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let failed = line.is_err();
+                if sender.send(line).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            command: command.to_string(),
+            child,
+            stdin,
+            kept,
+            reader: Some(reader),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Pass `word` to the command, returning the candidates it kept so far.
+    pub fn send(&mut self, word: &str, origin: &Origin) -> Result<Vec<(String, Origin)>, Error> {
+        if let Some(stdin) = &mut self.stdin {
+            match stdin
+                .write_all(word.as_bytes())
+                .and_then(|_| stdin.write_all(b"\n"))
+            {
+                Ok(()) => self.pending.push_back((word.to_string(), *origin)),
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                    debug!("'{}' stopped reading candidates", self.command);
+                    self.stdin = None;
+                }
+                Err(e) => return Err(self.failed(e)),
+            }
+        }
+        self.kept()
+    }
+
+    /// Hand the command the candidates buffered for it, returning those
+    /// it kept so far, for when no more are coming for a while.
+    pub fn flush(&mut self) -> Result<Vec<(String, Origin)>, Error> {
+        if let Some(stdin) = &mut self.stdin {
+            match stdin.flush() {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => self.stdin = None,
+                Err(e) => return Err(self.failed(e)),
+            }
+        }
+        self.kept()
+    }
+
+    /// Candidates the command printed back so far.
+    fn kept(&mut self) -> Result<Vec<(String, Origin)>, Error> {
+        let mut kept = vec![];
+        while let Ok(line) = self.kept.try_recv() {
+            kept.push(self.matched(line)?);
+        }
+        Ok(kept)
+    }
+
+    /// Close the command's input and return the candidates it still keeps
+    /// once it exits.
+    pub fn finish(mut self) -> Result<Vec<(String, Origin)>, Error> {
+        if let Some(mut stdin) = self.stdin.take() {
+            match stdin.flush() {
+                Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(self.failed(e)),
+                _ => {}
+            }
+        }
+        let mut kept = vec![];
+        while let Ok(line) = self.kept.recv() {
+            kept.push(self.matched(line)?);
+        }
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+        let status = self.child.wait().map_err(|e| self.failed(e))?;
+        debug!("'{}' exited with {}", self.command, status);
+        // the shell's codes for a command it couldn't find or run
+        if let Some(code @ (126 | 127)) = status.code() {
+            return Err(Error::InvalidConfig(format!(
+                "failed to run '{}' (exit status {})",
+                self.command, code
+            )));
+        }
+        Ok(kept)
+    }
+
+    /// The pending candidate `line` printed back, passing over those
+    /// before it.
+    fn matched(&mut self, line: io::Result<String>) -> Result<(String, Origin), Error> {
+        let line = line.map_err(|e| self.failed(e))?;
+        while let Some((word, origin)) = self.pending.pop_front() {
+            if word == line {
+                return Ok((word, origin));
+            }
+        }
+        Err(Error::InvalidConfig(format!(
+            "'{}' printed '{}', which it was not given, or not in order",
+            self.command, line
+        )))
+    }
+
+    fn failed(&self, e: io::Error) -> Error {
+        Error::Output(io::Error::new(
+            e.kind(),
+            format!("filter command '{}': {}", self.command, e),
+        ))
+    }
+}
+
+impl Drop for External {
+    fn drop(&mut self) {
+        // left running only if the run stopped early
+        if self.reader.is_some() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external() {
+        let mut external = External::spawn("grep a", 8, 0).unwrap();
+        let mut kept = vec![];
+        for (i, word) in ["abc", "xyz", "cab", "def", "a"].iter().enumerate() {
+            let origin = Origin {
+                append: Some(i),
+                ..Default::default()
+            };
+            kept.extend(external.send(word, &origin).unwrap());
+        }
+        kept.extend(external.finish().unwrap());
+        let words: Vec<(&str, Option<usize>)> =
+            kept.iter().map(|(w, o)| (w.as_str(), o.append)).collect();
+        assert!(words == [("abc", Some(0)), ("cab", Some(2)), ("a", Some(4))]);
+
+        let mut external = External::spawn("echo nope", 8, 0).unwrap();
+        assert!(external.send("abc", &Origin::default()).is_err() || external.finish().is_err());
+        let mut external = External::spawn("head -n 1", 8, 0).unwrap();
+        for _ in 0..100_000 {
+            external.send("abc", &Origin::default()).unwrap();
+        }
+        assert!(external.finish().unwrap().len() <= 1);
+
+        let mut external = External::spawn("grep \"^$WLINFLATE_SEED$\"", 8, 42).unwrap();
+        let mut kept = external.send("41", &Origin::default()).unwrap();
+        kept.extend(external.send("42", &Origin::default()).unwrap());
+        kept.extend(external.finish().unwrap());
+        assert!(kept.len() == 1 && kept[0].0 == "42");
+    }
+}
@@ -0,0 +1,134 @@
+//! C interface to the expansion engine, built with the `ffi` feature.
+//!
+//! A wordlist is created from a [`WordlistSpec`] in JSON, read one
+//! candidate at a time and freed when done. `include/wlinflate.h` declares
+//! these functions and is regenerated with `cbindgen --config cbindgen.toml
+//! --output include/wlinflate.h`.
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+use crate::{spec::WordlistSpec, Wordlist};
+
+/// Opaque handle owning a wordlist and the last candidate returned.
+pub struct WlinflateWordlist {
+    wordlist: Wordlist,
+    current: CString,
+}
+
+/// Create a wordlist from a JSON spec such as
+/// `{"wordlists": ["words.txt"], "prepend": ["dev-"]}`.
+///
+/// Returns null if the spec is invalid or a wordlist cannot be read.
+///
+/// # Safety
+///
+/// `spec_json` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn wlinflate_new(spec_json: *const c_char) -> *mut WlinflateWordlist {
+    if spec_json.is_null() {
+        return ptr::null_mut();
+    }
+    let spec = match CStr::from_ptr(spec_json)
+        .to_str()
+        .ok()
+        .and_then(|json| serde_json::from_str::<WordlistSpec>(json).ok())
+    {
+        Some(spec) => spec,
+        None => return ptr::null_mut(),
+    };
+    match spec.build() {
+        Ok(wordlist) => Box::into_raw(Box::new(WlinflateWordlist {
+            wordlist,
+            current: CString::default(),
+        })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// The next candidate, or null at the end.
+///
+/// The string is owned by the handle and stays valid until the next call
+/// on it. Candidates containing a NUL byte are skipped.
+///
+/// # Safety
+///
+/// `wl` must come from [`wlinflate_new`] and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn wlinflate_next(wl: *mut WlinflateWordlist) -> *const c_char {
+    let wl = match wl.as_mut() {
+        Some(wl) => wl,
+        None => return ptr::null(),
+    };
+    for word in wl.wordlist.by_ref() {
+        if let Ok(word) = CString::new(word) {
+            wl.current = word;
+            return wl.current.as_ptr();
+        }
+    }
+    ptr::null()
+}
+
+/// Number of candidates, saturating at `UINT64_MAX`.
+///
+/// # Safety
+///
+/// `wl` must come from [`wlinflate_new`] and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn wlinflate_keyspace(wl: *const WlinflateWordlist) -> u64 {
+    match wl.as_ref() {
+        Some(wl) => u64::try_from(wl.wordlist.keyspace()).unwrap_or(u64::MAX),
+        None => 0,
+    }
+}
+
+/// Release a wordlist. Null is ignored.
+///
+/// # Safety
+///
+/// `wl` must come from [`wlinflate_new`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn wlinflate_free(wl: *mut WlinflateWordlist) {
+    if !wl.is_null() {
+        drop(Box::from_raw(wl));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi_roundtrip() {
+        let path =
+            &std::env::temp_dir().join(format!("wlinflate_test_ffi_{}.txt", std::process::id()));
+        std::fs::write(path, "admin\nlogin\n").unwrap();
+        let spec = CString::new(format!(
+            r#"{{"wordlists": ["{}"], "extensions": [".bak"]}}"#,
+            path.display()
+        ))
+        .unwrap();
+
+        unsafe {
+            let wl = wlinflate_new(spec.as_ptr());
+            assert!(!wl.is_null());
+            assert!(wlinflate_keyspace(wl) == 4);
+            let mut words = vec![];
+            loop {
+                let word = wlinflate_next(wl);
+                if word.is_null() {
+                    break;
+                }
+                words.push(CStr::from_ptr(word).to_str().unwrap().to_string());
+            }
+            wlinflate_free(wl);
+            assert!(words == ["admin", "admin.bak", "login", "login.bak"]);
+
+            let bad = CString::new("{\"wordlists\": 3}").unwrap();
+            assert!(wlinflate_new(bad.as_ptr()).is_null());
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+//! Stateful filters applied to the candidate stream.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    io,
+};
+
+use crate::spill::DiskSet;
+
+/// Memory a remembered word is assumed to take beyond its text.
+const ENTRY_OVERHEAD: usize = 56;
+
+/// Drops candidates that were already emitted.
+///
+/// With `fold_case`, candidates differing only in case count as duplicates
+/// and the first spelling seen is kept.
+///
+/// Given a memory limit, [`Dedup::admit`] stops growing the set once it
+/// reaches the limit and remembers later words in temporary files
+/// instead, so words are still dropped or kept as they arrive.
+pub struct Dedup {
+    seen: HashSet<String>,
+    fold_case: bool,
+    /// Bytes the set may take, and roughly how many it does.
+    limit: Option<usize>,
+    used: usize,
+    spilled: Option<DiskSet>,
+}
+
+impl Dedup {
+    pub fn new(fold_case: bool) -> Self {
+        Self {
+            seen: HashSet::new(),
+            fold_case,
+            limit: None,
+            used: 0,
+            spilled: None,
+        }
+    }
+
+    /// Keep the words remembered by [`Dedup::admit`] to about `bytes` of
+    /// memory, spilling to the temporary directory beyond that.
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.limit = Some(bytes);
+        self
+    }
+
+    fn key<'a>(&self, word: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.fold_case {
+            word.to_lowercase().into()
+        } else {
+            word.into()
+        }
+    }
+
+    /// Record `word`, returning `false` if it is a duplicate. The memory
+    /// limit does not apply.
+    pub fn insert(&mut self, word: &str) -> bool {
+        if self.seen.contains(self.key(word).as_ref()) {
+            return false;
+        }
+        self.used += word.len() + ENTRY_OVERHEAD;
+        self.seen.insert(self.key(word).into_owned())
+    }
+
+    /// Record `word` within the memory limit, returning `false` if it is a
+    /// duplicate.
+    pub fn admit(&mut self, word: &str) -> io::Result<bool> {
+        let key = self.key(word);
+        if self.seen.contains(key.as_ref()) {
+            return Ok(false);
+        }
+        let fits = match self.limit {
+            Some(limit) => {
+                self.spilled.is_none() && self.used + word.len() + ENTRY_OVERHEAD <= limit
+            }
+            None => true,
+        };
+        if fits {
+            return Ok(self.insert(word));
+        }
+        let spilled = match &mut self.spilled {
+            Some(spilled) => spilled,
+            spilled => spilled.insert(DiskSet::new()?),
+        };
+        spilled.insert(&key)
+    }
+
+    /// Whether words past the memory limit are being remembered on disk.
+    pub fn is_spilled(&self) -> bool {
+        self.spilled.is_some()
+    }
+}
+
+/// Drops candidates repeated within the last `size` emitted ones.
+///
+/// Memory is bounded by the window, so this catches the common case of
+/// near-adjacent duplicates without remembering the whole output.
+pub struct WindowDedup {
+    recent: VecDeque<String>,
+    members: HashSet<String>,
+    size: usize,
+}
+
+impl WindowDedup {
+    pub fn new(size: usize) -> Self {
+        Self {
+            recent: VecDeque::with_capacity(size),
+            members: HashSet::with_capacity(size),
+            size,
+        }
+    }
+
+    /// Record `word`, returning `false` if it is inside the window.
+    pub fn insert(&mut self, word: &str) -> bool {
+        if self.size == 0 {
+            return true;
+        }
+        if self.members.contains(word) {
+            return false;
+        }
+        if self.recent.len() == self.size {
+            if let Some(oldest) = self.recent.pop_front() {
+                self.members.remove(&oldest);
+            }
+        }
+        self.recent.push_back(word.to_string());
+        self.members.insert(word.to_string());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_fold_case() {
+        let mut exact = Dedup::new(false);
+        assert!(exact.insert("Admin"));
+        assert!(exact.insert("admin"));
+        assert!(!exact.insert("admin"));
+
+        let mut folded = Dedup::new(true);
+        assert!(folded.insert("Admin"));
+        assert!(!folded.insert("admin"));
+        assert!(!folded.insert("ADMIN"));
+        assert!(folded.insert("root"));
+    }
+
+    #[test]
+    fn test_dedup_spill() {
+        // room for the first three words in the set
+        let limit = 3 * ENTRY_OVERHEAD + 13;
+        let mut dedup = Dedup::new(true).with_memory_limit(limit);
+        let words = [
+            "Admin", "root", "test", "ROOT", "guest", "Guest", "zz", "admin", "guest\n",
+        ];
+        let admitted: Vec<bool> = words
+            .iter()
+            .map(|word| dedup.admit(word).unwrap())
+            .collect();
+        assert!(admitted == [true, true, true, false, true, false, true, false, true]);
+        assert!(dedup.is_spilled());
+    }
+
+    #[test]
+    fn test_window_dedup() {
+        let mut window = WindowDedup::new(2);
+        assert!(window.insert("a"));
+        assert!(window.insert("b"));
+        assert!(!window.insert("a"));
+        assert!(window.insert("c"));
+        // "a" has slid out of the window
+        assert!(window.insert("a"));
+        assert!(!window.insert("c"));
+    }
+}
@@ -0,0 +1,133 @@
+//! Formats wordlist files can be read in, picking the base word out of
+//! each line.
+
+use serde::{Deserialize, Serialize};
+
+/// How each line of a wordlist file becomes a base word.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InputFormat {
+    /// Every line is a word.
+    #[default]
+    Lines,
+    /// Delimited rows such as CSV or TSV exports, taking the word from
+    /// `column` (1-based). Quoted fields may hold the delimiter and `""`
+    /// for a quote, but not line breaks. With `header`, the first row of
+    /// each file is skipped.
+    Delimited {
+        delimiter: char,
+        column: usize,
+        header: bool,
+    },
+    /// One JSON object per line, such as subfinder or amass output, taking
+    /// the word from the string at `field`. Nested fields are reached with
+    /// a dotted path, e.g. `host.name`. Lines that aren't JSON, or where
+    /// the field is missing or not a string, are skipped.
+    Jsonl { field: String },
+}
+
+impl InputFormat {
+    /// The base word in line `index` (0-based) of a file, or `None` to skip
+    /// the line.
+    pub fn word(&self, line: &str, index: usize) -> Option<String> {
+        match self {
+            InputFormat::Lines => Some(line.to_string()),
+            InputFormat::Delimited {
+                delimiter,
+                column,
+                header,
+            } => {
+                if *header && index == 0 {
+                    return None;
+                }
+                field(line, *delimiter, column.checked_sub(1)?)
+            }
+            InputFormat::Jsonl { field } => {
+                let value: serde_json::Value = serde_json::from_str(line).ok()?;
+                let value = field
+                    .split('.')
+                    .try_fold(&value, |value, key| value.get(key))?;
+                value.as_str().map(str::to_string)
+            }
+        }
+    }
+}
+
+/// Field `n` (0-based) of a delimited row, unquoted.
+fn field(line: &str, delimiter: char, n: usize) -> Option<String> {
+    let mut chars = line.chars().peekable();
+    for i in 0..=n {
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        value.push('"');
+                    }
+                    '"' => break,
+                    c => value.push(c),
+                }
+            }
+        }
+        // anything after a closing quote is kept as-is
+        let mut more = false;
+        for c in chars.by_ref() {
+            if c == delimiter {
+                more = true;
+                break;
+            }
+            value.push(c);
+        }
+        if i == n {
+            return Some(value);
+        }
+        if !more {
+            break;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delimited() {
+        let csv = InputFormat::Delimited {
+            delimiter: ',',
+            column: 2,
+            header: true,
+        };
+        assert!(csv.word("host,name", 0).is_none());
+        assert!(csv.word("a,admin,x", 1).as_deref() == Some("admin"));
+        assert!(csv.word("a,\"b,\"\"c\"\"\",d", 1).as_deref() == Some("b,\"c\""));
+        assert!(csv.word("a,", 1).as_deref() == Some(""));
+        assert!(csv.word("a", 1).is_none());
+
+        let tsv = InputFormat::Delimited {
+            delimiter: '\t',
+            column: 1,
+            header: false,
+        };
+        assert!(tsv.word("x\ty", 0).as_deref() == Some("x"));
+        assert!(InputFormat::Lines.word("a,b", 0).as_deref() == Some("a,b"));
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let jsonl = InputFormat::Jsonl {
+            field: "host.name".to_string(),
+        };
+        let word = |line| jsonl.word(line, 0);
+        assert!(
+            word(r#"{"host":{"name":"api.example.com"},"ip":"1.2.3.4"}"#).as_deref()
+                == Some("api.example.com")
+        );
+        assert!(word(r#"{"host":{"name":7}}"#).is_none());
+        assert!(word(r#"{"name":"x"}"#).is_none());
+        assert!(word("not json").is_none());
+    }
+}
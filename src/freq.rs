@@ -0,0 +1,75 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+
+use log::info;
+use structopt::StructOpt;
+
+use crate::{error::Error, human, Args};
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct FreqArgs {
+    #[structopt(
+        parse(from_os_str),
+        required = true,
+        help = "wordlists to merge and count"
+    )]
+    lists: Vec<PathBuf>,
+}
+
+/// Merge `lists`, counting how often each word appears across them, and
+/// write `word<TAB>count` lines, most frequent first, for `--weighted`.
+///
+/// Words with equal counts keep the order they were first seen in. Every
+/// distinct word is held in memory.
+pub fn run(args: &Args, freq: &FreqArgs) -> Result<(), Error> {
+    for path in &freq.lists {
+        if !path.is_file() {
+            return Err(Error::InputNotFound(path.clone()));
+        }
+        if let Some(outfile) = &args.outfile {
+            if outfile.canonicalize().ok() == path.canonicalize().ok() {
+                return Err(Error::InvalidConfig(
+                    "output file would overwrite an input wordlist".to_string(),
+                ));
+            }
+        }
+    }
+    let _lock = args.claim_output()?;
+
+    // word -> (count, first seen)
+    let mut counts: HashMap<String, (u64, usize)> = HashMap::new();
+    for path in &freq.lists {
+        let file = File::open(path).map_err(|e| Error::Input(path.clone(), e))?;
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| Error::Input(path.clone(), e))?;
+            let word = line.strip_suffix('\r').unwrap_or(&line);
+            if word.is_empty() {
+                continue;
+            }
+            let seen = counts.len();
+            match counts.get_mut(word) {
+                Some((count, _)) => *count += 1,
+                None => {
+                    counts.insert(word.to_string(), (1, seen));
+                }
+            }
+        }
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by_key(|(_, (count, seen))| (std::cmp::Reverse(*count), *seen));
+
+    let mut writer: Box<dyn Write> = match &args.outfile {
+        Some(outfile) => Box::new(BufWriter::new(File::create(outfile)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    for (word, (count, _)) in &counts {
+        writeln!(writer, "{}\t{}", word, count)?;
+    }
+    writer.flush()?;
+    info!("Distinct Words: {}", human::count(counts.len() as u128));
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+
+use log::info;
+use structopt::StructOpt;
+use wlinflate::pot;
+
+use crate::{error::Error, human, Args};
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct FromPotArgs {
+    #[structopt(parse(from_os_str), help = "hashcat or john potfile to read")]
+    potfile: PathBuf,
+}
+
+/// Write each distinct cracked plaintext of a potfile once, in the order
+/// first cracked, as a base wordlist for another run.
+pub fn run(args: &Args, from_pot: &FromPotArgs) -> Result<(), Error> {
+    let path = &from_pot.potfile;
+    if !path.is_file() {
+        return Err(Error::InputNotFound(path.clone()));
+    }
+    if let Some(outfile) = &args.outfile {
+        if outfile.canonicalize().ok() == path.canonicalize().ok() {
+            return Err(Error::InvalidConfig(
+                "output file would overwrite the potfile".to_string(),
+            ));
+        }
+    }
+    let _lock = args.claim_output()?;
+    let file = File::open(path).map_err(|e| Error::Input(path.clone(), e))?;
+    let mut writer: Box<dyn Write> = match &args.outfile {
+        Some(outfile) => Box::new(BufWriter::new(File::create(outfile)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let mut seen = HashSet::new();
+    for plain in pot::plaintexts(BufReader::new(file)) {
+        let plain = plain.map_err(|e| Error::Input(path.clone(), e))?;
+        if plain.is_empty() || seen.contains(&plain) {
+            continue;
+        }
+        writer.write_all(plain.as_bytes())?;
+        writer.write_all(b"\n")?;
+        seen.insert(plain);
+    }
+    writer.flush()?;
+    info!("Cracked Plaintexts: {}", human::count(seen.len() as u128));
+    Ok(())
+}
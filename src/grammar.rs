@@ -0,0 +1,254 @@
+//! A small BNF-like grammar describing how candidates are composed, for
+//! naming conventions too structured for templates.
+//!
+//! Each rule names a non-terminal and lists its alternatives, separated by
+//! `|`; an alternative is a sequence of items, put together in order:
+//!
+//! ```text
+//! # host.gram
+//! <host> ::= <env> "-" <role> <num> | <role> <num>
+//! <env>  ::= "dev" | "stg" | "prod"
+//! <role> ::= @roles.txt
+//!          | "web" | "db"
+//! <num>  ::= ?d ?d | ""
+//! ```
+//!
+//! Items are quoted literals (`"..."` or `'...'`, with `\` escaping the
+//! quote), non-terminals in angle brackets, word classes read from a file
+//! with `@path` (one word per line, relative to the grammar file), and
+//! mask charsets such as `?d` or `?l?u` standing for one character. A line
+//! starting with `|` continues the rule above, and `#` starts a comment
+//! outside quotes. The first rule is where generation starts. Rules can't
+//! refer to themselves, directly or not, so every grammar is finite.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{matches::Node, mutate};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    Literal(String),
+    Rule(String),
+    Words(String),
+    Charset(Vec<char>),
+}
+
+fn invalid(line: usize, message: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("grammar line {}: {}", line, message),
+    )
+}
+
+/// Split `text` into items and `|` separators (as `None`), up to a
+/// comment.
+fn tokens(text: &str, line: usize) -> io::Result<Vec<Option<Item>>> {
+    let mut tokens = vec![];
+    let mut rest = text.chars().peekable();
+    while let Some(c) = rest.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '#' => break,
+            '|' => tokens.push(None),
+            '"' | '\'' => {
+                let mut literal = String::new();
+                loop {
+                    match rest.next() {
+                        Some('\\') => match rest.next() {
+                            Some(c) => literal.push(c),
+                            None => return Err(invalid(line, "unterminated literal")),
+                        },
+                        Some(q) if q == c => break,
+                        Some(c) => literal.push(c),
+                        None => return Err(invalid(line, "unterminated literal")),
+                    }
+                }
+                tokens.push(Some(Item::Literal(literal)));
+            }
+            '<' => {
+                let name: String = rest.by_ref().take_while(|&c| c != '>').collect();
+                if name.is_empty() {
+                    return Err(invalid(line, "empty rule name"));
+                }
+                tokens.push(Some(Item::Rule(name)));
+            }
+            '@' | '?' => {
+                let mut word = String::new();
+                while let Some(&next) = rest.peek() {
+                    if next.is_whitespace() || next == '|' {
+                        break;
+                    }
+                    word.push(next);
+                    rest.next();
+                }
+                tokens.push(Some(if c == '@' {
+                    Item::Words(word)
+                } else {
+                    Item::Charset(
+                        mutate::parse_charset(&format!("?{}", word))
+                            .map_err(|e| invalid(line, e))?,
+                    )
+                }));
+            }
+            c => return Err(invalid(line, format!("unexpected '{}'", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Rules of a grammar, each alternative a sequence of items.
+struct Rules {
+    start: String,
+    rules: HashMap<String, Vec<Vec<Item>>>,
+}
+
+fn parse_rules(text: &str) -> io::Result<Rules> {
+    let mut start = None;
+    let mut rules: HashMap<String, Vec<Vec<Item>>> = HashMap::new();
+    let mut current: Option<String> = None;
+    for (i, line) in text.lines().enumerate() {
+        let n = i + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let body = match trimmed.split_once("::=") {
+            Some((head, body)) if !trimmed.starts_with('|') => {
+                let name = head
+                    .trim()
+                    .strip_prefix('<')
+                    .and_then(|h| h.strip_suffix('>'))
+                    .filter(|name| !name.is_empty())
+                    .ok_or_else(|| invalid(n, "expected '<name> ::= ...'"))?;
+                if rules.contains_key(name) {
+                    return Err(invalid(n, format!("<{}> is defined twice", name)));
+                }
+                rules.insert(name.to_string(), vec![vec![]]);
+                start.get_or_insert_with(|| name.to_string());
+                current = Some(name.to_string());
+                body
+            }
+            _ if trimmed.starts_with('|') && current.is_some() => trimmed,
+            _ => return Err(invalid(n, "expected '<name> ::= ...'")),
+        };
+        let alternatives = rules.get_mut(current.as_deref().unwrap_or("")).unwrap();
+        for token in tokens(body, n)? {
+            match token {
+                Some(item) => alternatives.last_mut().unwrap().push(item),
+                None => alternatives.push(vec![]),
+            }
+        }
+    }
+    let start =
+        start.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "grammar has no rules"))?;
+    Ok(Rules { start, rules })
+}
+
+/// Turn rule `name` into a tree, reading word classes from `dir`.
+fn resolve(
+    rules: &Rules,
+    name: &str,
+    dir: &Path,
+    stack: &mut Vec<String>,
+    words: &mut HashMap<String, Vec<String>>,
+) -> io::Result<Node> {
+    let alternatives = rules.rules.get(name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("grammar refers to undefined <{}>", name),
+        )
+    })?;
+    if stack.iter().any(|n| n == name) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("grammar rule <{}> refers to itself", name),
+        ));
+    }
+    stack.push(name.to_string());
+    let mut nodes = vec![];
+    for sequence in alternatives {
+        let mut parts = vec![];
+        for item in sequence {
+            parts.push(match item {
+                Item::Literal(literal) => Node::Literal(literal.clone()),
+                Item::Charset(chars) => Node::Class(chars.clone()),
+                Item::Rule(rule) => resolve(rules, rule, dir, stack, words)?,
+                Item::Words(path) => {
+                    if !words.contains_key(path) {
+                        words.insert(path.clone(), read_words(&dir.join(path))?);
+                    }
+                    Node::Words(words[path].clone())
+                }
+            });
+        }
+        nodes.push(Node::Concat(parts));
+    }
+    stack.pop();
+    Ok(Node::Alternation(nodes))
+}
+
+fn read_words(path: &Path) -> io::Result<Vec<String>> {
+    let file = fs::File::open(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    let mut words = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let word = line.strip_suffix('\r').unwrap_or(&line);
+        if !word.is_empty() {
+            words.push(word.to_string());
+        }
+    }
+    Ok(words)
+}
+
+/// Parse grammar `text`, reading word classes relative to `dir`.
+pub(crate) fn parse(text: &str, dir: &Path) -> io::Result<Node> {
+    let rules = parse_rules(text)?;
+    resolve(&rules, &rules.start, dir, &mut vec![], &mut HashMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matches::Matches;
+
+    #[test]
+    fn test_grammar() {
+        let dir =
+            std::env::temp_dir().join(format!("wlinflate_test_grammar_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("roles.txt"), "web\r\n\ndb\n").unwrap();
+        let grammar = "# hosts\n\
+            <host> ::= <env> \"-\" <role> <num> | <role>\n\
+            <env> ::= 'dev' | \"p\\\"rod\"  # quoted\n\
+            <role> ::= @roles.txt\n\
+                     | \"mail\"\n\
+            <num> ::= ?d | \"\"\n";
+        std::fs::write(dir.join("host.gram"), grammar).unwrap();
+        let matches = Matches::grammar(&dir.join("host.gram")).unwrap();
+        assert!(matches.total() == 2 * 3 * 11 + 3);
+        assert!(matches.byte_len() == matches.clone().map(|s| s.len() as u64 + 1).sum::<u64>());
+        let all: Vec<String> = matches.collect();
+        assert!(all[..3] == ["dev-web0", "dev-web1", "dev-web2"]);
+        assert!(all[10] == "dev-web" && all[11] == "dev-db0");
+        assert!(all[33] == "p\"rod-web0");
+        assert!(all[66..] == ["web", "db", "mail"]);
+
+        let fails = |text: &str| {
+            std::fs::write(dir.join("bad.gram"), text).unwrap();
+            Matches::grammar(&dir.join("bad.gram")).is_err()
+        };
+        assert!(fails("<a> ::= <b>\n<b> ::= \"x\" <a>\n"));
+        assert!(fails("<a> ::= <missing>\n"));
+        assert!(fails("<a> ::= \"x\n"));
+        assert!(fails("<a> ::= x\n"));
+        assert!(fails("<a> ::= @missing.txt\n"));
+        assert!(fails("| \"x\"\n"));
+        assert!(fails(""));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,46 @@
+use std::hash::Hasher;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 64-bit FNV-1a hasher.
+///
+/// Unlike `DefaultHasher`, the output is stable across Rust releases, so it
+/// is safe to persist (cache keys, fingerprints).
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv64(u64);
+
+impl Default for Fnv64 {
+    fn default() -> Self {
+        Fnv64(FNV_OFFSET)
+    }
+}
+
+impl Hasher for Fnv64 {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv64_known_values() {
+        let mut h = Fnv64::default();
+        h.write(b"");
+        assert!(h.finish() == 0xcbf29ce484222325);
+
+        let mut h = Fnv64::default();
+        h.write(b"a");
+        assert!(h.finish() == 0xaf63dc4c8601ec8c);
+    }
+}
@@ -0,0 +1,113 @@
+//! Human-readable formatting for log output, and parsing of counts given
+//! the same way.
+
+use std::time::Duration;
+
+/// Format a word count with SI suffixes (`1.2M words`).
+pub fn count(n: u128) -> String {
+    const UNITS: [&str; 5] = ["", "K", "M", "B", "T"];
+    if n < 1000 {
+        return format!("{} words", n);
+    }
+    let mut value = n as f64;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1}{} words", value, UNITS[unit])
+}
+
+/// Parse a count with an optional SI suffix (`10M`, `2.5K`, `1B`).
+pub fn parse_count(s: &str) -> Result<usize, String> {
+    let invalid = || format!("invalid count '{}', expected e.g. 500K or 10M", s);
+    let s = s.trim();
+    let (number, scale) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 1e3),
+        Some((i, 'M' | 'm')) => (&s[..i], 1e6),
+        Some((i, 'B' | 'b' | 'G' | 'g')) => (&s[..i], 1e9),
+        Some((i, 'T' | 't')) => (&s[..i], 1e12),
+        _ => return s.parse().map_err(|_| invalid()),
+    };
+    let value = number.parse::<f64>().map_err(|_| invalid())? * scale;
+    if !value.is_finite() || value < 0.0 || value >= usize::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(value.round() as usize)
+}
+
+/// Parse a size in bytes with an optional binary suffix (`512M`, `2G`,
+/// `1.5GiB`).
+pub fn parse_bytes(s: &str) -> Result<usize, String> {
+    let invalid = || format!("invalid size '{}', expected e.g. 512M or 2G", s);
+    let s = s.trim();
+    let s = s
+        .strip_suffix("iB")
+        .or_else(|| s.strip_suffix('B'))
+        .unwrap_or(s);
+    let (number, scale) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 1u64 << 10),
+        Some((i, 'M' | 'm')) => (&s[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&s[..i], 1 << 30),
+        Some((i, 'T' | 't')) => (&s[..i], 1 << 40),
+        _ => (s, 1),
+    };
+    let value = number.parse::<f64>().map_err(|_| invalid())? * scale as f64;
+    if !value.is_finite() || value < 0.0 || value >= usize::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(value.round() as usize)
+}
+
+/// Format a byte count with binary suffixes (`14.3 GiB`).
+pub fn bytes(n: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    if n < 1024 {
+        return format!("{} B", n);
+    }
+    let mut value = n as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Format an elapsed time (`2h 03m 10s`, `1m 04.2s`, `0.35s`).
+pub fn duration(d: Duration) -> String {
+    let secs = d.as_secs_f64();
+    let whole = d.as_secs();
+    if whole >= 3600 {
+        format!(
+            "{}h {:02}m {:02}s",
+            whole / 3600,
+            whole % 3600 / 60,
+            whole % 60
+        )
+    } else if whole >= 60 {
+        format!("{}m {:04.1}s", whole / 60, secs - (whole / 60 * 60) as f64)
+    } else {
+        format!("{:.2}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_units() {
+        assert!(count(999) == "999 words");
+        assert!(count(1_234_567) == "1.2M words");
+        assert!(parse_count("10M") == Ok(10_000_000));
+        assert!(parse_count("2.5k") == Ok(2500) && parse_count("42") == Ok(42));
+        assert!(parse_count("M").is_err() && parse_count("-1K").is_err());
+        assert!(parse_bytes("2G") == Ok(2 << 30) && parse_bytes("1.5KiB") == Ok(1536));
+        assert!(parse_bytes("100") == Ok(100) && parse_bytes("G").is_err());
+        assert!(bytes(512) == "512 B");
+        assert!(bytes(15_354_508_083) == "14.3 GiB");
+        assert!(duration(Duration::from_millis(64_200)) == "1m 04.2s");
+        assert!(duration(Duration::from_secs(7390)) == "2h 03m 10s");
+    }
+}
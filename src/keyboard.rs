@@ -0,0 +1,160 @@
+//! Keyboard layouts and which keys sit next to each other on them, for
+//! modelling typos that depend on where keys are.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Layout names accepted by [`Layout::from_str`].
+pub const LAYOUTS: &[&str] = &["qwerty", "qwertz", "azerty", "dvorak", "keypad"];
+
+/// A keyboard layout, by its unshifted keys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// US ANSI.
+    #[default]
+    Qwerty,
+    /// German.
+    Qwertz,
+    /// French.
+    Azerty,
+    /// US Dvorak.
+    Dvorak,
+    /// The 3x4 digit pad of phones and PIN entry screens.
+    Keypad,
+}
+
+impl Layout {
+    /// Rows of keys from the top, each with how far its first key sits from
+    /// the left edge, in quarter keys.
+    fn rows(&self) -> &'static [(&'static str, usize)] {
+        match self {
+            Layout::Qwerty => &[
+                ("1234567890-=", 0),
+                ("qwertyuiop[]", 2),
+                ("asdfghjkl;'", 3),
+                ("zxcvbnm,./", 5),
+            ],
+            Layout::Qwertz => &[
+                ("1234567890ß", 0),
+                ("qwertzuiopü+", 2),
+                ("asdfghjklöä#", 3),
+                ("yxcvbnm,.-", 5),
+            ],
+            Layout::Azerty => &[
+                ("&é\"'(-è_çà)=", 0),
+                ("azertyuiop^$", 2),
+                ("qsdfghjklmù*", 3),
+                ("wxcvbn,;:!", 5),
+            ],
+            Layout::Dvorak => &[
+                ("1234567890[]", 0),
+                ("',.pyfgcrl/=", 2),
+                ("aoeuidhtns-", 3),
+                (";qjkxbmwvz", 5),
+            ],
+            Layout::Keypad => &[("123", 0), ("456", 0), ("789", 0), ("*0#", 0)],
+        }
+    }
+
+    /// Row and horizontal position (in quarter keys) of `c`.
+    fn position(&self, c: char) -> Option<(usize, usize)> {
+        self.rows()
+            .iter()
+            .enumerate()
+            .find_map(|(row, (keys, offset))| {
+                let col = keys.chars().position(|k| k == c)?;
+                Some((row, offset + col * 4))
+            })
+    }
+
+    /// Keys touching `c`'s key, left to right and top to bottom: either
+    /// side on its row, and any overlapping it on the rows above and below.
+    ///
+    /// Upper case letters give their neighbours in upper case. Characters
+    /// not on the layout have none.
+    pub fn neighbours(&self, c: char) -> Vec<char> {
+        let upper = c.is_uppercase();
+        let key = if upper {
+            c.to_lowercase().next().unwrap_or(c)
+        } else {
+            c
+        };
+        let (row, x) = match self.position(key) {
+            Some(position) => position,
+            None => return vec![],
+        };
+        let mut neighbours = vec![];
+        for (r, (keys, offset)) in self.rows().iter().enumerate() {
+            if r + 1 < row || r > row + 1 {
+                continue;
+            }
+            for (col, k) in keys.chars().enumerate() {
+                let kx = offset + col * 4;
+                let touching = if r == row {
+                    kx.abs_diff(x) == 4
+                } else {
+                    kx.abs_diff(x) < 4
+                };
+                if touching {
+                    neighbours.push(if upper {
+                        k.to_uppercase().next().unwrap_or(k)
+                    } else {
+                        k
+                    });
+                }
+            }
+        }
+        neighbours
+    }
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "qwerty" => Ok(Layout::Qwerty),
+            "qwertz" => Ok(Layout::Qwertz),
+            "azerty" => Ok(Layout::Azerty),
+            "dvorak" => Ok(Layout::Dvorak),
+            "keypad" => Ok(Layout::Keypad),
+            other => Err(format!(
+                "unknown layout '{}', expected one of {}",
+                other,
+                LAYOUTS.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Layout::Qwerty => "qwerty",
+            Layout::Qwertz => "qwertz",
+            Layout::Azerty => "azerty",
+            Layout::Dvorak => "dvorak",
+            Layout::Keypad => "keypad",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neighbours() {
+        assert!(Layout::Qwerty.neighbours('s') == ['w', 'e', 'a', 'd', 'z', 'x']);
+        assert!(Layout::Qwerty.neighbours('S') == ['W', 'E', 'A', 'D', 'Z', 'X']);
+        assert!(Layout::Qwertz.neighbours('t') == ['5', '6', 'r', 'z', 'f', 'g']);
+        assert!(Layout::Azerty.neighbours('a') == ['&', 'é', 'z', 'q']);
+        assert!(Layout::Dvorak.neighbours('o') == [',', '.', 'a', 'e', ';', 'q']);
+        assert!(Layout::Keypad.neighbours('5') == ['2', '4', '6', '8']);
+        assert!(Layout::Qwerty.neighbours('€').is_empty());
+        assert!("colemak".parse::<Layout>().is_err());
+    }
+}
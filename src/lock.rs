@@ -0,0 +1,107 @@
+//! A lock on an output file, so two runs writing the same file fail
+//! rather than interleave their words.
+
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// An advisory lock on `.name.lock` beside an output file, held until
+/// dropped. The operating system releases it if the run dies, so a crash
+/// never leaves a stale lock behind.
+pub struct OutputLock {
+    path: PathBuf,
+    file: File,
+}
+
+impl OutputLock {
+    /// Lock the output file `dest`, failing straight away if another run
+    /// holds it.
+    pub fn acquire(dest: &Path) -> io::Result<Self> {
+        // a symlinked output is locked where it points
+        let target = dest.canonicalize().unwrap_or_else(|_| dest.to_path_buf());
+        let name = target.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "output is not a file name")
+        })?;
+        let path = target.with_file_name(format!(".{}.lock", name.to_string_lossy()));
+        loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)?;
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(TryLockError::WouldBlock) => {
+                    let holder = fs::read_to_string(&path).unwrap_or_default();
+                    let holder = match holder.trim() {
+                        "" => String::new(),
+                        pid => format!(" (pid {})", pid),
+                    };
+                    return Err(io::Error::new(
+                        io::ErrorKind::WouldBlock,
+                        format!(
+                            "{} is being written by another run{}",
+                            dest.display(),
+                            holder
+                        ),
+                    ));
+                }
+                Err(TryLockError::Error(e)) => return Err(e),
+            }
+            // the lock file may have been removed by the run that held it
+            // between opening and locking it; lock the new one instead
+            if !same_file(&file, &path) {
+                continue;
+            }
+            file.set_len(0)?;
+            writeln!(file, "{}", process::id())?;
+            return Ok(Self { path, file });
+        }
+    }
+}
+
+#[cfg(unix)]
+fn same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(_file: &File, path: &Path) -> bool {
+    path.exists()
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        // removed while still locked, so no other run can lock it first
+        let _ = fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_lock() {
+        let dir = std::env::temp_dir().join(format!("wlinflate_test_lock_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("out.txt");
+        let lock = OutputLock::acquire(&dest).unwrap();
+        let e = OutputLock::acquire(&dest).err().unwrap();
+        assert!(e.kind() == io::ErrorKind::WouldBlock);
+        assert!(e.to_string().contains(&format!("pid {}", process::id())));
+        drop(lock);
+        assert!(!dir.join(".out.txt.lock").exists());
+        drop(OutputLock::acquire(&dest).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,10 +1,63 @@
+mod atomic;
+mod bench;
+mod cache;
+mod config;
+mod coordinator;
+mod error;
+mod external;
+mod freq;
+mod from_pot;
+mod human;
+mod lock;
+mod plan;
+mod recipe;
+mod resume;
+mod rules;
+mod script;
+mod sink;
+mod stats;
+mod summary;
+mod train;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
+mod verify;
+mod watch;
+mod worker;
+
 use std::{
-    io::{self, stdout, BufWriter, Write},
+    ffi::OsString,
+    fs::{self, OpenOptions},
+    io::{self, stdout, BufWriter, IsTerminal, Write},
+    mem,
     path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+use atomic::Staged;
+use cache::{Cache, Tee};
+use config::Config;
+use error::Error;
+use lock::OutputLock;
+use log::{debug, error, info, warn, Level, LevelFilter};
+use sink::Sink;
+use stats::{Coverage, Stats};
 use structopt::StructOpt;
-use wlinflate::Wordlist;
+use summary::Summary;
+use verify::Digest;
+use wlinflate::{
+    encode::EncodeScope,
+    format::InputFormat,
+    keyboard::{self, Layout},
+    markov::MarkovModel,
+    mutate::{self, LeetMap, Mutation},
+    parallel::{ParallelWordlist, WriterThread},
+    parse_lengths,
+    phone::Region,
+    phrase::Casing,
+    pin, presets, split_values, Wordlist,
+};
 
 #[derive(Debug, StructOpt, Clone)]
 #[structopt(
@@ -13,71 +66,1628 @@ use wlinflate::Wordlist;
     about = "simple tool to expand a wordlist with prepends, appends, extensions, and substitutions"
 )]
 struct Args {
-    #[structopt(short = "v", long = "verbose", help = "enable basic logging")]
-    verbose: bool,
-    #[structopt(short = "p", long = "prepend", help = "prepend wordlist words (csv)")]
-    prepend: Option<String>,
-    #[structopt(short = "a", long = "append", help = "append wordlist words (csv)")]
-    append: Option<String>,
-    #[structopt(short = "x", long = "extensions", help = "extensions to search (csv)")]
-    extensions: Option<String>,
+    #[structopt(
+        short = "v",
+        long = "verbose",
+        help = "log to stderr; repeat for more detail (-vv, -vvv)",
+        parse(from_occurrences)
+    )]
+    verbose: u8,
+    #[structopt(
+        short = "q",
+        long = "quiet",
+        help = "suppress all diagnostics on stderr",
+        conflicts_with = "verbose"
+    )]
+    quiet: bool,
+    #[structopt(
+        short = "p",
+        long = "prepend",
+        number_of_values = 1,
+        help = "prepend wordlist words (csv, or repeat once per value) [env: WLINFLATE_PREPEND]"
+    )]
+    prepend: Vec<String>,
+    #[structopt(
+        short = "a",
+        long = "append",
+        number_of_values = 1,
+        help = "append wordlist words (csv, or repeat once per value) [env: WLINFLATE_APPEND]"
+    )]
+    append: Vec<String>,
+    #[structopt(
+        short = "x",
+        long = "extensions",
+        number_of_values = 1,
+        help = "extensions to search (csv, or repeat once per value) [env: WLINFLATE_EXTENSIONS]"
+    )]
+    extensions: Vec<String>,
     #[structopt(
         short = "s",
         long = "swap",
-        help = "swap in for entries that contain {SWAP} (csv)"
+        number_of_values = 1,
+        help = "swap in for entries that contain {SWAP} or {SWAP?} (csv, or repeat once per value) [env: WLINFLATE_SWAP]"
+    )]
+    swap: Vec<String>,
+    #[structopt(
+        long = "swap-token",
+        conflicts_with_all = &["token-open", "token-close"],
+        help = "marker replaced by swap values instead of {SWAP} (e.g. FUZZ)"
+    )]
+    swap_token: Option<String>,
+    #[structopt(
+        long = "token-open",
+        help = "opening delimiter of the swap token [default: {]"
+    )]
+    token_open: Option<String>,
+    #[structopt(
+        long = "token-close",
+        help = "closing delimiter of the swap token [default: }]"
+    )]
+    token_close: Option<String>,
+    #[structopt(
+        long = "nest-depth",
+        default_value = "0",
+        help = "expand tokens inside swap values up to this many levels deep"
+    )]
+    nest_depth: usize,
+    #[structopt(
+        long = "rand-count",
+        default_value = "1",
+        help = "random strings produced by each {RAND:len:charset} token"
+    )]
+    rand_count: usize,
+    #[structopt(
+        long = "seed",
+        env = "WLINFLATE_SEED",
+        help = "seed for everything random (random tokens, --script's rand, --filter-exec), for reproducible output"
+    )]
+    seed: Option<u64>,
+    /// `seed` was picked for this run rather than given.
+    #[structopt(skip)]
+    seed_picked: bool,
+    #[structopt(
+        long = "var",
+        number_of_values = 1,
+        help = "key=value for {ENV:key} tokens, overriding the environment (repeatable)"
+    )]
+    var: Vec<String>,
+    #[structopt(
+        long = "templates",
+        parse(from_os_str),
+        help = "file of template lines; each word from -w replaces {WORD} in every line"
+    )]
+    templates: Option<PathBuf>,
+    #[structopt(
+        long = "word2-list",
+        parse(from_os_str),
+        requires = "templates",
+        help = "wordlist bound to {WORD2} in templates ({WORD1} is -w)"
+    )]
+    word2_list: Option<PathBuf>,
+    #[structopt(
+        long = "markov",
+        parse(from_os_str),
+        conflicts_with = "wordlist",
+        help = "generate base words from a model made by `wlinflate train` instead of reading -w"
+    )]
+    markov: Option<PathBuf>,
+    #[structopt(
+        long = "pattern",
+        number_of_values = 1,
+        conflicts_with_all = &["wordlist", "markov"],
+        help = "generate base words from a pattern instead of reading -w: A upper case letter, a lower case letter, 9 digit, \\ escapes, anything else literal, e.g. 'AAA-9999' (repeatable)"
+    )]
+    pattern: Vec<String>,
+    #[structopt(
+        long = "regex",
+        conflicts_with_all = &["wordlist", "markov", "pattern"],
+        help = "generate base words as every string matching a regex with bounded quantifiers instead of reading -w, e.g. '(dev|prod)-(api|web)[0-9]{2}'"
+    )]
+    regex: Option<String>,
+    #[structopt(
+        long = "grammar",
+        parse(from_os_str),
+        conflicts_with_all = &["wordlist", "markov", "pattern", "regex"],
+        help = "generate base words from a BNF-like grammar file instead of reading -w"
+    )]
+    grammar: Option<PathBuf>,
+    #[structopt(
+        long = "markov-count",
+        default_value = "1000000",
+        help = "base words to generate from the --markov model"
+    )]
+    markov_count: usize,
+    #[structopt(
+        long = "markov-max-length",
+        default_value = "16",
+        help = "longest base word to generate from the --markov model"
+    )]
+    markov_max_length: usize,
+    #[structopt(
+        long = "enum-ordered",
+        requires = "markov",
+        help = "generate --markov words in strictly decreasing probability bands (OMEN-style) instead of depth-first"
+    )]
+    enum_ordered: bool,
+    #[structopt(
+        long = "mode",
+        default_value = "inflate",
+        possible_values = &["inflate", "fuzz", "passphrase", "pins", "phone"],
+        help = "fuzz also follows each word with byte-level mutations (bit flips, boundary bytes, long repeats); passphrase combines several words into each base word; pins and phone generate numbers instead of reading a wordlist"
+    )]
+    mode: String,
+    #[structopt(
+        long = "length",
+        default_value = "4",
+        help = "digits per number in --mode pins, e.g. '4-8'"
+    )]
+    length: String,
+    #[structopt(
+        long = "region",
+        default_value = "US",
+        help = "numbering plan of --mode phone numbers (US, CA)"
+    )]
+    region: Region,
+    #[structopt(
+        long = "area-codes",
+        required_if("mode", "phone"),
+        help = "area codes --mode phone generates numbers in, e.g. '212,718'"
+    )]
+    area_codes: Option<String>,
+    #[structopt(
+        long = "phone-formats",
+        allow_hyphen_values = true,
+        help = "formats --mode phone writes each number in, # for each digit, e.g. '###-###-####,+1##########'"
+    )]
+    phone_formats: Option<String>,
+    #[structopt(
+        long = "words",
+        default_value = "2",
+        help = "words per passphrase in --mode passphrase, e.g. '2-4'"
+    )]
+    words: String,
+    #[structopt(
+        long = "phrase-case",
+        help = "casings each passphrase word is tried in, e.g. 'lower,title' (as-is, lower, upper, title)"
+    )]
+    phrase_case: Option<String>,
+    #[structopt(
+        long = "joiners",
+        allow_hyphen_values = true,
+        help = "strings put between passphrase words, each phrase once per joiner (e.g. '-,_, ,.')"
+    )]
+    joiners: Option<String>,
+    #[structopt(
+        long = "leet-map",
+        parse(from_os_str),
+        help = "TOML file of character substitutions; each word is followed by its variants"
+    )]
+    leet_map: Option<PathBuf>,
+    #[structopt(
+        long = "transpose",
+        help = "follow each word with its variants with two adjacent characters swapped"
+    )]
+    transpose: bool,
+    #[structopt(
+        long = "keyboard-typos",
+        help = "follow each word with its variants with one character replaced by a key next to it on --layout"
+    )]
+    keyboard_typos: bool,
+    #[structopt(
+        long = "layout",
+        default_value = "qwerty",
+        possible_values = keyboard::LAYOUTS,
+        help = "keyboard layout adjacency-based mutations use"
+    )]
+    layout: Layout,
+    #[structopt(
+        long = "delete-each",
+        help = "follow each word with its variants with one character removed"
+    )]
+    delete_each: bool,
+    #[structopt(
+        long = "insert-each",
+        help = "follow each word with its variants with a character from this charset inserted, e.g. '?d'"
+    )]
+    insert_each: Option<String>,
+    #[structopt(
+        long = "overwrite",
+        number_of_values = 1,
+        help = "follow each word with its variants with the character at POS (or * for each) replaced from CHARS, e.g. '0:?u' (repeatable)"
+    )]
+    overwrite: Vec<String>,
+    #[structopt(
+        long = "sub",
+        number_of_values = 1,
+        help = "sed-style substitution applied to each word in order, e.g. 's/o/0/g'; flag k keeps the original too (repeatable)"
+    )]
+    sub: Vec<String>,
+    #[structopt(
+        long = "rules",
+        parse(from_os_str),
+        help = "file of --sub rules, one per line; '-' reads standard input, taking more while running"
+    )]
+    rules: Option<PathBuf>,
+    /// More `--rules` remain to be read from standard input while running.
+    #[structopt(skip)]
+    follow_rules: bool,
+    #[structopt(
+        long = "hybrid-prepend",
+        help = "put every string of this mask before each word, e.g. '?d'"
+    )]
+    hybrid_prepend: Option<String>,
+    #[structopt(
+        long = "hybrid-append",
+        help = "put every string of this mask after each word, e.g. '?d?d'"
+    )]
+    hybrid_append: Option<String>,
+    #[structopt(
+        long = "wrap-preset",
+        number_of_values = 1,
+        possible_values = presets::WRAP_PRESETS,
+        help = "replace each word with it wrapped in common injection payloads (repeatable)"
     )]
-    swap: Option<String>,
+    wrap_preset: Vec<String>,
+    #[structopt(
+        long = "encode",
+        help = "encode each word with these encoders in order, e.g. 'url,base64' (url, base64, hex, html)"
+    )]
+    encode: Option<String>,
+    #[structopt(
+        long = "encode-each",
+        requires = "encode",
+        conflicts_with = "encode-final",
+        help = "encode only the base word, leaving prepends, appends and extensions as given"
+    )]
+    encode_each: bool,
+    #[structopt(
+        long = "encode-final",
+        requires = "encode",
+        help = "encode the finished word, affixes included (the default)"
+    )]
+    encode_final: bool,
+    #[structopt(
+        long = "apply",
+        number_of_values = 1,
+        help = "only apply a transform to matching words, e.g. 'ext: if not contains \".\"' (repeatable)"
+    )]
+    apply: Vec<String>,
+    #[structopt(
+        long = "directives",
+        help = "honour '#!noext'-style directives at the end of wordlist lines"
+    )]
+    directives: bool,
+    #[structopt(
+        long = "ext-preset",
+        number_of_values = 1,
+        possible_values = presets::EXTENSION_PRESETS,
+        help = "add a curated group of extensions (repeatable)"
+    )]
+    ext_preset: Vec<String>,
+    #[structopt(
+        long = "suffix-preset",
+        number_of_values = 1,
+        possible_values = presets::SUFFIX_PRESETS,
+        help = "add a curated group of appends, e.g. emoji (repeatable)"
+    )]
+    suffix_preset: Vec<String>,
+    #[structopt(
+        long = "auto-dot",
+        help = "add a leading dot to extensions given without one"
+    )]
+    auto_dot: bool,
+    #[structopt(
+        long = "ext-strip",
+        help = "replace an existing extension instead of appending another"
+    )]
+    ext_strip: bool,
+    #[structopt(
+        long = "delimiter",
+        default_value = ",",
+        help = "separator for -p/-a/-x/-s values (escape with \\)"
+    )]
+    delimiter: String,
 
     #[structopt(
         short = "w",
         long = "wordlist",
-        help = "path to wordlist",
+        help = "path to wordlist (repeat to read several)",
+        number_of_values = 1,
         parse(from_os_str)
     )]
-    wordlist: PathBuf,
+    wordlist: Vec<PathBuf>,
+    #[structopt(
+        long = "input-format",
+        default_value = "lines",
+        possible_values = &["lines", "csv", "tsv", "jsonl"],
+        help = "how to read wordlist lines: whole lines, one column of CSV/TSV rows, or a --field of JSON lines"
+    )]
+    input_format: String,
+    #[structopt(
+        long = "field",
+        help = "field of JSON lines holding the word, e.g. 'host' or 'result.name' (with --input-format jsonl)"
+    )]
+    field: Option<String>,
+    #[structopt(
+        long = "column",
+        default_value = "1",
+        help = "column of CSV/TSV rows holding the word, counting from 1"
+    )]
+    column: usize,
+    #[structopt(long = "header", help = "skip the first row of each CSV/TSV wordlist")]
+    header: bool,
+    #[structopt(
+        long = "carry-columns",
+        help = "read word<TAB>columns lines and copy the columns after every word made from them"
+    )]
+    carry_columns: bool,
+    #[structopt(
+        long = "interleave",
+        help = "take words from each wordlist in turn instead of one list after another"
+    )]
+    interleave: bool,
+    #[structopt(
+        long = "follow",
+        help = "after the end of the last wordlist, wait for lines appended to it (tail -f)",
+        conflicts_with_all = &["interleave", "reverse-order", "order-by-likelihood", "weighted"]
+    )]
+    follow: bool,
+    #[structopt(
+        long = "watch",
+        help = "run again into a new version of the output file whenever an input or the recipe changes",
+        requires = "outfile",
+        conflicts_with_all = &["follow", "resume-from-output"]
+    )]
+    watch: bool,
     #[structopt(short = "o", long = "output", help = "output file", parse(from_os_str))]
     outfile: Option<PathBuf>,
+    #[structopt(
+        long = "append-output",
+        help = "append to an existing output file, skipping the words it already holds",
+        requires = "outfile",
+        conflicts_with_all = &["resume-from-output", "cache-dir", "watch"]
+    )]
+    append_output: bool,
+    #[structopt(
+        long = "no-atomic",
+        help = "write -o directly instead of to a temporary file renamed into place when done"
+    )]
+    no_atomic: bool,
+    #[structopt(long = "force", help = "overwrite an existing output file")]
+    force: bool,
+    #[structopt(
+        long = "resume-from-output",
+        help = "continue an interrupted run by appending to its output file",
+        conflicts_with_all = &["outfile", "cache-dir"],
+        parse(from_os_str)
+    )]
+    resume_from_output: Option<PathBuf>,
+    #[structopt(
+        long = "cache-dir",
+        env = "WLINFLATE_CACHE_DIR",
+        help = "reuse inflated output cached in this directory",
+        parse(from_os_str)
+    )]
+    cache_dir: Option<PathBuf>,
+    #[structopt(
+        long = "buffer-size",
+        env = "WLINFLATE_BUFFER_SIZE",
+        help = "output buffer size in bytes [default: 65536]"
+    )]
+    buffer_size: Option<usize>,
+    #[structopt(
+        long = "threads",
+        help = "expand words on this many threads; the output order is unchanged"
+    )]
+    threads: Option<usize>,
+    #[structopt(
+        long = "unordered",
+        requires = "threads",
+        help = "with --threads, write batches as they finish instead of in sequence"
+    )]
+    unordered: bool,
+    #[structopt(
+        long = "verify",
+        require_equals = true,
+        value_name = "hash",
+        conflicts_with = "follow",
+        help = "check every word of the keyspace was generated, and with =HASH that the output hashes to it"
+    )]
+    verify: Option<Option<String>>,
+    #[structopt(
+        long = "summary-json",
+        help = "write a JSON run report to a file (stderr if no path given)"
+    )]
+    summary_json: Option<Option<String>>,
+    #[structopt(
+        long = "coverage",
+        requires = "summary-json",
+        help = "add the character classes, lengths and prefix/suffix masks covered to the report"
+    )]
+    coverage: bool,
+
+    #[structopt(
+        long = "weighted",
+        help = "read word<TAB>count lines and emit the most frequent words first"
+    )]
+    weighted: bool,
+    #[structopt(
+        long = "min-count",
+        requires = "weighted",
+        help = "skip weighted words seen fewer than this many times"
+    )]
+    min_count: Option<u64>,
+    #[structopt(
+        long = "top",
+        requires = "weighted",
+        help = "only expand the K most frequent weighted words"
+    )]
+    top: Option<usize>,
+    #[structopt(
+        long = "order-by-likelihood",
+        help = "emit base words first, then single affixes, then combinations"
+    )]
+    order_by_likelihood: bool,
+    #[structopt(
+        long = "reverse-order",
+        help = "emit candidates from the end of the keyspace backwards (indexes count from the end)"
+    )]
+    reverse_order: bool,
+    #[structopt(long = "unique", help = "drop duplicate words (kept in memory)")]
+    unique: bool,
+    #[structopt(
+        long = "unique-insensitive",
+        help = "drop words that differ from an earlier one only in case"
+    )]
+    unique_insensitive: bool,
+    #[structopt(
+        long = "unique-window",
+        help = "drop words repeated within the last N emitted words"
+    )]
+    unique_window: Option<usize>,
+    #[structopt(
+        long = "potfile",
+        parse(from_os_str),
+        help = "hashcat or john potfile; skip words it already holds a plaintext for"
+    )]
+    potfile: Option<PathBuf>,
+    #[structopt(
+        long = "script",
+        parse(from_os_str),
+        help = "Rhai script run on each candidate to rewrite, expand or drop it"
+    )]
+    script: Option<PathBuf>,
+    #[structopt(
+        long = "filter-exec",
+        help = "shell command candidates are piped through, keeping those it prints back"
+    )]
+    filter_exec: Option<String>,
+    #[structopt(
+        long = "memory-limit",
+        parse(try_from_str = human::parse_bytes),
+        help = "memory --unique may use before spilling to temporary files (e.g. 2G)"
+    )]
+    memory_limit: Option<usize>,
+    #[structopt(
+        long = "skip-degenerate",
+        help = "skip words where an affix repeats the base word or another affix"
+    )]
+    skip_degenerate: bool,
+    #[structopt(
+        long = "keep-lengths",
+        help = "only emit words of these lengths in characters, e.g. '6-12' or '8,10-'"
+    )]
+    keep_lengths: Option<String>,
+    #[structopt(
+        long = "top-length-buckets",
+        conflicts_with = "keep-lengths",
+        help = "only emit words of the N most common lengths among the input words"
+    )]
+    top_length_buckets: Option<usize>,
+    #[structopt(
+        long = "start-index",
+        help = "index of the first candidate to emit (0-based)"
+    )]
+    start_index: Option<usize>,
+    #[structopt(long = "end-index", help = "index to stop before (exclusive)")]
+    end_index: Option<usize>,
+    #[structopt(
+        long = "annotate",
+        help = "append a tab-separated provenance tag to each word"
+    )]
+    annotate: bool,
+    #[structopt(
+        long = "confirm-over",
+        help = "ask before generating more than this many words (e.g. 1e9)"
+    )]
+    confirm_over: Option<f64>,
+    #[structopt(short = "y", long = "yes", help = "answer yes to confirmation prompts")]
+    yes: bool,
+    #[structopt(long = "recipe", help = "apply the options of a named recipe file")]
+    recipe: Option<String>,
+    #[structopt(
+        long = "recipe-dir",
+        env = "WLINFLATE_RECIPE_DIR",
+        help = "directory of <name>.toml recipes [default: ~/.config/wlinflate/recipes]",
+        parse(from_os_str)
+    )]
+    recipe_dir: Option<PathBuf>,
+
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+enum Command {
+    #[structopt(about = "measure generation throughput for the current pipeline")]
+    Bench(bench::BenchArgs),
+    #[structopt(about = "split the keyspace into per-node index ranges, printed as JSON")]
+    Plan(plan::PlanArgs),
+    #[structopt(about = "hand out keyspace chunks to `work` processes")]
+    Coordinate(coordinator::CoordinateArgs),
+    #[structopt(about = "generate keyspace chunks handed out by a coordinator")]
+    Work(worker::WorkArgs),
+    #[structopt(
+        name = "from-pot",
+        about = "print the cracked plaintexts of a potfile as a wordlist to inflate"
+    )]
+    FromPot(from_pot::FromPotArgs),
+    #[structopt(about = "merge wordlists into word<TAB>count lines, most frequent first")]
+    Freq(freq::FreqArgs),
+    #[structopt(about = "train a character-level Markov model on password corpora")]
+    Train(train::TrainArgs),
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// How often a followed wordlist is checked for new lines.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(200);
+
+impl Args {
+    /// Parse `command`, a full command line, as [`main`] does.
+    fn parse(command: &[OsString]) -> Result<Self, Error> {
+        let mut args = Self::parse_options(command)?;
+        args.load()?;
+        Ok(args)
+    }
+
+    /// Parse `command` without reading the files its options refer to.
+    /// `--help` and `--version` print and exit here.
+    fn parse_options(command: &[OsString]) -> Result<Self, Error> {
+        let command = recipe::expand(command.to_vec())?;
+        Args::from_iter_safe(command).map_err(|e| {
+            if !e.use_stderr() {
+                e.exit();
+            }
+            // the first line, without clap's prefix and usage
+            let message = e.message.lines().next().unwrap_or_default();
+            Error::InvalidConfig(message.trim_start_matches("error: ").to_string())
+        })
+    }
+
+    /// Read what the options refer to beyond the command line: the config
+    /// file and `--rules`.
+    fn load(&mut self) -> Result<(), Error> {
+        self.merge(Config::load()?);
+        if let Some(path) = &self.rules {
+            if self.watch && path.as_os_str() == "-" {
+                return Err(Error::InvalidConfig(
+                    "--watch can't read --rules from standard input".to_string(),
+                ));
+            }
+            let (rules, more) = rules::read(path)?;
+            self.sub.extend(rules);
+            self.follow_rules = more;
+        }
+        Ok(())
+    }
+
+    /// Fill options left unset on the command line from `WLINFLATE_*`
+    /// variables, then the config file.
+    fn merge(&mut self, config: Config) {
+        fn fill<T>(arg: &mut Option<T>, default: Option<T>) {
+            if arg.is_none() {
+                *arg = default;
+            }
+        }
+        // clap adds a multi-value option's variable to the values given,
+        // so these are read here rather than through `env`
+        fn fill_values(arg: &mut Vec<String>, var: &str, default: Option<String>) {
+            if arg.is_empty() {
+                let value = std::env::var(var).ok().filter(|value| !value.is_empty());
+                arg.extend(value.or(default));
+            }
+        }
+        fill_values(&mut self.prepend, "WLINFLATE_PREPEND", config.prepend);
+        fill_values(&mut self.append, "WLINFLATE_APPEND", config.append);
+        fill_values(
+            &mut self.extensions,
+            "WLINFLATE_EXTENSIONS",
+            config.extensions,
+        );
+        fill_values(&mut self.swap, "WLINFLATE_SWAP", config.swap);
+        if self.ext_preset.is_empty() {
+            self.ext_preset = config.ext_preset;
+        }
+        fill(&mut self.buffer_size, config.buffer_size);
+        fill(&mut self.cache_dir, config.cache_dir);
+    }
+
+    /// The output file a run picks up where it left off: the one given to
+    /// `--resume-from-output`, or an existing `-o` file with `--append-output`.
+    fn resumed_output(&self) -> Option<&PathBuf> {
+        match &self.outfile {
+            Some(path) if self.append_output && path.is_file() => Some(path),
+            _ => self.resume_from_output.as_ref(),
+        }
+    }
+
+    /// Whether words are dropped or added after generation, so the output
+    /// no longer holds one line per candidate.
+    fn filters_output(&self) -> bool {
+        self.unique
+            || self.unique_insensitive
+            || self.unique_window.is_some()
+            || self.potfile.is_some()
+            || self.script.is_some()
+            || self.filter_exec.is_some()
+    }
+
+    /// Lock the output file against other runs for as long as the returned
+    /// lock is held, then check it may be written.
+    fn claim_output(&self) -> Result<Option<OutputLock>, Error> {
+        let lock = match self.outfile.iter().chain(&self.resume_from_output).next() {
+            // special files such as /dev/null are shared by design
+            Some(path) if fs::metadata(path).is_err() || path.is_file() => {
+                Some(OutputLock::acquire(path)?)
+            }
+            _ => None,
+        };
+        self.check_overwrite()?;
+        Ok(lock)
+    }
+
+    /// Refuse to replace an existing, non-empty `-o` file unless `--force`
+    /// says to. Special files such as `/dev/null` are always written.
+    fn check_overwrite(&self) -> Result<(), Error> {
+        let path = match &self.outfile {
+            Some(path) if !self.force && self.resumed_output().is_none() => path,
+            _ => return Ok(()),
+        };
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() && metadata.len() > 0 => {
+                Err(Error::InvalidConfig(format!(
+                    "{} already exists; use --force to overwrite it or --append-output to continue it",
+                    path.display()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Describe everything that affects the generated words, for cache keys.
+    fn pipeline_repr(&self) -> String {
+        let mut pipeline = self.clone();
+        pipeline.verbose = 0;
+        pipeline.quiet = false;
+        pipeline.wordlist = vec![];
+        pipeline.cmd = None;
+        pipeline.outfile = None;
+        pipeline.append_output = false;
+        pipeline.no_atomic = false;
+        pipeline.force = false;
+        pipeline.verify = None;
+        pipeline.resume_from_output = None;
+        pipeline.cache_dir = None;
+        // expanded into the options they set
+        pipeline.recipe = None;
+        pipeline.recipe_dir = None;
+        pipeline.buffer_size = None;
+        pipeline.threads = None;
+        pipeline.memory_limit = None;
+        pipeline.confirm_over = None;
+        pipeline.yes = false;
+        pipeline.summary_json = None;
+        // hashed by content, with the option naming each, through `inputs()`
+        pipeline.templates = None;
+        pipeline.word2_list = None;
+        pipeline.leet_map = None;
+        pipeline.potfile = None;
+        pipeline.markov = None;
+        pipeline.grammar = None;
+        pipeline.script = None;
+        // read into `sub`
+        pipeline.rules = None;
+        pipeline.follow_rules = false;
+        // a picked seed doesn't make one unseeded run differ from another
+        if self.seed_picked {
+            pipeline.seed = None;
+        }
+        pipeline.seed_picked = false;
+        format!("{:?}", pipeline)
+    }
+
+    /// Identifies the full candidate sequence, whichever range of it is
+    /// generated, so separate processes can check they agree.
+    /// The seed is left out, as an unseeded run picks one per process.
+    fn sequence_fingerprint(&self) -> Result<u64, Error> {
+        let mut pipeline = self.clone();
+        pipeline.start_index = None;
+        pipeline.end_index = None;
+        pipeline.seed = None;
+        cache::fingerprint(&self.inputs(), &pipeline.pipeline_repr())
+    }
+
+    /// Every file the output depends on, with the option it was given to.
+    fn inputs(&self) -> Vec<(&'static str, PathBuf)> {
+        [
+            ("wordlist", self.wordlist.as_slice()),
+            ("templates", self.templates.as_slice()),
+            ("word2-list", self.word2_list.as_slice()),
+            ("leet-map", self.leet_map.as_slice()),
+            ("potfile", self.potfile.as_slice()),
+            ("markov", self.markov.as_slice()),
+            ("grammar", self.grammar.as_slice()),
+            ("script", self.script.as_slice()),
+        ]
+        .into_iter()
+        .flat_map(|(option, paths)| paths.iter().map(move |path| (option, path.clone())))
+        .collect()
+    }
+
+    /// Build the configured pipeline over the wordlists at `paths`.
+    fn wordlist_at(&self, paths: &[PathBuf]) -> Result<Wordlist, Error> {
+        // a single occurrence is a delimited list, repeated flags are
+        // taken literally, one value each
+        let split = |values: &Vec<String>| match values.as_slice() {
+            [single] => split_values(single, &self.delimiter),
+            _ => values.clone(),
+        };
+        let mut extensions = split(&self.extensions);
+        for name in &self.ext_preset {
+            let preset = presets::extension_preset(name).ok_or_else(|| {
+                Error::InvalidConfig(format!("unknown extension preset '{}'", name))
+            })?;
+            for ext in preset {
+                if !extensions.iter().any(|e| e == ext) {
+                    extensions.push(ext.to_string());
+                }
+            }
+        }
+        if self.auto_dot {
+            for ext in extensions.iter_mut() {
+                if !ext.is_empty() && !ext.starts_with('.') {
+                    ext.insert(0, '.');
+                }
+            }
+        }
+        let mut appends = split(&self.append);
+        for name in &self.suffix_preset {
+            let preset = presets::suffix_preset(name)
+                .ok_or_else(|| Error::InvalidConfig(format!("unknown suffix preset '{}'", name)))?;
+            for suffix in preset {
+                if !appends.iter().any(|a| a == suffix) {
+                    appends.push(suffix.to_string());
+                }
+            }
+        }
+        let generated = match &self.markov {
+            Some(path) => {
+                let model = MarkovModel::read(path).map_err(|e| Error::Input(path.clone(), e))?;
+                let mut words = vec![];
+                model.generate(self.enum_ordered, self.markov_max_length, |word| {
+                    words.push(word);
+                    words.len() < self.markov_count
+                });
+                Some(words)
+            }
+            // filled in by `set_patterns`, `set_regex`, `set_grammar`,
+            // `set_pins` or `set_phones` below
+            None if !self.pattern.is_empty()
+                || self.regex.is_some()
+                || self.grammar.is_some()
+                || matches!(self.mode.as_str(), "pins" | "phone") =>
+            {
+                Some(vec![])
+            }
+            None => None,
+        };
+        let mut wl = match generated {
+            Some(words) => Wordlist::with_words(
+                words,
+                split(&self.prepend),
+                appends,
+                split(&self.swap),
+                extensions,
+            ),
+            None => {
+                let mut wl = Wordlist::try_with_values(
+                    &paths[0],
+                    split(&self.prepend),
+                    appends,
+                    split(&self.swap),
+                    extensions,
+                )?;
+                for path in &paths[1..] {
+                    wl.add_wordlist(path)
+                        .map_err(|e| Error::Input(path.clone(), e))?;
+                }
+                wl
+            }
+        };
+        // named in errors about the configured input
+        let input = match &self.markov {
+            Some(path) => path.clone(),
+            None => paths.first().cloned().unwrap_or_default(),
+        };
+        let format = match self.input_format.as_str() {
+            "csv" | "tsv" => {
+                if self.column == 0 {
+                    return Err(Error::InvalidConfig("--column counts from 1".to_string()));
+                }
+                Some(InputFormat::Delimited {
+                    delimiter: if self.input_format == "csv" {
+                        ','
+                    } else {
+                        '\t'
+                    },
+                    column: self.column,
+                    header: self.header,
+                })
+            }
+            "jsonl" => {
+                let field = self.field.clone().ok_or_else(|| {
+                    Error::InvalidConfig("--input-format jsonl needs --field".to_string())
+                })?;
+                Some(InputFormat::Jsonl { field })
+            }
+            _ => None,
+        };
+        if let Some(format) = format {
+            wl.set_input_format(format)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+        }
+        if let Some(token) = &self.swap_token {
+            wl.set_swap_token(token)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+        } else if self.token_open.is_some() || self.token_close.is_some() {
+            wl.set_token_delimiters(
+                self.token_open.as_deref().unwrap_or("{"),
+                self.token_close.as_deref().unwrap_or("}"),
+            )
+            .map_err(|e| Error::Input(input.clone(), e))?;
+        }
+        wl.set_random(self.rand_count, self.seed.unwrap_or_default())
+            .map_err(|e| Error::Input(input.clone(), e))?;
+        if let Some(path) = &self.templates {
+            let templates = fs::read_to_string(path)
+                .map_err(|e| Error::Input(path.clone(), e))?
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect();
+            wl.set_templates(templates)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+        }
+        if let Some(path) = &self.word2_list {
+            let words = fs::read_to_string(path)
+                .map_err(|e| Error::Input(path.clone(), e))?
+                .lines()
+                .map(str::to_string)
+                .collect();
+            wl.set_word2(words)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+        }
+        for var in &self.var {
+            let (name, value) = var.split_once('=').ok_or_else(|| {
+                Error::InvalidConfig(format!("--var expects key=value, got '{}'", var))
+            })?;
+            wl.set_var(name, value);
+        }
+        if self.nest_depth > 0 {
+            wl.set_nest_depth(self.nest_depth)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+        }
+        wl.apply = self
+            .apply
+            .iter()
+            .map(|rule| rule.parse().map_err(Error::InvalidConfig))
+            .collect::<Result<_, _>>()?;
+        wl.directives = self.directives;
+        if self.carry_columns {
+            wl.set_metadata_columns(true)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+        }
+        wl.likelihood_order = self.order_by_likelihood;
+        wl.interleave = self.interleave;
+        wl.set_follow(self.follow);
+        wl.reverse = self.reverse_order;
+        wl.skip_degenerate = self.skip_degenerate;
+        wl.ext_strip = self.ext_strip;
+        let mut mutations = vec![];
+        if let Some(path) = &self.leet_map {
+            let text = fs::read_to_string(path).map_err(|e| Error::Input(path.clone(), e))?;
+            let map = LeetMap::from_toml(&text)
+                .map_err(|e| Error::InvalidConfig(format!("{}: {}", path.display(), e)))?;
+            mutations.push(Mutation::Leet(map));
+        }
+        if self.transpose {
+            mutations.push(Mutation::Transpose);
+        }
+        if self.keyboard_typos {
+            mutations.push(Mutation::KeyboardTypo(self.layout));
+        }
+        if self.delete_each {
+            mutations.push(Mutation::DeleteEach);
+        }
+        if let Some(charset) = &self.insert_each {
+            let chars = mutate::parse_charset(charset).map_err(Error::InvalidConfig)?;
+            mutations.push(Mutation::InsertEach(chars));
+        }
+        for spec in &self.overwrite {
+            mutations.push(mutate::parse_overwrite(spec).map_err(Error::InvalidConfig)?);
+        }
+        if self.mode == "fuzz" {
+            mutations.push(Mutation::Fuzz);
+        }
+        if !mutations.is_empty() {
+            wl.set_mutations(mutations)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+        }
+        if !self.sub.is_empty() {
+            let rules = self
+                .sub
+                .iter()
+                .map(|rule| rule.parse().map_err(Error::InvalidConfig))
+                .collect::<Result<_, _>>()?;
+            wl.set_subs(rules)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+        }
+        wl.set_hybrid_masks(
+            self.hybrid_prepend.as_deref(),
+            self.hybrid_append.as_deref(),
+        )
+        .map_err(|e| Error::InvalidConfig(e.to_string()))?;
+        let mut wraps = vec![];
+        for name in &self.wrap_preset {
+            let preset = presets::wrap_preset(name)
+                .ok_or_else(|| Error::InvalidConfig(format!("unknown wrap preset '{}'", name)))?;
+            for &(before, after) in preset {
+                let wrap = (before.to_string(), after.to_string());
+                if !wraps.contains(&wrap) {
+                    wraps.push(wrap);
+                }
+            }
+        }
+        wl.set_wraps(wraps);
+        if let Some(chain) = &self.encode {
+            let chain = chain
+                .split(',')
+                .map(|name| name.parse().map_err(Error::InvalidConfig))
+                .collect::<Result<_, _>>()?;
+            let scope = if self.encode_each && !self.encode_final {
+                EncodeScope::Each
+            } else {
+                EncodeScope::Final
+            };
+            wl.set_encoders(chain, scope);
+        }
+        if self.weighted {
+            let top = self.top.unwrap_or(usize::MAX);
+            wl.load_weighted_top(self.min_count.unwrap_or(0), top)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+        }
+        if self.mode == "passphrase" {
+            let words = match parse_lengths(&self.words).map_err(Error::InvalidConfig)?[..] {
+                [ref words] if *words.end() != usize::MAX && *words.start() > 0 => words.clone(),
+                _ => {
+                    return Err(Error::InvalidConfig(format!(
+                        "--words expects a count or range such as '2-4', got '{}'",
+                        self.words
+                    )))
+                }
+            };
+            let casings: Vec<Casing> = match &self.phrase_case {
+                Some(casings) => casings
+                    .split(',')
+                    .map(|casing| casing.parse().map_err(Error::InvalidConfig))
+                    .collect::<Result<_, _>>()?,
+                None => vec![],
+            };
+            let joiners = match &self.joiners {
+                Some(joiners) => split_values(joiners, &self.delimiter),
+                None => vec![],
+            };
+            wl.set_passphrases(words, &casings, joiners)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+        }
+        if self.mode == "pins" {
+            let digits = match parse_lengths(&self.length).map_err(Error::InvalidConfig)?[..] {
+                [ref digits] if *digits.start() > 0 && *digits.end() <= pin::MAX_LENGTH => {
+                    digits.clone()
+                }
+                _ => {
+                    return Err(Error::InvalidConfig(format!(
+                    "--length expects a digit count or range from 1 to {} such as '4-8', got '{}'",
+                    pin::MAX_LENGTH,
+                    self.length
+                )))
+                }
+            };
+            wl.set_pins(digits)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+        }
+        if !self.pattern.is_empty() {
+            wl.set_patterns(&self.pattern)
+                .map_err(|e| Error::InvalidConfig(e.to_string()))?;
+        }
+        if let Some(regex) = &self.regex {
+            wl.set_regex(regex)
+                .map_err(|e| Error::InvalidConfig(format!("--regex: {}", e)))?;
+        }
+        if let Some(path) = &self.grammar {
+            wl.set_grammar(path).map_err(|e| match e.kind() {
+                io::ErrorKind::InvalidData => {
+                    Error::InvalidConfig(format!("{}: {}", path.display(), e))
+                }
+                _ => Error::Input(path.clone(), e),
+            })?;
+        }
+        if self.mode == "phone" {
+            let area_codes =
+                split_values(self.area_codes.as_deref().unwrap_or(""), &self.delimiter);
+            let formats = match &self.phone_formats {
+                Some(formats) => split_values(formats, &self.delimiter),
+                None => vec![],
+            };
+            wl.set_phones(self.region, &area_codes, &formats)
+                .map_err(|e| Error::InvalidConfig(e.to_string()))?;
+        }
+        if let Some(lengths) = &self.keep_lengths {
+            wl.lengths = parse_lengths(lengths).map_err(Error::InvalidConfig)?;
+        } else if let Some(n) = self.top_length_buckets {
+            wl.lengths = wl
+                .top_length_buckets(n)
+                .map_err(|e| Error::Input(input.clone(), e))?;
+            debug!("Keeping lengths {:?}", wl.lengths);
+        }
+        Ok(wl)
+    }
+}
+
+/// Route log records to stderr, keeping stdout free for words.
+///
+/// `RUST_LOG` still takes precedence over the `-v` count when set, but
+/// `--quiet` silences everything.
+fn init_logging(verbosity: u8, quiet: bool) {
+    let level = match verbosity {
+        _ if quiet => LevelFilter::Off,
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level);
+    if !quiet {
+        builder.parse_default_env();
+    }
+    builder
+        .target(env_logger::Target::Stderr)
+        .format(|buf, record| {
+            let tag = match record.level() {
+                Level::Error | Level::Warn => "[!]",
+                Level::Info => "[*]",
+                Level::Debug => "[-]",
+                Level::Trace => "[.]",
+            };
+            writeln!(buf, "{} {}", tag, record.args())
+        })
+        .init();
 }
 
-fn main() -> io::Result<()> {
-    let args = Args::from_args();
-    let mut count: usize = 0;
+fn main() {
+    let command: Vec<OsString> = std::env::args_os().collect();
+    let mut args = match Args::parse_options(&command) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("[!] {}", e);
+            std::process::exit(e.exit_code());
+        }
+    };
+    init_logging(args.verbose, args.quiet);
+
+    if let Err(e) = args.load() {
+        error!("{}", e);
+        std::process::exit(e.exit_code());
+    }
+
+    // an unseeded run still uses a single seed throughout, logged so the
+    // output can be repeated with --seed
+    if args.seed.is_none() {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        debug!("Random seed: {}", seed);
+        args.seed = Some(seed);
+        args.seed_picked = true;
+    }
+
+    if let Err(e) = ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::SeqCst)) {
+        warn!("Failed to install interrupt handler: {}", e);
+    }
+
+    let result = match &args.cmd {
+        Some(Command::Bench(bench)) => bench::run(&args, bench),
+        Some(Command::Plan(plan)) => plan::run(&args, plan),
+        Some(Command::Coordinate(coordinate)) => coordinator::run(&args, coordinate),
+        Some(Command::Work(work)) => worker::run(&args, work),
+        Some(Command::FromPot(from_pot)) => from_pot::run(&args, from_pot),
+        Some(Command::Freq(freq)) => freq::run(&args, freq),
+        Some(Command::Train(train)) => train::run(train),
+        None if args.watch => watch::run(&command, args.clone()),
+        None => run(&args),
+    };
+
+    if let Err(e) = result {
+        if !matches!(e, Error::Interrupted) {
+            error!("{}", e);
+        }
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn validate(args: &Args) -> Result<(), Error> {
+    if args.delimiter.is_empty() {
+        return Err(Error::InvalidConfig(
+            "--delimiter must not be empty".to_string(),
+        ));
+    }
+    if args.swap_token.as_deref() == Some("") {
+        return Err(Error::InvalidConfig(
+            "swap token must not be empty".to_string(),
+        ));
+    }
+    if args.follow && args.wordlist.is_empty() {
+        return Err(Error::InvalidConfig(
+            "--follow needs a wordlist file (-w) to follow".to_string(),
+        ));
+    }
+    if matches!(args.mode.as_str(), "pins" | "phone") {
+        if !args.wordlist.is_empty()
+            || args.markov.is_some()
+            || !args.pattern.is_empty()
+            || args.regex.is_some()
+            || args.grammar.is_some()
+        {
+            return Err(Error::InvalidConfig(format!(
+                "--mode {} generates its own base words and takes no --wordlist, --markov, --pattern, --regex or --grammar",
+                args.mode
+            )));
+        }
+    } else if args.wordlist.is_empty()
+        && args.markov.is_none()
+        && args.pattern.is_empty()
+        && args.regex.is_none()
+        && args.grammar.is_none()
+    {
+        return Err(Error::InvalidConfig("--wordlist is required".to_string()));
+    }
+    // where an unordered run stops says nothing about what it has covered
+    if args.unordered
+        && (args.end_index.is_some() || args.resume_from_output.is_some() || args.append_output)
+    {
+        return Err(Error::InvalidConfig(
+            "--unordered cannot be combined with --end-index, --append-output or --resume-from-output"
+                .to_string(),
+        ));
+    }
+    // resuming takes each line already written for one candidate
+    if (args.resume_from_output.is_some() || args.append_output) && args.filters_output() {
+        return Err(Error::InvalidConfig(
+            "--append-output and --resume-from-output cannot be combined with --unique, --unique-window, --potfile, --script or --filter-exec"
+                .to_string(),
+        ));
+    }
+    for input in args
+        .templates
+        .iter()
+        .chain(args.word2_list.iter())
+        .chain(args.leet_map.iter())
+        .chain(args.potfile.iter())
+        .chain(args.markov.iter())
+        .chain(args.grammar.iter())
+        .chain(args.script.iter())
+    {
+        if !input.is_file() {
+            return Err(Error::InputNotFound(input.clone()));
+        }
+    }
+    for wordlist in &args.wordlist {
+        if !wordlist.is_file() {
+            return Err(Error::InputNotFound(wordlist.clone()));
+        }
+        for outfile in args.outfile.iter().chain(args.resume_from_output.iter()) {
+            if outfile.canonicalize().ok() == wordlist.canonicalize().ok() {
+                return Err(Error::InvalidConfig(
+                    "output file would overwrite an input wordlist".to_string(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Require confirmation before generating a keyspace above `--confirm-over`.
+fn confirm_keyspace(args: &Args, keyspace: u128) -> Result<(), Error> {
+    let threshold = match args.confirm_over {
+        Some(threshold) => threshold,
+        None => return Ok(()),
+    };
+    if (keyspace as f64) <= threshold || args.yes {
+        return Ok(());
+    }
+
+    eprint!(
+        "[?] Keyspace is {} words (over {}). Continue? [y/N] ",
+        keyspace, threshold
+    );
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        eprintln!();
+        return Err(Error::KeyspaceTooLarge);
+    }
+    let mut answer = String::new();
+    stdin
+        .read_line(&mut answer)
+        .map_err(|e| Error::Input(PathBuf::from("standard input"), e))?;
+    match answer.trim() {
+        "y" | "Y" | "yes" => Ok(()),
+        _ => Err(Error::KeyspaceTooLarge),
+    }
+}
+
+/// Buffered writer for a freshly created output file.
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+fn file_writer(file: fs::File, buffer_size: usize) -> Box<dyn Write + Send> {
+    match uring::UringWriter::new(&file, buffer_size) {
+        Ok(writer) => {
+            debug!("Writing output through io_uring");
+            Box::new(writer)
+        }
+        Err(e) => {
+            debug!("io_uring unavailable, using buffered writes: {}", e);
+            Box::new(BufWriter::with_capacity(buffer_size, file))
+        }
+    }
+}
+
+/// Buffered writer for a freshly created output file.
+#[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+fn file_writer(file: fs::File, buffer_size: usize) -> Box<dyn Write + Send> {
+    Box::new(BufWriter::with_capacity(buffer_size, file))
+}
+
+fn run(args: &Args) -> Result<(), Error> {
+    validate(args)?;
+    let _lock = args.claim_output()?;
+
+    let started = Instant::now();
     let file;
-    let stdout = stdout();
-    let stdout_lock = stdout.lock();
 
-    let mut writer: Box<dyn Write> = match args.outfile {
-        None => Box::new(BufWriter::new(stdout_lock)),
-        Some(filename) => {
-            file = std::fs::File::create(filename)?;
-            Box::new(BufWriter::new(file))
+    let buffer_size = args.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
+
+    let mut start_index = args.start_index.unwrap_or(0);
+    let mut resume_last = None;
+    let mut staged = None;
+
+    let mut writer: Box<dyn Write + Send> = if let Some(path) = args.resumed_output() {
+        let tail = resume::inspect(path).map_err(|e| Error::Input(path.clone(), e))?;
+        info!(
+            "Resuming after {} words already in {}",
+            tail.lines,
+            path.display()
+        );
+        // drop a torn final line so the next word starts on a fresh line
+        file = OpenOptions::new().append(true).open(path)?;
+        file.set_len(tail.complete_len)?;
+        start_index += tail.lines;
+        resume_last = tail.last;
+        Box::new(BufWriter::with_capacity(buffer_size, file))
+    } else {
+        match args.outfile.clone() {
+            None => Box::new(BufWriter::with_capacity(buffer_size, stdout())),
+            // a followed run's output is read while it grows
+            Some(filename) if !args.no_atomic && !args.follow && Staged::supported(&filename) => {
+                let (output, created) = Staged::create(&filename)?;
+                staged = Some(output);
+                file = created;
+                file_writer(file, buffer_size)
+            }
+            Some(filename) => {
+                file = std::fs::File::create(filename)?;
+                file_writer(file, buffer_size)
+            }
         }
     };
 
-    let wl = Wordlist::new(
-        &args.wordlist,
-        args.prepend,
-        args.append,
-        args.swap,
-        args.extensions,
+    let mut wl = args.wordlist_at(&args.wordlist)?;
+
+    if let Some(last) = &resume_last {
+        // the existing output must end exactly where our ordering says
+        wl.seek_to(start_index - 1);
+        let expected = wl.next_with_origin().map(|(word, _)| word);
+        // the word may carry columns and be followed by an annotation,
+        // both after tabs
+        let matches = expected.is_some_and(|word| {
+            last.strip_prefix(word.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('\t'))
+        });
+        if !matches {
+            return Err(Error::InvalidConfig(format!(
+                "existing output does not match this configuration at word {}",
+                start_index - 1
+            )));
+        }
+    }
+
+    // positions count up to the end of the range, not of the keyspace
+    let bound = match args.end_index {
+        Some(end) => wl.keyspace().min(end as u128),
+        None => wl.keyspace(),
+    };
+    if bound >= usize::MAX as u128 {
+        return Err(Error::KeyspaceTooLarge);
+    }
+    confirm_keyspace(args, wl.keyspace())?;
+
+    let mut summary = Summary {
+        input_words: wl.base_count,
+        keyspace: wl.keyspace(),
+        seed: args.seed,
+        ..Default::default()
+    };
+
+    // a cache entry holds a whole run, not the rest of one, and a verified
+    // run generates its words rather than replaying them
+    let cache = match &args.cache_dir {
+        Some(dir) if args.resumed_output().is_none() && args.verify.is_none() => {
+            Some(Cache::new(dir, &args.inputs(), &args.pipeline_repr())?)
+        }
+        _ => None,
+    };
+
+    if let Some(cache) = &cache {
+        if cache.is_hit() {
+            let count = cache.replay(&mut writer)?;
+            writer.flush()?;
+            drop(writer);
+            staged.map(Staged::commit).transpose()?;
+            info!("Replayed Cached Output: {}", cache.path().display());
+            info!(
+                "Inflated Wordlist Size: {} in {}",
+                human::count(count as u128),
+                human::duration(started.elapsed())
+            );
+            summary.emitted = count;
+            summary.cached = true;
+            // the run replayed picked a seed of its own
+            if args.seed_picked {
+                summary.seed = None;
+            }
+            return report(args, summary, started);
+        }
+        debug!("Recording Cache Entry: {}", cache.path().display());
+        writer = Box::new(Tee::new(writer, BufWriter::new(cache.record()?)));
+    }
+    let digest = Digest::default();
+    if args.verify.is_some() {
+        writer = Box::new(Tee::new(writer, digest.clone()));
+    }
+
+    info!(
+        "Orginal Wordlist Size: {}",
+        human::count(wl.base_count as u128)
     );
+    info!(
+        "Estimated Inflated Size: {} (~{})",
+        human::count(wl.keyspace()),
+        human::bytes(wl.estimated_bytes())
+    );
+    debug!(
+        "Pipeline: {} prepends, {} appends, {} swaps, {} extensions",
+        wl.prepend.len(),
+        wl.append.len(),
+        wl.swap.len(),
+        wl.extensions.len()
+    );
+
+    let end_index = args.end_index.unwrap_or(usize::MAX);
+    if start_index > 0 {
+        debug!("Seeking to candidate {}", start_index);
+        wl.seek_to(start_index);
+    }
+
+    let mut sink = Sink::new(args, &wl, writer);
+    sink.configure(args)?;
+    // rules still arriving apply to the words after them, so candidates
+    // are generated in order on one thread
+    let follow = args.follow_rules && args.threads.is_none_or(|threads| threads <= 1);
+    if args.follow_rules && !follow {
+        warn!("Rules added while running need a single thread; reading no more");
+    }
+    let live_rules = follow.then(rules::follow);
+    let mut rules_added = 0;
+    // nothing to transform, so input lines are copied in bulk
+    let passthrough = sink.is_plain() && wl.is_passthrough() && !follow;
+    if passthrough {
+        debug!("No transforms configured, copying input through");
+    }
+    // a followed file is inflated as it grows, in order
+    let threads = args
+        .threads
+        .filter(|&threads| threads > 1 && !passthrough && !args.follow);
+    let resume_at = match threads {
+        Some(threads) => {
+            debug!("Expanding on {} threads", threads);
+            // formatted chunks are written out while the next are expanded
+            let inner = mem::replace(&mut sink.writer, Box::new(io::sink()));
+            sink.writer = Box::new(WriterThread::new(inner, buffer_size));
+            let plain = sink.is_plain();
+            let mut pool = ParallelWordlist::new(wl, threads);
+            if args.unordered {
+                pool = pool.unordered();
+            }
+            while pool.position() < end_index && !INTERRUPTED.load(Ordering::Relaxed) {
+                let mut chunk = match pool.next() {
+                    Some(chunk) => chunk,
+                    None => break,
+                };
+                chunk.truncate(end_index - chunk.start);
+                if plain {
+                    sink.write_chunk(&chunk)?;
+                    continue;
+                }
+                for (word, origin) in chunk.iter() {
+                    sink.emit(pool.wordlist(), word, origin)?;
+                }
+            }
+            let resume_at = pool.position().min(end_index);
+            wl = pool.into_inner();
+            resume_at
+        }
+        None => {
+            // without per-word filtering or annotation, format candidates
+            // straight into the writer
+            let plain = sink.is_plain();
+            while wl.position() < end_index && !INTERRUPTED.load(Ordering::Relaxed) {
+                if let Some(rules) = &live_rules {
+                    let added: Vec<_> = rules.try_iter().collect();
+                    if !added.is_empty() {
+                        info!("Applying {} more rules from here on", added.len());
+                        rules_added += added.len();
+                        wl.add_subs(added);
+                    }
+                }
+                let more = if passthrough {
+                    let limit = end_index - wl.position();
+                    sink.write_block(&mut wl, limit)?
+                } else if plain {
+                    sink.write_next(&mut wl)?
+                } else {
+                    match wl.next_with_origin() {
+                        Some((word, origin)) => {
+                            sink.emit(&wl, &word, &origin)?;
+                            true
+                        }
+                        None => false,
+                    }
+                };
+                if !more {
+                    if !args.follow {
+                        break;
+                    }
+                    // caught up with the followed file; wait for it to grow
+                    sink.flush(&wl)?;
+                    thread::sleep(FOLLOW_INTERVAL);
+                }
+            }
+            wl.position()
+        }
+    };
 
-    if args.verbose {
-        println!("[*] Orginal Wordlist Size: {}", wl.base_count);
-        println!("[*] Estimated Inflated Size: {}", wl.total_count);
+    sink.finish(&wl)?;
+    sink.writer.flush()?;
+    let Sink {
+        writer,
+        stats,
+        generated,
+        count,
+        bytes,
+        duplicates_removed,
+        filtered,
+        coverage,
+        ..
+    } = sink;
+    drop(writer);
+    summary.emitted = count;
+    summary.duplicates_removed = duplicates_removed;
+    summary.filtered = filtered;
+    summary.transforms = stats.hits(&wl);
+    Stats::log(&summary.transforms);
+    summary.coverage = coverage.map(|coverage| coverage.report());
+    if let Some(report) = &summary.coverage {
+        Coverage::log(report);
     }
 
-    for word in wl {
-        writer.write(word.as_bytes())?;
-        writer.write(b"\n")?;
-        count += 1;
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        warn!("Interrupted after {} words; output was flushed", count);
+        // left under its temporary name, so nothing takes it for a whole list
+        let partial = staged.map(Staged::keep);
+        if let Some(partial) = &partial {
+            warn!("Partial output kept in {}", partial.display());
+        }
+        match (&partial, &args.outfile) {
+            _ if args.unordered => warn!("Unordered output cannot be resumed"),
+            (Some(partial), Some(dest)) => warn!(
+                "Resume with --resume-from-output {}, then move it to {}",
+                partial.display(),
+                dest.display()
+            ),
+            _ => warn!("Resume with --start-index {}", resume_at),
+        }
+        summary.interrupted = true;
+        report(args, summary, started)?;
+        return Err(Error::Interrupted);
     }
 
-    writer.flush()?;
+    // rules added while running aren't part of the cached pipeline
+    if let Some(cache) = cache.as_ref().filter(|_| rules_added == 0) {
+        cache.commit()?;
+    }
+    if let Some(hash) = &args.verify {
+        if rules_added > 0 {
+            warn!("Not verified: rules added while running changed the keyspace");
+        } else {
+            // counted apart from this run, exactly, even where filters
+            // make the keyspace an upper bound
+            let (_, end) = plan::range(args)?;
+            let expected = (end as u128).saturating_sub(start_index as u128);
+            verify::check(expected, generated, &digest, hash.as_deref())?;
+            summary.output_hash = Some(digest.hex());
+        }
+    }
+    staged.map(Staged::commit).transpose()?;
 
-    if args.verbose {
-        println!("[*] Inflated Wordlist Size: {}", count);
+    if summary.filtered > 0 {
+        info!(
+            "Already Cracked: {}",
+            human::count(summary.filtered as u128)
+        );
     }
+    if summary.duplicates_removed > 0 {
+        info!(
+            "Duplicates Removed: {}",
+            human::count(summary.duplicates_removed as u128)
+        );
+    }
+    info!(
+        "Inflated Wordlist Size: {} ({}) in {}",
+        human::count(count as u128),
+        human::bytes(bytes),
+        human::duration(started.elapsed())
+    );
 
-    return Ok(());
+    report(args, summary, started)
+}
+
+fn report(args: &Args, mut summary: Summary, started: Instant) -> Result<(), Error> {
+    if let Some(dest) = &args.summary_json {
+        summary.finish(started);
+        summary.write(dest.as_ref().map(PathBuf::from))?;
+    }
+    Ok(())
 }
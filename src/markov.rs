@@ -0,0 +1,294 @@
+//! Character-level Markov statistics trained on a corpus of real
+//! passwords, for generating candidates that look like them.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Model file layout written by [`MarkovModel::write`].
+pub const MODEL_VERSION: u32 = 1;
+
+/// Highest level an n-gram is given; rarer ones are rounded down to it.
+pub const MAX_LEVEL: u32 = 10;
+
+/// n-gram counts of a corpus: how words start, which character follows
+/// each run of `order - 1` characters, and how long words are.
+///
+/// Stored as JSON, conventionally with a `.wlm` extension.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkovModel {
+    pub version: u32,
+    /// Length of the n-grams counted, at least 2.
+    pub order: usize,
+    /// Words of each length, in characters.
+    pub lengths: BTreeMap<usize, u64>,
+    /// Times each `order - 1` character prefix starts a word.
+    pub starts: BTreeMap<String, u64>,
+    /// Times each character follows an `order - 1` character context.
+    pub transitions: BTreeMap<String, BTreeMap<char, u64>>,
+}
+
+impl MarkovModel {
+    /// An untrained model counting n-grams of `order` characters.
+    pub fn new(order: usize) -> io::Result<Self> {
+        if order < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Markov order must be at least 2",
+            ));
+        }
+        Ok(Self {
+            version: MODEL_VERSION,
+            order,
+            lengths: BTreeMap::new(),
+            starts: BTreeMap::new(),
+            transitions: BTreeMap::new(),
+        })
+    }
+
+    /// Count the n-grams of `word`. Words shorter than a context only add
+    /// to the length counts.
+    pub fn add(&mut self, word: &str) {
+        let chars: Vec<char> = word.chars().collect();
+        *self.lengths.entry(chars.len()).or_default() += 1;
+        let context = self.order - 1;
+        if chars.len() < context {
+            return;
+        }
+        let start: String = chars[..context].iter().collect();
+        *self.starts.entry(start).or_default() += 1;
+        for gram in chars.windows(self.order) {
+            let (prefix, next) = gram.split_at(context);
+            *self
+                .transitions
+                .entry(prefix.iter().collect())
+                .or_default()
+                .entry(next[0])
+                .or_default() += 1;
+        }
+    }
+
+    /// Number of words trained on.
+    pub fn words(&self) -> u64 {
+        self.lengths.values().sum()
+    }
+
+    /// Generate words up to `max_length` characters, calling `f` with each
+    /// until it returns `false` or none are left.
+    ///
+    /// Every length, start and transition is given a level, `-ln` of its
+    /// probability rounded to a whole number (at most [`MAX_LEVEL`]), and a
+    /// word's level is the sum of those it uses. With `ordered`, words are
+    /// enumerated as in OMEN: all words of level 0, then all of level 1,
+    /// and so on, so more likely words strictly come first. Otherwise each
+    /// length is walked depth-first, taking the likeliest branch first.
+    pub fn generate(&self, ordered: bool, max_length: usize, mut f: impl FnMut(String) -> bool) {
+        let levels = Levels::new(self, max_length);
+        if !ordered {
+            for &(len, len_level) in &levels.lengths {
+                if !levels.walk_starts(len, u32::MAX - len_level, false, &mut f) {
+                    return;
+                }
+            }
+            return;
+        }
+        let transitions = max_length.saturating_sub(self.order - 1) as u32;
+        let max_total = MAX_LEVEL.saturating_mul(transitions + 2);
+        for target in 0..=max_total {
+            for &(len, len_level) in &levels.lengths {
+                if len_level > target {
+                    continue;
+                }
+                if !levels.walk_starts(len, target - len_level, true, &mut f) {
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let model: Self = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        if model.version != MODEL_VERSION || model.order < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported model version {}", model.version),
+            ));
+        }
+        Ok(model)
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut out, self)?;
+        out.write_all(b"\n")?;
+        out.flush()
+    }
+}
+
+/// Level of an n-gram seen `count` times out of `total`.
+fn level(count: u64, total: u64) -> u32 {
+    let p = count as f64 / total.max(1) as f64;
+    (-p.ln()).round().clamp(0.0, MAX_LEVEL as f64) as u32
+}
+
+fn by_level<T>(counts: &mut [(T, u32)]) {
+    counts.sort_by_key(|&(_, level)| level);
+}
+
+/// A model's counts as levels, each list likeliest first.
+struct Levels {
+    context: usize,
+    lengths: Vec<(usize, u32)>,
+    starts: Vec<(Vec<char>, u32)>,
+    transitions: HashMap<Vec<char>, Vec<(char, u32)>>,
+}
+
+impl Levels {
+    fn new(model: &MarkovModel, max_length: usize) -> Self {
+        let context = model.order - 1;
+        let total = model.words();
+        let mut lengths: Vec<_> = model
+            .lengths
+            .iter()
+            .filter(|(&len, _)| len >= context && len <= max_length && len > 0)
+            .map(|(&len, &count)| (len, level(count, total)))
+            .collect();
+        by_level(&mut lengths);
+        let total = model.starts.values().sum();
+        let mut starts: Vec<_> = model
+            .starts
+            .iter()
+            .map(|(start, &count)| (start.chars().collect(), level(count, total)))
+            .collect();
+        by_level(&mut starts);
+        let transitions = model
+            .transitions
+            .iter()
+            .map(|(context, next)| {
+                let total = next.values().sum();
+                let mut next: Vec<_> = next
+                    .iter()
+                    .map(|(&c, &count)| (c, level(count, total)))
+                    .collect();
+                by_level(&mut next);
+                (context.chars().collect(), next)
+            })
+            .collect();
+        Self {
+            context,
+            lengths,
+            starts,
+            transitions,
+        }
+    }
+
+    /// Walk words of `len` characters from every start within `budget`,
+    /// returning `false` once `f` has asked to stop.
+    fn walk_starts(
+        &self,
+        len: usize,
+        budget: u32,
+        exact: bool,
+        f: &mut impl FnMut(String) -> bool,
+    ) -> bool {
+        for (start, start_level) in &self.starts {
+            if *start_level > budget {
+                break;
+            }
+            let mut word = start.clone();
+            if !self.walk(&mut word, len, budget - start_level, exact, f) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Extend `word` to `len` characters depth-first, spending at most
+    /// `budget` levels, or exactly all of it when `exact`.
+    fn walk(
+        &self,
+        word: &mut Vec<char>,
+        len: usize,
+        budget: u32,
+        exact: bool,
+        f: &mut impl FnMut(String) -> bool,
+    ) -> bool {
+        if word.len() == len {
+            if exact && budget != 0 {
+                return true;
+            }
+            return f(word.iter().collect());
+        }
+        let next = match self.transitions.get(&word[word.len() - self.context..]) {
+            Some(next) => next,
+            None => return true,
+        };
+        for &(c, level) in next {
+            if level > budget {
+                break;
+            }
+            word.push(c);
+            let more = self.walk(word, len, budget - level, exact, f);
+            word.pop();
+            if !more {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_train() {
+        let mut model = MarkovModel::new(3).unwrap();
+        for word in ["pass1", "password", "p"] {
+            model.add(word);
+        }
+        assert!(model.words() == 3);
+        assert!(model.starts["pa"] == 2);
+        assert!(model.transitions["pa"][&'s'] == 2);
+        assert!(model.transitions["ss"][&'1'] == 1);
+        assert!(model.transitions["ss"][&'w'] == 1);
+        assert!(model.lengths[&1] == 1);
+
+        let path =
+            &std::env::temp_dir().join(format!("wlinflate_test_model_{}.wlm", std::process::id()));
+        model.write(path).unwrap();
+        assert!(MarkovModel::read(path).unwrap() == model);
+        std::fs::remove_file(path).unwrap();
+        assert!(MarkovModel::new(1).is_err());
+    }
+
+    #[test]
+    fn test_generate() {
+        let mut model = MarkovModel::new(2).unwrap();
+        for word in ["ab", "ab", "ab", "ab", "ac", "ba", "abc"] {
+            model.add(word);
+        }
+        let generate = |ordered| {
+            let mut words = vec![];
+            model.generate(ordered, 3, |word| {
+                words.push(word);
+                words.len() < 5
+            });
+            words
+        };
+        let ordered = generate(true);
+        println!("test_generate: {:?}", ordered);
+        assert!(ordered[0] == "ab");
+        let mut sorted = ordered.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert!(sorted.len() == ordered.len());
+        assert!(generate(false)[..2] == ["ab", "ac"]);
+    }
+}
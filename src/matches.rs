@@ -0,0 +1,314 @@
+//! Every string a bounded regular expression or a grammar matches,
+//! enumerated in the manner of exrex.
+
+use std::{fs, io, path::Path};
+
+use regex_syntax::hir::{Class, Hir, HirKind, Look};
+
+/// Largest character class a regex may use, so `.` and negated classes,
+/// which stand for most of Unicode, are refused rather than enumerated.
+pub const MAX_CLASS: usize = 1024;
+
+/// A regex or grammar as a tree that can count its strings and produce
+/// the `i`th.
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    Literal(String),
+    Class(Vec<char>),
+    /// One of a list of words, as an alternation of literals.
+    Words(Vec<String>),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        sub: Box<Node>,
+        min: u32,
+        max: u32,
+    },
+}
+
+impl Node {
+    fn from_hir(hir: &Hir) -> Result<Self, String> {
+        Ok(match hir.kind() {
+            HirKind::Empty => Node::Literal(String::new()),
+            HirKind::Literal(literal) => Node::Literal(
+                String::from_utf8(literal.0.to_vec())
+                    .map_err(|_| "regex literals must be valid UTF-8".to_string())?,
+            ),
+            HirKind::Class(class) => {
+                let chars: Vec<char> = match class {
+                    Class::Unicode(class) => {
+                        let size: usize = class
+                            .iter()
+                            .map(|r| r.end() as usize - r.start() as usize + 1)
+                            .sum();
+                        if size > MAX_CLASS {
+                            return Err(format!(
+                                "character class of {} characters is too large to enumerate (at most {})",
+                                size, MAX_CLASS
+                            ));
+                        }
+                        class.iter().flat_map(|r| r.start()..=r.end()).collect()
+                    }
+                    Class::Bytes(class) => class
+                        .iter()
+                        .flat_map(|r| r.start()..=r.end())
+                        .map(|b| {
+                            b.is_ascii()
+                                .then_some(b as char)
+                                .ok_or_else(|| "byte classes must be ASCII".to_string())
+                        })
+                        .collect::<Result<_, _>>()?,
+                };
+                Node::Class(chars)
+            }
+            HirKind::Look(look) => match look {
+                Look::Start
+                | Look::End
+                | Look::StartLF
+                | Look::EndLF
+                | Look::StartCRLF
+                | Look::EndCRLF => Node::Literal(String::new()),
+                _ => return Err(format!("unsupported assertion {:?}", look)),
+            },
+            HirKind::Repetition(repetition) => match repetition.max {
+                Some(max) => Node::Repeat {
+                    sub: Box::new(Node::from_hir(&repetition.sub)?),
+                    min: repetition.min,
+                    max,
+                },
+                None => {
+                    return Err(
+                        "unbounded quantifier ('*', '+' or '{n,}'), give an upper bound such as '{0,8}'"
+                            .to_string(),
+                    )
+                }
+            },
+            HirKind::Capture(capture) => Node::from_hir(&capture.sub)?,
+            HirKind::Concat(subs) => {
+                Node::Concat(subs.iter().map(Node::from_hir).collect::<Result<_, _>>()?)
+            }
+            HirKind::Alternation(subs) => {
+                Node::Alternation(subs.iter().map(Node::from_hir).collect::<Result<_, _>>()?)
+            }
+        })
+    }
+
+    /// Number of strings, saturating at `u128::MAX`.
+    fn count(&self) -> u128 {
+        match self {
+            Node::Literal(_) => 1,
+            Node::Class(chars) => chars.len() as u128,
+            Node::Words(words) => words.len() as u128,
+            Node::Concat(subs) => subs
+                .iter()
+                .fold(1u128, |total, sub| total.saturating_mul(sub.count())),
+            Node::Alternation(subs) => subs
+                .iter()
+                .fold(0u128, |total, sub| total.saturating_add(sub.count())),
+            Node::Repeat { sub, min, max } => {
+                let n = sub.count();
+                (*min..=*max)
+                    .map(|k| n.saturating_pow(k))
+                    .fold(0u128, u128::saturating_add)
+            }
+        }
+    }
+
+    /// Total length of every string in bytes, saturating at `u128::MAX`.
+    fn bytes(&self) -> u128 {
+        match self {
+            Node::Literal(s) => s.len() as u128,
+            Node::Class(chars) => chars.iter().map(|c| c.len_utf8() as u128).sum(),
+            Node::Words(words) => words.iter().map(|w| w.len() as u128).sum(),
+            Node::Concat(subs) => {
+                // each part appears once with every combination of the others
+                let counts: Vec<u128> = subs.iter().map(Node::count).collect();
+                subs.iter()
+                    .enumerate()
+                    .map(|(j, sub)| {
+                        counts
+                            .iter()
+                            .enumerate()
+                            .filter(|&(k, _)| k != j)
+                            .fold(sub.bytes(), |total, (_, &n)| total.saturating_mul(n))
+                    })
+                    .fold(0u128, u128::saturating_add)
+            }
+            Node::Alternation(subs) => subs
+                .iter()
+                .fold(0u128, |total, sub| total.saturating_add(sub.bytes())),
+            Node::Repeat { sub, min, max } => {
+                let (n, bytes) = (sub.count(), sub.bytes());
+                (*min..=*max)
+                    .filter(|&k| k > 0)
+                    .map(|k| {
+                        bytes
+                            .saturating_mul(k as u128)
+                            .saturating_mul(n.saturating_pow(k - 1))
+                    })
+                    .fold(0u128, u128::saturating_add)
+            }
+        }
+    }
+
+    /// Append the `i`th string to `out`. Concatenations vary their last
+    /// part fastest and repetitions give fewer copies first.
+    fn nth(&self, mut i: u128, out: &mut String) {
+        match self {
+            Node::Literal(s) => out.push_str(s),
+            Node::Class(chars) => out.push(chars[i as usize]),
+            Node::Words(words) => out.push_str(&words[i as usize]),
+            Node::Concat(subs) => nth_of_sequence(subs.iter().map(|s| (s, s.count())), i, out),
+            Node::Alternation(subs) => {
+                for sub in subs {
+                    let n = sub.count();
+                    if i < n {
+                        return sub.nth(i, out);
+                    }
+                    i -= n;
+                }
+            }
+            Node::Repeat { sub, min, max } => {
+                let n = sub.count();
+                for k in *min..=*max {
+                    let strings = n.saturating_pow(k);
+                    if i < strings {
+                        return nth_of_sequence((0..k).map(|_| (sub.as_ref(), n)), i, out);
+                    }
+                    i -= strings;
+                }
+            }
+        }
+    }
+}
+
+/// Append the `i`th string of a concatenation of `parts`, each given with
+/// its count.
+fn nth_of_sequence<'a>(
+    parts: impl DoubleEndedIterator<Item = (&'a Node, u128)>,
+    mut i: u128,
+    out: &mut String,
+) {
+    let mut indexes = vec![];
+    for (part, n) in parts.rev() {
+        indexes.push((part, i % n.max(1)));
+        i /= n.max(1);
+    }
+    for (part, index) in indexes.into_iter().rev() {
+        part.nth(index, out);
+    }
+}
+
+/// Every string matching a regex with bounded quantifiers, such as
+/// `^(dev|prod)-(api|web)[0-9]{2}$`, or a grammar (see [`crate::grammar`]).
+///
+/// Alternatives are taken in order, concatenations vary their last part
+/// fastest, and repetitions give fewer copies first. `^` and `$` are
+/// accepted and ignored, as every string is matched whole. A string that
+/// can be matched in more than one way, as `a|a`, is generated once for
+/// each.
+#[derive(Debug, Clone)]
+pub struct Matches {
+    source: String,
+    root: Node,
+    total: u128,
+    next: u128,
+}
+
+impl Matches {
+    /// Parse `pattern`, failing on invalid syntax, unbounded quantifiers,
+    /// and classes larger than [`MAX_CLASS`].
+    pub fn regex(pattern: &str) -> Result<Self, String> {
+        let hir = regex_syntax::parse(pattern).map_err(|e| e.to_string())?;
+        Ok(Self::from_node(pattern.to_string(), Node::from_hir(&hir)?))
+    }
+
+    /// Parse the grammar file at `path` (see [`crate::grammar`]), reading
+    /// its word classes.
+    pub fn grammar(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let root = crate::grammar::parse(&text, dir)?;
+        Ok(Self::from_node(text, root))
+    }
+
+    /// Strings of `root`, described by `source` (the regex or grammar
+    /// text).
+    pub(crate) fn from_node(source: String, root: Node) -> Self {
+        Self {
+            source,
+            total: root.count(),
+            root,
+            next: 0,
+        }
+    }
+
+    /// Start over from the first string.
+    pub fn rewind(&mut self) {
+        self.next = 0;
+    }
+
+    /// The regex or grammar as given.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Number of strings, saturating at `u128::MAX`.
+    pub fn total(&self) -> u128 {
+        self.total
+    }
+
+    /// Total size of every string in bytes, newlines included.
+    pub fn byte_len(&self) -> u64 {
+        let bytes = self.root.bytes().saturating_add(self.total);
+        u64::try_from(bytes).unwrap_or(u64::MAX)
+    }
+
+    /// The `i`th string, if there are that many.
+    pub fn get(&self, i: u128) -> Option<String> {
+        if i >= self.total {
+            return None;
+        }
+        let mut string = String::new();
+        self.root.nth(i, &mut string);
+        Some(string)
+    }
+}
+
+impl Iterator for Matches {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let string = self.get(self.next)?;
+        self.next += 1;
+        Some(string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex() {
+        let matches = Matches::regex("^(dev|prod)-(api|web)[0-9]{2}$").unwrap();
+        assert!(matches.total() == 2 * 2 * 100);
+        let all: Vec<String> = matches.collect();
+        assert!(all.len() == 400);
+        assert!(all[..2] == ["dev-api00", "dev-api01"]);
+        assert!(all[100] == "dev-web00" && all[399] == "prod-web99");
+
+        let matches = Matches::regex("a(b|cd)?é{1,2}").unwrap();
+        assert!(matches.byte_len() == matches.clone().map(|s| s.len() as u64 + 1).sum::<u64>());
+        let optional: Vec<String> = Matches::regex("ab?c{1,2}").unwrap().collect();
+        assert!(optional == ["ac", "acc", "abc", "abcc"]);
+        let all: Vec<String> = Matches::regex("(?i)x[a-b]").unwrap().collect();
+        assert!(all.len() == 8);
+
+        assert!(Matches::regex("a+").is_err());
+        assert!(Matches::regex("a{2,}").is_err());
+        assert!(Matches::regex(".").is_err());
+        assert!(Matches::regex("\\bx").is_err());
+        assert!(Matches::regex("(").is_err());
+    }
+}
@@ -0,0 +1,731 @@
+//! Mutations deriving variants of each base word, such as leetspeak
+//! substitutions, and sed-style rules rewriting it.
+//!
+//! Variants are base words in their own right: each is emitted right after
+//! the word it came from, and affixes are then added to all of them alike.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::keyboard::Layout;
+
+/// One way of deriving variants from a base word.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mutation {
+    /// Character substitutions from a [`LeetMap`].
+    Leet(LeetMap),
+    /// Each pair of adjacent characters swapped in turn, as in the typo
+    /// `apssword`. Swapping two equal characters gives the word back, so
+    /// those pairs are skipped.
+    Transpose,
+    /// One character removed at each position in turn, as in `pssword`.
+    /// Removing either of two equal neighbours gives the same word, so
+    /// only the first is removed, and single characters are left alone.
+    DeleteEach,
+    /// Each character inserted at every position in turn, positions
+    /// varying slowest. An insertion right before the same character is
+    /// skipped, as the insertion after it gives the same word.
+    InsertEach(Vec<char>),
+    /// The character at `position` (0-based, or every position if `None`)
+    /// replaced by each of `chars` other than itself, as hashcat's `o`
+    /// rule. Words too short to have the position are left alone.
+    Overwrite {
+        position: Option<usize>,
+        chars: Vec<char>,
+    },
+    /// Each character replaced in turn by every key next to it on a
+    /// [`Layout`], as in `psssword` or `passeord`. Characters not on the
+    /// layout are left alone.
+    KeyboardTypo(Layout),
+    /// Byte-level fuzzing: every single-bit flip of each ASCII character,
+    /// each character replaced by boundary bytes (NUL, other controls,
+    /// DEL), the last character repeated out to a few long lengths, and
+    /// the word repeated. Candidates stay newline-separated text, so flips
+    /// that would give a line break or a byte outside ASCII are skipped.
+    Fuzz,
+}
+
+impl Mutation {
+    /// Pass every variant of `word` to `f`, in order. The word itself is not
+    /// included.
+    pub fn variants(&self, word: &str, f: impl FnMut(String)) {
+        match self {
+            Mutation::Leet(map) => map.variants(word, f),
+            Mutation::Transpose => transpositions(word, f),
+            Mutation::DeleteEach => deletions(word, f),
+            Mutation::InsertEach(chars) => insertions(word, chars, f),
+            Mutation::Overwrite { position, chars } => overwrites(word, *position, chars, f),
+            Mutation::KeyboardTypo(layout) => keyboard_typos(word, *layout, f),
+            Mutation::Fuzz => fuzz(word, f),
+        }
+    }
+
+    /// Number of variants [`Mutation::variants`] yields for `word`.
+    pub fn count(&self, word: &str) -> u128 {
+        match self {
+            Mutation::Leet(map) => map.count(word),
+            Mutation::Transpose => {
+                let chars: Vec<char> = word.chars().collect();
+                chars.windows(2).filter(|pair| pair[0] != pair[1]).count() as u128
+            }
+            Mutation::DeleteEach => {
+                let chars: Vec<char> = word.chars().collect();
+                if chars.len() < 2 {
+                    return 0;
+                }
+                1 + chars.windows(2).filter(|pair| pair[0] != pair[1]).count() as u128
+            }
+            Mutation::InsertEach(chars) => {
+                let repeats = word.chars().filter(|c| chars.contains(c)).count();
+                ((word.chars().count() + 1) * chars.len() - repeats) as u128
+            }
+            Mutation::Overwrite { position, chars } => {
+                let mut count = 0;
+                overwrites(word, *position, chars, |_| count += 1);
+                count
+            }
+            Mutation::KeyboardTypo(layout) => word
+                .chars()
+                .map(|c| layout.neighbours(c).len() as u128)
+                .sum(),
+            Mutation::Fuzz => {
+                if word.is_empty() {
+                    return 0;
+                }
+                let flips: usize = word.chars().map(|c| bit_flips(c).count()).sum();
+                let boundaries: usize = word
+                    .chars()
+                    .map(|c| BOUNDARY_BYTES.iter().filter(|&&b| b != c).count())
+                    .sum();
+                let repeats = REPEAT_LENGTHS.iter().filter(|&&n| n > word.len()).count();
+                (flips + boundaries + repeats + 1) as u128
+            }
+        }
+    }
+}
+
+/// Whether a substitution applies to characters one at a time or to every
+/// occurrence at once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeetMode {
+    /// Each occurrence of a mapped character is substituted independently,
+    /// so `aa` with `a = ["4"]` gives `4a`, `a4` and `44`.
+    #[default]
+    Each,
+    /// Every occurrence of a character takes the same substitute, so `aa`
+    /// only gives `44`.
+    All,
+}
+
+/// Characters and the strings they may be replaced with, read from TOML:
+///
+/// ```toml
+/// mode = "each"   # or "all"
+///
+/// [map]
+/// a = ["4", "@"]
+/// e = ["3"]
+/// s = ["5", "$"]
+/// ```
+///
+/// Variants are every combination of substitutions except the original,
+/// with the last substituted character varying fastest. Characters are
+/// matched exactly, so list upper case letters separately if needed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeetMap {
+    pub mode: LeetMode,
+    pub map: BTreeMap<char, Vec<String>>,
+}
+
+impl LeetMap {
+    /// Parse a map from its TOML form.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.message().to_string())
+    }
+
+    /// For each character of `word`, which substitution slot it belongs
+    /// to, along with the substitutes of every slot.
+    fn slots(&self, word: &str) -> (Vec<Option<usize>>, Vec<&[String]>) {
+        let mut slots: Vec<&[String]> = vec![];
+        let mut seen: BTreeMap<char, usize> = BTreeMap::new();
+        let slot_of = word
+            .chars()
+            .map(|c| {
+                let subs = self.map.get(&c).filter(|subs| !subs.is_empty())?;
+                if self.mode == LeetMode::All {
+                    if let Some(&slot) = seen.get(&c) {
+                        return Some(slot);
+                    }
+                    seen.insert(c, slots.len());
+                }
+                slots.push(subs);
+                Some(slots.len() - 1)
+            })
+            .collect();
+        (slot_of, slots)
+    }
+
+    fn count(&self, word: &str) -> u128 {
+        let (_, slots) = self.slots(word);
+        slots.iter().fold(1u128, |total, subs| {
+            total.saturating_mul(1 + subs.len() as u128)
+        }) - 1
+    }
+
+    fn variants(&self, word: &str, mut f: impl FnMut(String)) {
+        let (slot_of, slots) = self.slots(word);
+        // 0 keeps the character, n picks the nth substitute
+        let mut picks = vec![0usize; slots.len()];
+        loop {
+            let mut slot = slots.len();
+            loop {
+                if slot == 0 {
+                    return;
+                }
+                slot -= 1;
+                picks[slot] += 1;
+                if picks[slot] <= slots[slot].len() {
+                    break;
+                }
+                picks[slot] = 0;
+            }
+            let mut variant = String::with_capacity(word.len());
+            for (c, slot) in word.chars().zip(&slot_of) {
+                match *slot {
+                    Some(slot) if picks[slot] > 0 => {
+                        variant.push_str(&slots[slot][picks[slot] - 1])
+                    }
+                    _ => variant.push(c),
+                }
+            }
+            f(variant);
+        }
+    }
+}
+
+fn transpositions(word: &str, mut f: impl FnMut(String)) {
+    let chars: Vec<char> = word.chars().collect();
+    for i in 1..chars.len() {
+        if chars[i - 1] != chars[i] {
+            let mut swapped = chars.clone();
+            swapped.swap(i - 1, i);
+            f(swapped.into_iter().collect());
+        }
+    }
+}
+
+fn keyboard_typos(word: &str, layout: Layout, mut f: impl FnMut(String)) {
+    let chars: Vec<char> = word.chars().collect();
+    for (i, &c) in chars.iter().enumerate() {
+        for neighbour in layout.neighbours(c) {
+            let mut typo = chars.clone();
+            typo[i] = neighbour;
+            f(typo.into_iter().collect());
+        }
+    }
+}
+
+fn deletions(word: &str, mut f: impl FnMut(String)) {
+    let chars: Vec<char> = word.chars().collect();
+    if chars.len() < 2 {
+        return;
+    }
+    for i in 0..chars.len() {
+        if i == 0 || chars[i - 1] != chars[i] {
+            let mut deleted = chars.clone();
+            deleted.remove(i);
+            f(deleted.into_iter().collect());
+        }
+    }
+}
+
+fn insertions(word: &str, insert: &[char], mut f: impl FnMut(String)) {
+    let chars: Vec<char> = word.chars().collect();
+    for i in 0..=chars.len() {
+        for &c in insert {
+            if chars.get(i) == Some(&c) {
+                continue;
+            }
+            let mut inserted = chars.clone();
+            inserted.insert(i, c);
+            f(inserted.into_iter().collect());
+        }
+    }
+}
+
+fn overwrites(word: &str, position: Option<usize>, with: &[char], mut f: impl FnMut(String)) {
+    let chars: Vec<char> = word.chars().collect();
+    let positions = match position {
+        Some(i) if i < chars.len() => i..i + 1,
+        Some(_) => return,
+        None => 0..chars.len(),
+    };
+    for i in positions {
+        for &c in with {
+            if chars[i] != c {
+                let mut overwritten = chars.clone();
+                overwritten[i] = c;
+                f(overwritten.into_iter().collect());
+            }
+        }
+    }
+}
+
+/// Parse `--overwrite`'s `POS:CHARS`, where `POS` is a 0-based position
+/// or `*` for every position and `CHARS` a charset as for
+/// [`parse_charset`], e.g. `0:?u` or `*:0o`.
+pub fn parse_overwrite(spec: &str) -> Result<Mutation, String> {
+    let (position, chars) = spec
+        .split_once(':')
+        .ok_or_else(|| format!("expected POS:CHARS, e.g. '0:?u', got '{}'", spec))?;
+    let position = match position {
+        "*" => None,
+        n => Some(
+            n.parse()
+                .map_err(|_| format!("invalid position '{}' in '{}'", n, spec))?,
+        ),
+    };
+    Ok(Mutation::Overwrite {
+        position,
+        chars: parse_charset(chars)?,
+    })
+}
+
+/// Bytes each character is replaced with by [`Mutation::Fuzz`].
+const BOUNDARY_BYTES: [char; 5] = ['\0', '\x01', '\x1b', '\x1f', '\x7f'];
+
+/// Lengths [`Mutation::Fuzz`] pads words out to.
+const REPEAT_LENGTHS: [usize; 3] = [128, 1024, 8192];
+
+/// Times [`Mutation::Fuzz`] repeats the whole word.
+const WORD_REPEATS: usize = 16;
+
+/// `c` with each of its bits flipped in turn, where the result is still
+/// ASCII and not a line break.
+fn bit_flips(c: char) -> impl Iterator<Item = char> {
+    (0..7)
+        .filter(move |_| c.is_ascii())
+        .map(move |bit| ((c as u8) ^ (1 << bit)) as char)
+        .filter(|&c| c != '\n' && c != '\r')
+}
+
+fn fuzz(word: &str, mut f: impl FnMut(String)) {
+    let chars: Vec<char> = word.chars().collect();
+    let Some(&last) = chars.last() else {
+        return;
+    };
+    let replaced = |i: usize, c: char| {
+        let mut chars = chars.clone();
+        chars[i] = c;
+        chars.into_iter().collect::<String>()
+    };
+    for (i, &c) in chars.iter().enumerate() {
+        bit_flips(c).for_each(|flipped| f(replaced(i, flipped)));
+    }
+    for (i, &c) in chars.iter().enumerate() {
+        for &b in BOUNDARY_BYTES.iter().filter(|&&b| b != c) {
+            f(replaced(i, b));
+        }
+    }
+    for &n in REPEAT_LENGTHS.iter().filter(|&&n| n > word.len()) {
+        let mut long = word.to_string();
+        long.extend(std::iter::repeat_n(
+            last,
+            (n - word.len()) / last.len_utf8(),
+        ));
+        f(long);
+    }
+    f(word.repeat(WORD_REPEATS));
+}
+
+/// Characters of a hashcat-style charset such as `?d` or `?l?d_`: `?d`,
+/// `?l`, `?u` and `?s` are digits, lower and upper case letters and
+/// symbols, `?a` all of those, `??` a question mark, and any other
+/// character stands for itself. Locale alphabets are named in full, as
+/// `?german`, `?cyrillic` or `?latin1-upper` (with `-upper` forms of
+/// each). Repeats are dropped.
+pub fn parse_charset(spec: &str) -> Result<Vec<char>, String> {
+    let mut chars = vec![];
+    let mut rest = spec.chars();
+    while let Some(c) = rest.next() {
+        let class = match c {
+            '?' => charset_after(&mut rest, spec)?,
+            c => vec![c],
+        };
+        for c in class {
+            if !chars.contains(&c) {
+                chars.push(c);
+            }
+        }
+    }
+    if chars.is_empty() {
+        return Err("charset must not be empty".to_string());
+    }
+    Ok(chars)
+}
+
+/// Characters of the charset named after a `?`, moving `rest` past the
+/// name.
+fn charset_after(rest: &mut std::str::Chars, spec: &str) -> Result<Vec<char>, String> {
+    let named = crate::template::NAMED_CLASSES
+        .iter()
+        .find(|name| rest.as_str().starts_with(*name));
+    if let Some(name) = named {
+        *rest = rest.as_str()[name.len()..].chars();
+        return Ok(crate::template::class(name)
+            .into_iter()
+            .flat_map(str::chars)
+            .collect());
+    }
+    let names: &[&str] = match rest.next() {
+        Some('?') => return Ok(vec!['?']),
+        Some('a') => &["l", "u", "d", "s"],
+        Some('d') => &["d"],
+        Some('l') => &["l"],
+        Some('u') => &["u"],
+        Some('s') => &["s"],
+        Some(name) => return Err(format!("unknown charset '?{}' in '{}'", name, spec)),
+        None => return Err(format!("'{}' ends in a lone '?'", spec)),
+    };
+    Ok(names
+        .iter()
+        .filter_map(|name| crate::template::class(name))
+        .flat_map(str::chars)
+        .collect())
+}
+
+/// Every string a hashcat-style mask such as `?d?d` or `x?u` stands for,
+/// each position a charset as for [`parse_charset`], in order with the
+/// last position varying fastest.
+pub fn expand_mask(mask: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![String::new()];
+    let mut rest = mask.chars();
+    while let Some(c) = rest.next() {
+        let position = match c {
+            '?' => charset_after(&mut rest, mask)?,
+            c => vec![c],
+        };
+        words = words
+            .iter()
+            .flat_map(|word| {
+                position.iter().map(move |c| {
+                    let mut word = word.clone();
+                    word.push(*c);
+                    word
+                })
+            })
+            .collect();
+    }
+    Ok(words)
+}
+
+/// A sed-style substitution, `s/<regex>/<replacement>/<flags>`, e.g.
+/// `s/o/0/g`.
+///
+/// Any character may stand in for `/` (`s|/admin|/root|`). In the
+/// replacement, `&` is the whole match and `\1` to `\9` are groups. Flags
+/// are `g` to replace every match rather than the first, `i` to ignore
+/// case, and `k` to keep the original word alongside the rewritten one.
+///
+/// Serialized in this same text form.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SubRule {
+    text: String,
+    regex: Regex,
+    /// Replacement in the `regex` crate's syntax.
+    replacement: String,
+    pub global: bool,
+    pub keep: bool,
+}
+
+impl SubRule {
+    /// `word` with the substitution made.
+    pub fn apply(&self, word: &str) -> String {
+        if self.global {
+            self.regex.replace_all(word, self.replacement.as_str())
+        } else {
+            self.regex.replace(word, self.replacement.as_str())
+        }
+        .into_owned()
+    }
+}
+
+/// Pass every form `rules` rewrite `word` into to `f`, in order: each rule
+/// in turn rewrites the forms left by the ones before it, and a rule with
+/// the `k` flag keeps the unchanged form first when it made a change.
+pub fn substitute(rules: &[SubRule], word: &str, mut f: impl FnMut(String)) {
+    let mut forms = vec![word.to_string()];
+    for rule in rules {
+        let mut next = Vec::with_capacity(forms.len());
+        for form in forms {
+            let rewritten = rule.apply(&form);
+            if rule.keep && rewritten != form {
+                next.push(form);
+            }
+            next.push(rewritten);
+        }
+        forms = next;
+    }
+    forms.into_iter().for_each(&mut f);
+}
+
+impl FromStr for SubRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid substitution '{}', expected e.g. 's/o/0/g'", s);
+        let rest = s.strip_prefix('s').ok_or_else(invalid)?;
+        let delimiter = rest.chars().next().ok_or_else(invalid)?;
+        if delimiter.is_alphanumeric() || delimiter == '\\' {
+            return Err(invalid());
+        }
+
+        // split on unescaped delimiters, dropping the escape before them
+        let mut parts = vec![String::new()];
+        let mut chars = rest[delimiter.len_utf8()..].chars();
+        while let Some(c) = chars.next() {
+            let part = parts.last_mut().unwrap();
+            match c {
+                '\\' => match chars.next() {
+                    Some(next) if next == delimiter => part.push(next),
+                    Some(next) => {
+                        part.push('\\');
+                        part.push(next);
+                    }
+                    None => part.push('\\'),
+                },
+                c if c == delimiter => parts.push(String::new()),
+                c => part.push(c),
+            }
+        }
+        let [pattern, replacement, flags] =
+            <[String; 3]>::try_from(parts).map_err(|_| invalid())?;
+
+        let (mut global, mut insensitive, mut keep) = (false, false, false);
+        for flag in flags.chars() {
+            match flag {
+                'g' => global = true,
+                'i' => insensitive = true,
+                'k' => keep = true,
+                other => {
+                    return Err(format!("unknown flag '{}' in substitution '{}'", other, s));
+                }
+            }
+        }
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(insensitive)
+            .build()
+            .map_err(|e| format!("invalid regex in substitution '{}': {}", s, e))?;
+
+        Ok(Self {
+            text: s.to_string(),
+            regex,
+            replacement: sed_replacement(&replacement),
+            global,
+            keep,
+        })
+    }
+}
+
+/// Translate a sed replacement into the `regex` crate's syntax.
+fn sed_replacement(sed: &str) -> String {
+    let mut out = String::new();
+    let mut chars = sed.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => out.push_str("${0}"),
+            '$' => out.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(d) if d.is_ascii_digit() => {
+                    out.push_str("${");
+                    out.push(d);
+                    out.push('}');
+                }
+                Some('$') => out.push_str("$$"),
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl TryFrom<String> for SubRule {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SubRule> for String {
+    fn from(rule: SubRule) -> Self {
+        rule.text
+    }
+}
+
+impl fmt::Display for SubRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl PartialEq for SubRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for SubRule {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leet_map() {
+        let mut map = LeetMap::from_toml("[map]\na = [\"4\", \"@\"]\ns = [\"5\"]\n").unwrap();
+        let mut words = vec![];
+        map.variants("sas", |w| words.push(w));
+        assert!(words.len() as u128 == map.count("sas"));
+        assert!(words[..4] == ["sa5", "s4s", "s45", "s@s"]);
+        assert!(words.len() == 2 * 3 * 2 - 1);
+
+        map.mode = LeetMode::All;
+        let mut words = vec![];
+        map.variants("sas", |w| words.push(w));
+        assert!(words == ["s4s", "s@s", "5a5", "545", "5@5"]);
+        assert!(map.count("xyz") == 0);
+
+        assert!(LeetMap::from_toml("mode = \"all\"\n[map]\nab = [\"x\"]\n").is_err());
+        assert!(LeetMap::from_toml("mode = \"some\"\n").is_err());
+    }
+
+    #[test]
+    fn test_transpose() {
+        let mut words = vec![];
+        Mutation::Transpose.variants("pass", |w| words.push(w));
+        assert!(words == ["apss", "psas"]);
+        assert!(Mutation::Transpose.count("pass") == 2);
+        assert!(Mutation::Transpose.count("a") == 0);
+    }
+
+    #[test]
+    fn test_keyboard_typo() {
+        let typo = Mutation::KeyboardTypo(Layout::Qwerty);
+        let mut words = vec![];
+        typo.variants("ab", |w| words.push(w));
+        assert!(words == ["qb", "wb", "sb", "zb", "ag", "ah", "av", "an"]);
+        assert!(typo.count("ab") == 8);
+        let typo = Mutation::KeyboardTypo(Layout::Keypad);
+        assert!(typo.count("1a") == 2);
+    }
+
+    #[test]
+    fn test_delete_each() {
+        let mut words = vec![];
+        Mutation::DeleteEach.variants("pass", |w| words.push(w));
+        assert!(words == ["ass", "pss", "pas"]);
+        assert!(Mutation::DeleteEach.count("pass") == 3);
+        assert!(Mutation::DeleteEach.count("a") == 0);
+    }
+
+    #[test]
+    fn test_insert_each() {
+        let chars = parse_charset("?d").unwrap();
+        assert!(chars.len() == 10 && chars[0] == '0');
+        assert!(parse_charset("?a").unwrap().len() == 95);
+        assert!(parse_charset("x??x").unwrap() == ['x', '?']);
+        assert!(parse_charset("?q").is_err() && parse_charset("ab?").is_err());
+
+        let insert = Mutation::InsertEach(vec!['a', 'b']);
+        let mut words = vec![];
+        insert.variants("ab", |w| words.push(w));
+        assert!(words == ["bab", "aab", "aba", "abb"]);
+        assert!(insert.count("ab") == words.len() as u128);
+    }
+
+    #[test]
+    fn test_overwrite() {
+        let overwrite = parse_overwrite("0:ab").unwrap();
+        let mut words = vec![];
+        overwrite.variants("abc", |w| words.push(w));
+        assert!(words == ["bbc"]);
+
+        let overwrite = parse_overwrite("*:x").unwrap();
+        words.clear();
+        overwrite.variants("axc", |w| words.push(w));
+        assert!(words == ["xxc", "axx"]);
+        assert!(overwrite.count("axc") == 2);
+        assert!(parse_overwrite("9:x").unwrap().count("abc") == 0);
+        assert!(parse_overwrite("x").is_err() && parse_overwrite("-1:a").is_err());
+    }
+
+    #[test]
+    fn test_expand_mask() {
+        let words = expand_mask("x?d?d").unwrap();
+        assert!(words.len() == 100);
+        assert!(words[0] == "x00" && words[1] == "x01" && words[99] == "x99");
+        assert!(expand_mask("??").unwrap() == ["?"]);
+        assert!(expand_mask("").unwrap() == [""]);
+        assert!(expand_mask("?x").is_err());
+
+        let words = expand_mask("?german?d").unwrap();
+        assert!(words.len() == 300 && words[299] == "ß9");
+        assert!(expand_mask("?latin1-upper").unwrap().len() == 56);
+        assert!(expand_mask("?latin1").unwrap().len() == 58);
+        assert!(expand_mask("?cyrillic").unwrap()[6] == "ё");
+        assert!(expand_mask("?la").unwrap()[0] == "aa");
+        assert!(parse_charset("?german-upper?u").unwrap().len() == 29);
+    }
+
+    #[test]
+    fn test_fuzz() {
+        for word in ["", "a", "admin", "\x0b\x0c", "é!"] {
+            let mut words = vec![];
+            Mutation::Fuzz.variants(word, |w| words.push(w));
+            assert!(words.len() as u128 == Mutation::Fuzz.count(word));
+            assert!(words.iter().all(|w| !w.contains('\n') && w != word));
+        }
+        let mut words = vec![];
+        Mutation::Fuzz.variants("a", |w| words.push(w));
+        assert!(words[0] == "`" && words.contains(&"\0".to_string()));
+        assert!(words
+            .iter()
+            .any(|w| w.len() == 8192 && w.bytes().all(|b| b == b'a')));
+    }
+
+    #[test]
+    fn test_sub_rule() {
+        let rule: SubRule = "s/o/0/g".parse().unwrap();
+        assert!(rule.apply("foo") == "f00");
+        let rule: SubRule = "s|/(ad)min|&/\\1|i".parse().unwrap();
+        assert!(rule.apply("/ADMIN/admin") == "/ADMIN/AD/admin");
+        let rule: SubRule = r"s/a\/b/$/".parse().unwrap();
+        assert!(rule.apply("xa/b") == "x$");
+
+        let rules: Vec<SubRule> = ["s/o/0/gk", "s/admin/root/"]
+            .iter()
+            .map(|r| r.parse().unwrap())
+            .collect();
+        let mut forms = vec![];
+        substitute(&rules, "admin-docs", |w| forms.push(w));
+        assert!(forms == ["root-docs", "root-d0cs"]);
+        forms.clear();
+        substitute(&rules, "xyz", |w| forms.push(w));
+        assert!(forms == ["xyz"]);
+
+        for bad in ["s/a/b", "s/a/b/gx", "y/a/b/", "s/(/b/"] {
+            assert!(bad.parse::<SubRule>().is_err(), "{}", bad);
+        }
+    }
+}
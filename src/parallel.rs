@@ -0,0 +1,580 @@
+//! Candidate generation spread over a pool of threads, in the same order a
+//! single [`Wordlist`] yields them, and output written on a thread of its
+//! own.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    mem,
+    sync::{mpsc, Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+
+use crate::{Origin, Wordlist};
+
+/// Candidates a work unit is sized to yield, so cheap lines are batched
+/// together and expensive ones go out alone.
+const UNIT_CANDIDATES: u128 = 4096;
+
+/// Most base lines in one work unit.
+const UNIT_LINES: u128 = 1024;
+
+/// Work units in flight per thread, bounding how far generation runs
+/// ahead of the consumer.
+const UNITS_PER_THREAD: usize = 4;
+
+/// Blocks queued for a [`WriterThread`] before writes wait for it.
+const BLOCKS_IN_FLIGHT: usize = 4;
+
+/// Candidates from a run of consecutive base lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Chunk {
+    /// Index of the first candidate in the sequence.
+    pub start: usize,
+    /// Candidates, one per line.
+    text: String,
+    /// End of each candidate's line in `text`, and the transforms behind
+    /// it.
+    words: Vec<(usize, Origin)>,
+}
+
+impl Chunk {
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Keep only the first `len` candidates.
+    pub fn truncate(&mut self, len: usize) {
+        self.words.truncate(len);
+        let end = self.words.last().map(|(end, _)| *end).unwrap_or(0);
+        self.text.truncate(end);
+    }
+
+    /// Candidates in order, with the transforms that produced them.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Origin)> {
+        let mut start = 0;
+        self.words.iter().map(move |(end, origin)| {
+            let word = &self.text[start..*end - 1];
+            start = *end;
+            (word, origin)
+        })
+    }
+
+    /// Every candidate followed by a newline, ready to write out.
+    pub fn lines(&self) -> &str {
+        &self.text
+    }
+
+    /// Render every candidate `wl` has queued from its current line.
+    fn drain(&mut self, wl: &mut Wordlist) {
+        while let Some((base, origin)) = wl.pop_queued() {
+            wl.render_into(base, &origin, &mut self.text);
+            self.text.push('\n');
+            self.words.push((self.text.len(), origin));
+        }
+    }
+}
+
+/// Base lines for one work unit, each with its likelihood pass.
+type Unit = Vec<(String, usize)>;
+
+/// A wordlist whose lines are expanded by a pool of threads.
+///
+/// A reading thread hands runs of base lines to the pool as work units,
+/// and the resulting [`Chunk`]s are put back in sequence before being
+/// returned, so the output is identical to iterating the wordlist directly
+/// whatever the thread count. The wordlist may have been moved with
+/// [`Wordlist::seek_to`] first.
+///
+/// Only a fixed number of units may be read but not yet returned, so a
+/// consumer that falls behind, such as a slow output, stalls reading and
+/// expansion rather than letting chunks pile up in memory.
+///
+/// ```no_run
+/// use std::io::Write;
+/// use wlinflate::{parallel::ParallelWordlist, spec::WordlistSpec};
+///
+/// let wl = WordlistSpec::new(["words.txt"]).build()?;
+/// let mut out = std::io::stdout().lock();
+/// for chunk in ParallelWordlist::new(wl, 4) {
+///     for (word, _) in chunk.iter() {
+///         writeln!(out, "{}", word)?;
+///     }
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct ParallelWordlist {
+    /// Copy of the wordlist's configuration, for describing candidates.
+    config: Wordlist,
+    /// Thread reading base lines, which returns the wordlist and the
+    /// number of units it sent.
+    reader: Option<JoinHandle<(Wordlist, usize)>>,
+    /// The wordlist once the reading thread has finished with it.
+    source: Option<Wordlist>,
+    /// Candidates left over from a line the source was part way through.
+    partial: Option<Chunk>,
+    /// Hands a unit's slot back to the reading thread once its chunk has
+    /// been returned.
+    credits: mpsc::SyncSender<()>,
+    chunks: mpsc::Receiver<(usize, Chunk)>,
+    /// Chunks that arrived ahead of their turn.
+    pending: BTreeMap<usize, Chunk>,
+    /// Return chunks in sequence rather than as they complete.
+    ordered: bool,
+    /// Units returned so far.
+    next: usize,
+    position: usize,
+}
+
+impl ParallelWordlist {
+    /// Expand `wordlist` on `threads` threads (at least one).
+    pub fn new(mut wordlist: Wordlist, threads: usize) -> Self {
+        let threads = threads.max(1);
+        let mut partial = Chunk {
+            start: wordlist.position(),
+            ..Default::default()
+        };
+        partial.drain(&mut wordlist);
+
+        let per_line = wordlist.keyspace() / (wordlist.base_count.max(1) as u128);
+        let lines_per_unit = (UNIT_CANDIDATES / per_line.max(1)).clamp(1, UNIT_LINES) as usize;
+        let max_in_flight = threads * UNITS_PER_THREAD;
+
+        let (credits, available) = mpsc::sync_channel(max_in_flight);
+        for _ in 0..max_in_flight {
+            let _ = credits.send(());
+        }
+        let (units, queue) = mpsc::sync_channel::<(usize, Unit)>(threads);
+        let queue = Arc::new(Mutex::new(queue));
+        let (done, chunks) = mpsc::sync_channel(max_in_flight);
+        for _ in 0..threads {
+            let mut expander = wordlist.expander();
+            let (queue, done) = (queue.clone(), done.clone());
+            thread::spawn(move || loop {
+                let next = queue.lock().unwrap().recv();
+                let (seq, lines) = match next {
+                    Ok(unit) => unit,
+                    Err(_) => break,
+                };
+                let mut chunk = Chunk::default();
+                for (line, pass) in &lines {
+                    expander.expand_line(line, *pass);
+                    chunk.drain(&mut expander);
+                }
+                if done.send((seq, chunk)).is_err() {
+                    break;
+                }
+            });
+        }
+
+        let config = wordlist.expander();
+        let reader = thread::spawn(move || {
+            let mut sent = 0;
+            while available.recv().is_ok() {
+                let lines: Unit = (0..lines_per_unit)
+                    .map_while(|_| wordlist.next_line())
+                    .collect();
+                let last = lines.len() < lines_per_unit;
+                if lines.is_empty() || units.send((sent, lines)).is_err() {
+                    break;
+                }
+                sent += 1;
+                if last {
+                    break;
+                }
+            }
+            (wordlist, sent)
+        });
+
+        Self {
+            config,
+            reader: Some(reader),
+            source: None,
+            position: partial.start,
+            partial: Some(partial),
+            credits,
+            chunks,
+            pending: BTreeMap::new(),
+            ordered: true,
+            next: 0,
+        }
+    }
+
+    /// Return each chunk as soon as it is ready rather than in sequence.
+    ///
+    /// Candidates stay together within a chunk, but chunks come back in
+    /// whatever order the threads finish them, so no thread waits on a
+    /// slower one. [`Chunk::start`] and [`ParallelWordlist::position`]
+    /// then count candidates returned rather than sequence indexes.
+    pub fn unordered(mut self) -> Self {
+        self.ordered = false;
+        self
+    }
+
+    /// Index of the first candidate of the next chunk.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The configuration of the wordlist being expanded, e.g. for
+    /// [`Wordlist::describe`].
+    pub fn wordlist(&self) -> &Wordlist {
+        &self.config
+    }
+
+    /// Stop expanding and return the wordlist, positioned after the last
+    /// line read for the pool rather than the last chunk returned.
+    pub fn into_inner(self) -> Wordlist {
+        let Self {
+            reader,
+            source,
+            credits,
+            chunks,
+            ..
+        } = self;
+        if let Some(wordlist) = source {
+            return wordlist;
+        }
+        // with nowhere to send their work the threads wind down
+        drop((credits, chunks));
+        reader
+            .expect("reading thread already joined")
+            .join()
+            .expect("reading thread panicked")
+            .0
+    }
+
+    /// Collect the wordlist back from the reading thread once every chunk
+    /// has been received.
+    fn finish(&mut self) {
+        if let Some(reader) = self.reader.take() {
+            let (wordlist, sent) = reader.join().expect("reading thread panicked");
+            assert!(self.next == sent, "expansion thread panicked");
+            self.source = Some(wordlist);
+        }
+    }
+}
+
+impl Iterator for ParallelWordlist {
+    type Item = Chunk;
+
+    fn next(&mut self) -> Option<Chunk> {
+        if let Some(chunk) = self.partial.take().filter(|chunk| !chunk.is_empty()) {
+            self.position += chunk.len();
+            return Some(chunk);
+        }
+        loop {
+            let mut chunk = match self.pending.remove(&self.next) {
+                Some(chunk) => chunk,
+                None => match self.chunks.recv() {
+                    Ok((_, chunk)) if !self.ordered => chunk,
+                    Ok((seq, chunk)) => {
+                        self.pending.insert(seq, chunk);
+                        continue;
+                    }
+                    // every thread has finished
+                    Err(_) => {
+                        self.finish();
+                        return None;
+                    }
+                },
+            };
+            self.next += 1;
+            let _ = self.credits.send(());
+            if chunk.is_empty() {
+                continue;
+            }
+            chunk.start = self.position;
+            self.position += chunk.len();
+            return Some(chunk);
+        }
+    }
+}
+
+/// What a [`WriterThread`] is sent.
+enum Block {
+    Data(Vec<u8>),
+    /// Flush, and reply with how it went.
+    Flush(mpsc::SyncSender<io::Result<()>>),
+}
+
+/// Output written on a thread of its own, so formatting candidates
+/// overlaps with writing the ones before them.
+///
+/// Writes are gathered into blocks of `block_size` bytes and queued for
+/// the thread. Only a few blocks may be queued at once, so a slow output
+/// stalls the writer rather than letting blocks pile up in memory. An
+/// error on the thread is returned by the next write or flush; dropping
+/// the writer waits for every queued block but ignores errors.
+///
+/// ```no_run
+/// use std::io::Write;
+/// use wlinflate::{parallel::{ParallelWordlist, WriterThread}, spec::WordlistSpec};
+///
+/// let wl = WordlistSpec::new(["words.txt"]).build()?;
+/// let mut out = WriterThread::new(std::io::stdout(), 1 << 20);
+/// for chunk in ParallelWordlist::new(wl, 4) {
+///     out.write_all(chunk.lines().as_bytes())?;
+/// }
+/// out.into_inner()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct WriterThread<W: Write + Send + 'static> {
+    block: Vec<u8>,
+    block_size: usize,
+    blocks: Option<mpsc::SyncSender<Block>>,
+    /// Blocks the thread has written, emptied for reuse.
+    spare: mpsc::Receiver<Vec<u8>>,
+    thread: Option<JoinHandle<io::Result<W>>>,
+}
+
+impl<W: Write + Send + 'static> WriterThread<W> {
+    pub fn new(mut inner: W, block_size: usize) -> Self {
+        let block_size = block_size.max(1);
+        let (blocks, queue) = mpsc::sync_channel(BLOCKS_IN_FLIGHT);
+        let (emptied, spare) = mpsc::sync_channel(BLOCKS_IN_FLIGHT);
+        let thread = thread::spawn(move || {
+            for block in queue {
+                match block {
+                    Block::Data(mut data) => {
+                        // on error the queue is dropped, failing the next send
+                        inner.write_all(&data)?;
+                        data.clear();
+                        let _ = emptied.try_send(data);
+                    }
+                    Block::Flush(reply) => {
+                        let flushed = inner.flush();
+                        let failed = flushed.is_err();
+                        let _ = reply.send(flushed);
+                        if failed {
+                            break;
+                        }
+                    }
+                }
+            }
+            Ok(inner)
+        });
+        Self {
+            block: Vec::with_capacity(block_size),
+            block_size,
+            blocks: Some(blocks),
+            spare,
+            thread: Some(thread),
+        }
+    }
+
+    /// Write out everything queued and return the inner writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+        self.blocks = None;
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            _ => Err(stopped()),
+        }
+    }
+
+    fn send(&mut self, block: Block) -> io::Result<()> {
+        let sent = match &self.blocks {
+            Some(blocks) => blocks.send(block),
+            None => return Err(stopped()),
+        };
+        if sent.is_ok() {
+            return Ok(());
+        }
+        // the thread has gone; its result says why
+        self.blocks = None;
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(Err(e))) => Err(e),
+            _ => Err(stopped()),
+        }
+    }
+
+    /// Queue the current block, starting another.
+    fn send_block(&mut self) -> io::Result<()> {
+        let next = self
+            .spare
+            .try_recv()
+            .unwrap_or_else(|_| Vec::with_capacity(self.block_size));
+        let block = mem::replace(&mut self.block, next);
+        self.send(Block::Data(block))
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::other("output thread stopped")
+}
+
+impl<W: Write + Send + 'static> Write for WriterThread<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.block.is_empty() && self.block.len() + buf.len() > self.block_size {
+            self.send_block()?;
+        }
+        self.block.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.block.is_empty() {
+            self.send_block()?;
+        }
+        let (reply, flushed) = mpsc::sync_channel(1);
+        self.send(Block::Flush(reply))?;
+        flushed.recv().unwrap_or_else(|_| Err(stopped()))
+    }
+}
+
+impl<W: Write + Send + 'static> Drop for WriterThread<W> {
+    fn drop(&mut self) {
+        if !self.block.is_empty() {
+            let _ = self.send_block();
+        }
+        self.blocks = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::WordlistSpec;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("wlinflate_test_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_parallel_order() {
+        let path = &temp_path("parallel.txt");
+        let words: String = (0..500).map(|i| format!("w{}\n", i)).collect();
+        std::fs::write(path, words).unwrap();
+
+        let mut spec = WordlistSpec::new([path]);
+        spec.prepend = vec!["a".to_string(), "b".to_string()];
+        spec.extensions = vec![".php".to_string()];
+        for (likelihood_order, reverse) in [(false, false), (true, false), (true, true)] {
+            spec.likelihood_order = likelihood_order;
+            spec.reverse = reverse;
+            let serial: Vec<String> = spec.build().unwrap().collect();
+
+            for threads in [1, 3] {
+                let mut wl = spec.build().unwrap();
+                wl.seek_to(7);
+                let mut parallel = vec![];
+                for chunk in ParallelWordlist::new(wl, threads) {
+                    assert!(chunk.start == 7 + parallel.len());
+                    parallel.extend(chunk.iter().map(|(word, _)| word.to_string()));
+                }
+                assert!(parallel == serial[7..]);
+            }
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_unordered() {
+        let path = &temp_path("unordered.txt");
+        let words: String = (0..300).map(|i| format!("w{}\n", i)).collect();
+        std::fs::write(path, words).unwrap();
+
+        let mut spec = WordlistSpec::new([path]);
+        spec.append = vec!["1".to_string(), "2".to_string()];
+        let mut serial: Vec<String> = spec.build().unwrap().collect();
+        let mut unordered = vec![];
+        for chunk in ParallelWordlist::new(spec.build().unwrap(), 3).unordered() {
+            assert!(chunk.start == unordered.len());
+            unordered.extend(chunk.iter().map(|(word, _)| word.to_string()));
+        }
+        serial.sort();
+        unordered.sort();
+        assert!(unordered == serial);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_stop_early() {
+        let path = &temp_path("parallel_stop.txt");
+        let words: String = (0..100_000).map(|i| format!("w{}\n", i)).collect();
+        std::fs::write(path, words).unwrap();
+
+        // the pool is stalled on the unread chunks and must still wind down
+        let mut pool = ParallelWordlist::new(WordlistSpec::new([path]).build().unwrap(), 2);
+        assert!(pool.next().unwrap().start == 0);
+        let mut wl = pool.into_inner();
+        assert!(wl.next().is_some());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_chunk_truncate() {
+        let path = &temp_path("parallel_chunk.txt");
+        std::fs::write(path, "a\n").unwrap();
+        let mut spec = WordlistSpec::new([path]);
+        spec.append = vec!["1".to_string(), "2".to_string()];
+        let mut chunk = ParallelWordlist::new(spec.build().unwrap(), 2)
+            .next()
+            .unwrap();
+        assert!(chunk.len() == 3);
+        chunk.truncate(2);
+        let words: Vec<&str> = chunk.iter().map(|(word, _)| word).collect();
+        assert!(words == ["a", "a1"]);
+        assert!(chunk.lines() == "a\na1\n");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_writer_thread() {
+        let shared = Shared::default();
+        let mut out = WriterThread::new(shared.clone(), 8);
+        for i in 0..1000 {
+            writeln!(out, "w{}", i).unwrap();
+        }
+        out.flush().unwrap();
+        let expected: String = (0..1000).map(|i| format!("w{}\n", i)).collect();
+        assert!(*shared.0.lock().unwrap() == expected.as_bytes());
+
+        out.write_all(b"last\n").unwrap();
+        drop(out);
+        assert!(shared.0.lock().unwrap().ends_with(b"w999\nlast\n"));
+    }
+
+    struct Failing;
+
+    impl Write for Failing {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_writer_thread_error() {
+        // an output that went away fails the writes after it
+        let mut out = WriterThread::new(Failing, 4);
+        let failed = (0..100).find_map(|_| out.write_all(b"word\n").err());
+        assert!(failed.unwrap().kind() == io::ErrorKind::BrokenPipe);
+        assert!(out.flush().is_err());
+    }
+}
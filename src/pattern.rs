@@ -0,0 +1,153 @@
+//! Structured identifiers such as license plates, asset tags and ticket
+//! numbers, generated from a small pattern language.
+
+/// Every string matching each of a list of patterns, in order.
+///
+/// In a pattern, `A` stands for an upper case letter, `a` for a lower
+/// case letter and `9` for a digit; any other character stands for
+/// itself, and `\` makes the next one literal, so `AAA-9999` gives
+/// `AAA-0000` through `ZZZ-9999` and `\A-99` gives `A-00` through
+/// `A-99`. Each pattern is enumerated with the last position varying
+/// fastest, then the next pattern follows.
+#[derive(Debug, Clone)]
+pub struct Patterns {
+    text: Vec<String>,
+    /// Characters each position of each pattern may take.
+    patterns: Vec<Vec<Vec<char>>>,
+    current: usize,
+    /// Character indexes of the next string; `None` once exhausted.
+    next: Option<Vec<usize>>,
+}
+
+/// Characters a pattern position stands for.
+fn placeholder(c: char) -> Option<Vec<char>> {
+    match c {
+        'A' => Some(('A'..='Z').collect()),
+        'a' => Some(('a'..='z').collect()),
+        '9' => Some(('0'..='9').collect()),
+        _ => None,
+    }
+}
+
+/// The positions of `pattern`, each the characters it may take.
+pub fn parse(pattern: &str) -> Result<Vec<Vec<char>>, String> {
+    let mut positions = vec![];
+    let mut rest = pattern.chars();
+    while let Some(c) = rest.next() {
+        positions.push(match c {
+            '\\' => match rest.next() {
+                Some(c) => vec![c],
+                None => return Err(format!("pattern '{}' ends in a lone '\\'", pattern)),
+            },
+            c => placeholder(c).unwrap_or_else(|| vec![c]),
+        });
+    }
+    if positions.is_empty() {
+        return Err("pattern must not be empty".to_string());
+    }
+    Ok(positions)
+}
+
+impl Patterns {
+    /// Strings of each of `patterns`, failing on any that can't be parsed.
+    pub fn new(patterns: &[String]) -> Result<Self, String> {
+        let parsed = patterns
+            .iter()
+            .map(|p| parse(p))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut generated = Self {
+            text: patterns.to_vec(),
+            patterns: parsed,
+            current: 0,
+            next: None,
+        };
+        generated.rewind();
+        Ok(generated)
+    }
+
+    /// Start over from the first string.
+    pub fn rewind(&mut self) {
+        self.current = 0;
+        self.next = self.patterns.first().map(|p| vec![0; p.len()]);
+    }
+
+    /// The patterns as given.
+    pub fn patterns(&self) -> &[String] {
+        &self.text
+    }
+
+    /// Number of strings, saturating at `u128::MAX`.
+    pub fn total(&self) -> u128 {
+        self.patterns
+            .iter()
+            .map(|p| strings(p))
+            .fold(0u128, u128::saturating_add)
+    }
+
+    /// Total size of every string in bytes, newlines included.
+    pub fn byte_len(&self) -> u64 {
+        let mut total = 0u128;
+        for pattern in &self.patterns {
+            let count = strings(pattern);
+            // each character of a position appears in an equal share
+            for position in pattern {
+                let bytes: u128 = position.iter().map(|c| c.len_utf8() as u128).sum();
+                total =
+                    total.saturating_add((count / position.len() as u128).saturating_mul(bytes));
+            }
+            total = total.saturating_add(count);
+        }
+        u64::try_from(total).unwrap_or(u64::MAX)
+    }
+}
+
+/// Number of strings a parsed pattern stands for.
+fn strings(pattern: &[Vec<char>]) -> u128 {
+    pattern
+        .iter()
+        .fold(1u128, |total, p| total.saturating_mul(p.len() as u128))
+}
+
+impl Iterator for Patterns {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let indexes = self.next.as_mut()?;
+        let pattern = &self.patterns[self.current];
+        let string = indexes
+            .iter()
+            .zip(pattern)
+            .map(|(&i, position)| position[i])
+            .collect();
+        match (0..indexes.len()).rposition(|pos| indexes[pos] + 1 < pattern[pos].len()) {
+            Some(pos) => {
+                indexes[pos] += 1;
+                indexes[pos + 1..].iter_mut().for_each(|i| *i = 0);
+            }
+            None => {
+                self.current += 1;
+                self.next = self.patterns.get(self.current).map(|p| vec![0; p.len()]);
+            }
+        }
+        Some(string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns() {
+        let patterns = Patterns::new(&["A9-a".to_string(), "\\A\\9é9".to_string()]).unwrap();
+        assert!(patterns.total() == 26 * 10 * 26 + 10);
+        assert!(patterns.byte_len() == patterns.clone().map(|p| p.len() as u64 + 1).sum::<u64>());
+        let all: Vec<String> = patterns.collect();
+        assert!(all[..2] == ["A0-a", "A0-b"]);
+        assert!(all[26] == "A1-a");
+        assert!(all[6759] == "Z9-z");
+        assert!(all[6760] == "A9é0" && all.last().unwrap() == "A9é9");
+        assert!(parse("AB\\").is_err());
+        assert!(parse("").is_err());
+    }
+}
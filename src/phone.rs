@@ -0,0 +1,246 @@
+//! Telephone numbers in the formats people write them, as passwords and
+//! as usernames to pivot on.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Region names accepted by [`Region::from_str`].
+pub const REGIONS: &[&str] = &["US", "CA"];
+
+/// Where numbers are generated for, which decides their shape.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Region {
+    /// United States, under the North American Numbering Plan.
+    #[default]
+    Us,
+    /// Canada, under the same plan.
+    Ca,
+}
+
+impl Region {
+    /// Formats tried when none are given, `#` standing for each digit of
+    /// the national number.
+    pub fn default_formats(&self) -> Vec<String> {
+        match self {
+            Region::Us | Region::Ca => [
+                "##########",
+                "###-###-####",
+                "(###) ###-####",
+                "###.###.####",
+                "1##########",
+                "+1##########",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+
+    /// Digits in a national number.
+    fn digits(&self) -> usize {
+        match self {
+            Region::Us | Region::Ca => 10,
+        }
+    }
+
+    /// Whether `code` is an area code numbers can have: three digits, the
+    /// first 2 to 9, and not an `N11` service code.
+    fn valid_area_code(&self, code: &str) -> bool {
+        match self {
+            Region::Us | Region::Ca => {
+                let digits = code.as_bytes();
+                digits.len() == 3
+                    && digits.iter().all(u8::is_ascii_digit)
+                    && digits[0] >= b'2'
+                    && digits[1..] != *b"11"
+            }
+        }
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "US" => Ok(Region::Us),
+            "CA" => Ok(Region::Ca),
+            _ => Err(format!(
+                "unknown region '{}', expected one of {}",
+                s.trim(),
+                REGIONS.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Region::Us => "US",
+            Region::Ca => "CA",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Every number in a set of area codes, each written in every format.
+///
+/// Exchanges (the next three digits) run from 200 to 999, skipping `N11`
+/// service codes, and the line number from 0000 to 9999. Area codes are
+/// taken in the order given, then exchanges and lines in ascending order,
+/// each number once per format with the formats varying fastest.
+#[derive(Debug, Clone)]
+pub struct Phones {
+    area_codes: Vec<String>,
+    formats: Vec<String>,
+    exchanges: Vec<u16>,
+    /// Index of the next number; past the end once exhausted.
+    next: u64,
+    /// Format of the next number.
+    format: usize,
+}
+
+/// Line numbers per exchange.
+const LINES: u64 = 10_000;
+
+impl Phones {
+    /// Numbers of `region` in `area_codes`, written in each of `formats`
+    /// (the region's defaults if empty), where `#` stands for each digit.
+    pub fn new(region: Region, area_codes: &[String], formats: &[String]) -> Result<Self, String> {
+        if area_codes.is_empty() {
+            return Err("at least one area code is needed".to_string());
+        }
+        let mut codes: Vec<String> = vec![];
+        for code in area_codes {
+            let code = code.trim();
+            if !region.valid_area_code(code) {
+                return Err(format!("'{}' is not a valid {} area code", code, region));
+            }
+            if !codes.iter().any(|c| c == code) {
+                codes.push(code.to_string());
+            }
+        }
+        let formats = if formats.is_empty() {
+            region.default_formats()
+        } else {
+            formats.to_vec()
+        };
+        for format in &formats {
+            let digits = format.matches('#').count();
+            if digits != region.digits() {
+                return Err(format!(
+                    "phone format '{}' has {} '#', expected {}",
+                    format,
+                    digits,
+                    region.digits()
+                ));
+            }
+        }
+        Ok(Self {
+            area_codes: codes,
+            formats,
+            exchanges: (200..1000).filter(|e| e % 100 != 11).collect(),
+            next: 0,
+            format: 0,
+        })
+    }
+
+    /// Start over from the first number.
+    pub fn rewind(&mut self) {
+        self.next = 0;
+        self.format = 0;
+    }
+
+    /// Area codes numbers are generated in.
+    pub fn area_codes(&self) -> &[String] {
+        &self.area_codes
+    }
+
+    fn numbers(&self) -> u64 {
+        self.area_codes.len() as u64 * self.exchanges.len() as u64 * LINES
+    }
+
+    /// Number of numbers, each format counted.
+    pub fn total(&self) -> u128 {
+        self.numbers() as u128 * self.formats.len() as u128
+    }
+
+    /// Total size of every number in bytes, newlines included.
+    pub fn byte_len(&self) -> u64 {
+        let formats: u64 = self.formats.iter().map(|f| f.len() as u64 + 1).sum();
+        self.numbers().saturating_mul(formats)
+    }
+}
+
+impl Iterator for Phones {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.next >= self.numbers() {
+            return None;
+        }
+        let per_area = self.exchanges.len() as u64 * LINES;
+        let area = &self.area_codes[(self.next / per_area) as usize];
+        let exchange = self.exchanges[(self.next % per_area / LINES) as usize];
+        let number = format!("{}{}{:04}", area, exchange, self.next % LINES);
+        let mut digits = number.chars();
+        let phone = self.formats[self.format]
+            .chars()
+            .map(|c| match c {
+                '#' => digits.next().unwrap_or(c),
+                c => c,
+            })
+            .collect();
+        self.format += 1;
+        if self.format == self.formats.len() {
+            self.format = 0;
+            self.next += 1;
+        }
+        Some(phone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phones() {
+        let codes = ["212", "718", "212"].map(String::from);
+        let phones = Phones::new(Region::Us, &codes, &[]).unwrap();
+        assert!(phones.area_codes() == ["212", "718"]);
+        assert!(phones.total() == 2 * 792 * 10_000 * 6);
+        let first: Vec<String> = phones.clone().take(7).collect();
+        assert!(
+            first
+                == [
+                    "2122000000",
+                    "212-200-0000",
+                    "(212) 200-0000",
+                    "212.200.0000",
+                    "12122000000",
+                    "+12122000000",
+                    "2122000001"
+                ]
+        );
+        assert!(phones.clone().nth(6 * 10_000 * 10) == Some("2122100000".to_string()));
+        assert!(phones.clone().nth(6 * 10_000 * 11) == Some("2122120000".to_string()));
+
+        let canada = Phones::new(
+            Region::Ca,
+            &["416".to_string()],
+            &["#### ######".to_string()],
+        )
+        .unwrap();
+        assert!(canada.byte_len() == 7_920_000 * 12);
+        assert!(canada.clone().next() == Some("4162 000000".to_string()));
+
+        assert!(Phones::new(Region::Us, &["911".to_string()], &[]).is_err());
+        assert!(Phones::new(Region::Us, &["123".to_string()], &[]).is_err());
+        assert!(Phones::new(Region::Us, &codes, &["###".to_string()]).is_err());
+        assert!("uk".parse::<Region>().is_err());
+        assert!("us".parse::<Region>() == Ok(Region::Us));
+    }
+}
@@ -0,0 +1,346 @@
+//! Multi-word passphrase candidates such as `correcthorsebattery`, built
+//! from every combination of dictionary words.
+
+use std::{collections::HashSet, fmt, ops::RangeInclusive, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Casing names accepted by [`Casing::from_str`].
+pub const CASINGS: &[&str] = &["as-is", "lower", "upper", "title"];
+
+/// How a word is cased when put into a passphrase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Casing {
+    AsIs,
+    Lower,
+    Upper,
+    /// First letter upper case, the rest lower case.
+    Title,
+}
+
+impl Casing {
+    pub fn apply(&self, word: &str) -> String {
+        match self {
+            Casing::AsIs => word.to_string(),
+            Casing::Lower => word.to_lowercase(),
+            Casing::Upper => word.to_uppercase(),
+            Casing::Title => {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first
+                        .to_uppercase()
+                        .chain(chars.flat_map(char::to_lowercase))
+                        .collect(),
+                    None => String::new(),
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Casing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "as-is" => Ok(Casing::AsIs),
+            "lower" => Ok(Casing::Lower),
+            "upper" => Ok(Casing::Upper),
+            "title" => Ok(Casing::Title),
+            other => Err(format!(
+                "unknown casing '{}', expected one of {}",
+                other,
+                CASINGS.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Casing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Casing::AsIs => "as-is",
+            Casing::Lower => "lower",
+            Casing::Upper => "upper",
+            Casing::Title => "title",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Every sequence of `lengths` different words from a dictionary,
+/// shortest first, each length in odometer order with the last word
+/// varying fastest.
+///
+/// Each word may appear in every position, in each of the casings given,
+/// but only once in a phrase whatever its casing, so a dictionary of `n`
+/// words yields `n!/(n-k)!` phrases of `k` words times the casings of
+/// each. Phrases of several words are then put together with each of the
+/// joiners in turn (`correct-horse`, `correct_horse`), multiplying their
+/// number by the number of joiners.
+#[derive(Debug, Clone)]
+pub struct Phrases {
+    words: Vec<String>,
+    /// Dictionary word each form in `words` was made from.
+    sources: Vec<usize>,
+    /// Forms made from each dictionary word, and their length in bytes.
+    forms: Vec<(u128, u128)>,
+    lengths: RangeInclusive<usize>,
+    /// Strings put between the words of a phrase, one per variant.
+    joiners: Vec<String>,
+    /// Word indexes of the next phrase; `None` once exhausted.
+    next: Option<Vec<usize>>,
+    /// Joiner of the next phrase.
+    joiner: usize,
+}
+
+impl Phrases {
+    /// Phrases over `words`, each put in every one of `casings` (forms
+    /// that come out the same are used once, as are repeated words).
+    pub fn new(
+        words: impl IntoIterator<Item = String>,
+        casings: &[Casing],
+        lengths: RangeInclusive<usize>,
+    ) -> Self {
+        let casings = if casings.is_empty() {
+            &[Casing::AsIs][..]
+        } else {
+            casings
+        };
+        let mut seen = HashSet::new();
+        let (mut forms, mut sources, mut counts) = (vec![], vec![], vec![]);
+        for word in words {
+            if !seen.insert(word.clone()) {
+                continue;
+            }
+            let start = forms.len();
+            for casing in casings {
+                let form = casing.apply(&word);
+                if !forms[start..].contains(&form) {
+                    forms.push(form);
+                    sources.push(counts.len());
+                }
+            }
+            let bytes: usize = forms[start..].iter().map(String::len).sum();
+            counts.push(((forms.len() - start) as u128, bytes as u128));
+        }
+        let mut phrases = Self {
+            words: forms,
+            sources,
+            forms: counts,
+            lengths,
+            joiners: vec![String::new()],
+            next: None,
+            joiner: 0,
+        };
+        phrases.rewind();
+        phrases
+    }
+
+    /// Emit every phrase of several words once with each of `joiners`
+    /// between its words, instead of running the words together.
+    pub fn with_joiners(mut self, joiners: Vec<String>) -> Self {
+        if !joiners.is_empty() {
+            self.joiners = joiners;
+        }
+        self
+    }
+
+    /// Start over from the first phrase.
+    pub fn rewind(&mut self) {
+        let first = (*self.lengths.start()).max(1);
+        self.joiner = 0;
+        self.next = (first..=*self.lengths.end()).find_map(|k| self.first(k));
+    }
+
+    /// Word forms phrases are made of, casings applied.
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// Number of variants of each phrase of `k` words.
+    fn variants(&self, k: usize) -> usize {
+        if k > 1 {
+            self.joiners.len()
+        } else {
+            1
+        }
+    }
+
+    /// The first phrase of `k` words, if the dictionary holds that many.
+    fn first(&self, k: usize) -> Option<Vec<usize>> {
+        let mut indexes = vec![0; k];
+        self.fill(&mut indexes, 0).then_some(indexes)
+    }
+
+    /// Point `indexes[from..]` at the first forms of words not already in
+    /// the phrase, returning `false` if there are too few words left.
+    fn fill(&self, indexes: &mut [usize], from: usize) -> bool {
+        for pos in from..indexes.len() {
+            match self.next_form(&indexes[..pos], 0) {
+                Some(i) => indexes[pos] = i,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// The first form from `start` on of a word not in `taken`.
+    fn next_form(&self, taken: &[usize], start: usize) -> Option<usize> {
+        (start..self.words.len()).find(|&i| {
+            taken
+                .iter()
+                .all(|&other| self.sources[other] != self.sources[i])
+        })
+    }
+
+    /// Advance `indexes` to the next phrase of the same length, returning
+    /// `false` once there is none.
+    fn advance(&self, indexes: &mut [usize]) -> bool {
+        for pos in (0..indexes.len()).rev() {
+            if let Some(i) = self.next_form(&indexes[..pos], indexes[pos] + 1) {
+                indexes[pos] = i;
+                if self.fill(indexes, pos + 1) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Ways to pick an unordered set of `k` words and a form of each, for
+    /// every `k` up to the longest phrase, with the bytes those forms add
+    /// up to across every choice. Saturates at `u128::MAX`.
+    fn choices(&self) -> Vec<(u128, u128)> {
+        let longest = *self.lengths.end();
+        let mut choices = vec![(0u128, 0u128); longest + 1];
+        choices[0].0 = 1;
+        for &(count, bytes) in &self.forms {
+            for k in (1..=longest).rev() {
+                let (ways, total) = choices[k - 1];
+                let with = (
+                    ways.saturating_mul(count),
+                    total
+                        .saturating_mul(count)
+                        .saturating_add(ways.saturating_mul(bytes)),
+                );
+                choices[k].0 = choices[k].0.saturating_add(with.0);
+                choices[k].1 = choices[k].1.saturating_add(with.1);
+            }
+        }
+        choices
+    }
+
+    /// Phrases of each length with the bytes of their words, in every
+    /// order but before joiners. Saturates at `u128::MAX`.
+    fn counts(&self) -> Vec<(usize, u128, u128)> {
+        let choices = self.choices();
+        let mut orders = 1u128;
+        let mut counts = vec![];
+        for (k, &(ways, bytes)) in choices.iter().enumerate().skip(1) {
+            orders = orders.saturating_mul(k as u128);
+            if k >= *self.lengths.start() {
+                counts.push((k, ways.saturating_mul(orders), bytes.saturating_mul(orders)));
+            }
+        }
+        counts
+    }
+
+    /// Number of phrases, joiner variants included, saturating at
+    /// `u128::MAX`.
+    pub fn total(&self) -> u128 {
+        self.counts()
+            .into_iter()
+            .map(|(k, phrases, _)| phrases.saturating_mul(self.variants(k) as u128))
+            .fold(0u128, u128::saturating_add)
+    }
+
+    /// Total size of every phrase in bytes, newlines included.
+    pub fn byte_len(&self) -> u64 {
+        let joiners: u128 = self.joiners.iter().map(|j| j.len() as u128).sum();
+        let bytes = self
+            .counts()
+            .into_iter()
+            .map(|(k, phrases, words)| {
+                let variants = self.variants(k) as u128;
+                // k - 1 joiners in each phrase, once with each joiner
+                let joined = joiners
+                    .saturating_mul(phrases)
+                    .saturating_mul(k as u128 - 1);
+                words
+                    .saturating_add(phrases)
+                    .saturating_mul(variants)
+                    .saturating_add(joined)
+            })
+            .fold(0u128, u128::saturating_add);
+        u64::try_from(bytes).unwrap_or(u64::MAX)
+    }
+}
+
+impl Iterator for Phrases {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let indexes = self.next.as_ref()?;
+        let words: Vec<&str> = indexes.iter().map(|&i| self.words[i].as_str()).collect();
+        let phrase = words.join(&self.joiners[self.joiner]);
+        if self.joiner + 1 < self.variants(words.len()) {
+            self.joiner += 1;
+            return Some(phrase);
+        }
+        self.joiner = 0;
+        let mut indexes = self.next.take()?;
+        // advance the odometer, moving on to longer phrases when it wraps
+        if self.advance(&mut indexes) {
+            self.next = Some(indexes);
+        } else if indexes.len() < *self.lengths.end() {
+            self.next = self.first(indexes.len() + 1);
+        }
+        Some(phrase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phrases() {
+        let words = ["correct", "Horse"].map(String::from);
+        let phrases = Phrases::new(words.clone(), &[], 1..=2);
+        assert!(phrases.total() == 4);
+        assert!(phrases.byte_len() == phrases.clone().map(|p| p.len() as u64 + 1).sum::<u64>());
+        let all: Vec<String> = phrases.collect();
+        assert!(all == ["correct", "Horse", "correctHorse", "Horsecorrect"]);
+
+        // a word is used once per phrase, whatever its casing
+        let cased = Phrases::new(words, &[Casing::Lower, Casing::Title], 2..=2);
+        assert!(cased.words() == ["correct", "Correct", "horse", "Horse"]);
+        assert!(cased.total() == 8);
+        assert!(cased.byte_len() == cased.clone().map(|p| p.len() as u64 + 1).sum::<u64>());
+        let all: Vec<String> = cased.collect();
+        assert!(all[..2] == ["correcthorse", "correctHorse"] && all.len() == 8);
+        assert!(!all.iter().any(|p| p.to_lowercase() == "correctcorrect"));
+        assert!(Casing::Title.apply("hORSE") == "Horse");
+        assert!("camel".parse::<Casing>().is_err());
+
+        let joined = Phrases::new(["a", "b"].map(String::from), &[], 1..=2)
+            .with_joiners(vec!["-".to_string(), " ".to_string()]);
+        assert!(joined.total() == 6);
+        assert!(joined.byte_len() == joined.clone().map(|p| p.len() as u64 + 1).sum::<u64>());
+        let all: Vec<String> = joined.collect();
+        assert!(all == ["a", "b", "a-b", "a b", "b-a", "b a"]);
+
+        // repeated words count once, and phrases stop at the dictionary's size
+        let words = ["a", "b", "a", "c"].map(String::from);
+        let phrases = Phrases::new(words, &[Casing::AsIs, Casing::Upper], 2..=5);
+        assert!(phrases.total() == 3 * 2 * 4 + 6 * 8);
+        assert!(phrases.byte_len() == phrases.clone().map(|p| p.len() as u64 + 1).sum::<u64>());
+        let all: Vec<String> = phrases.collect();
+        assert!(all.len() == 72 && all[71] == "CBA");
+        assert!(Phrases::new(["a"].map(String::from), &[], 2..=3)
+            .next()
+            .is_none());
+    }
+}
@@ -0,0 +1,206 @@
+//! Numeric candidates such as PINs, the patterns people favour first.
+
+use std::{collections::HashSet, ops::RangeInclusive};
+
+/// Longest PIN generated, so every length fits a `u64`.
+pub const MAX_LENGTH: usize = 18;
+
+/// Years tried on their own and in dates.
+const YEARS: RangeInclusive<u32> = 1940..=2039;
+
+/// Every number of `lengths` digits, zero-padded, the likely ones first.
+///
+/// Likely numbers of every length come first, shortest first: repeated
+/// digits (`1111`), ascending and descending runs (`1234`, `9876`) and
+/// repeated pairs (`1212`), then, where the length fits, years (`1987`)
+/// and dates as `MMDD`/`DDMM`, `DDMMYY`/`MMDDYY`/`YYMMDD` or
+/// `DDMMYYYY`/`MMDDYYYY`/`YYYYMMDD`. Every other number follows in
+/// ascending order, shortest first, so each is generated exactly once.
+#[derive(Debug, Clone)]
+pub struct Pins {
+    lengths: RangeInclusive<usize>,
+    /// Likely numbers, in the order they're tried.
+    likely: Vec<String>,
+    /// The same, left out of the full enumeration.
+    seen: HashSet<String>,
+    next_likely: usize,
+    /// Length and value of the next number enumerated; `None` once
+    /// exhausted.
+    next: Option<(usize, u64)>,
+}
+
+impl Pins {
+    /// Numbers of `lengths` digits, clamped to 1 to [`MAX_LENGTH`].
+    pub fn new(lengths: RangeInclusive<usize>) -> Self {
+        let lengths = (*lengths.start()).max(1)..=(*lengths.end()).min(MAX_LENGTH);
+        let mut likely = vec![];
+        let mut seen = HashSet::new();
+        for len in lengths.clone() {
+            for pin in patterns(len) {
+                if seen.insert(pin.clone()) {
+                    likely.push(pin);
+                }
+            }
+        }
+        let mut pins = Self {
+            lengths,
+            likely,
+            seen,
+            next_likely: 0,
+            next: None,
+        };
+        pins.rewind();
+        pins
+    }
+
+    /// Start over from the first number.
+    pub fn rewind(&mut self) {
+        self.next_likely = 0;
+        self.next = if self.lengths.is_empty() {
+            None
+        } else {
+            Some((*self.lengths.start(), 0))
+        };
+    }
+
+    /// The numbers tried before the rest.
+    pub fn likely(&self) -> &[String] {
+        &self.likely
+    }
+
+    /// Number of numbers, saturating at `u128::MAX`.
+    pub fn total(&self) -> u128 {
+        self.lengths
+            .clone()
+            .map(|len| 10u128.pow(len as u32))
+            .fold(0u128, u128::saturating_add)
+    }
+
+    /// Total size of every number in bytes, newlines included.
+    pub fn byte_len(&self) -> u64 {
+        self.lengths
+            .clone()
+            .map(|len| {
+                10u64
+                    .saturating_pow(len as u32)
+                    .saturating_mul(len as u64 + 1)
+            })
+            .fold(0u64, u64::saturating_add)
+    }
+}
+
+impl Iterator for Pins {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if let Some(pin) = self.likely.get(self.next_likely) {
+            self.next_likely += 1;
+            return Some(pin.clone());
+        }
+        loop {
+            let (len, n) = self.next?;
+            self.next = if n + 1 < 10u64.pow(len as u32) {
+                Some((len, n + 1))
+            } else if len < *self.lengths.end() {
+                Some((len + 1, 0))
+            } else {
+                None
+            };
+            let pin = format!("{:0width$}", n, width = len);
+            if !self.seen.contains(&pin) {
+                return Some(pin);
+            }
+        }
+    }
+}
+
+/// Likely numbers of `len` digits, likeliest kinds first. Kinds may
+/// overlap.
+fn patterns(len: usize) -> Vec<String> {
+    let digit = |d: usize| char::from(b'0' + (d % 10) as u8);
+    let mut pins = vec![];
+    for d in 0..10 {
+        pins.push(std::iter::repeat_n(digit(d), len).collect());
+    }
+    for start in 0..10 {
+        pins.push((0..len).map(|i| digit(start + i)).collect());
+    }
+    for start in (0..10).rev() {
+        pins.push((0..len).map(|i| digit(start + 10 * len - i)).collect());
+    }
+    if len >= 4 && len.is_multiple_of(2) {
+        for a in 0..10 {
+            for b in (0..10).filter(|&b| b != a) {
+                pins.push(
+                    (0..len)
+                        .map(|i| digit(if i % 2 == 0 { a } else { b }))
+                        .collect(),
+                );
+            }
+        }
+    }
+    let days = || (1..=12u32).flat_map(|month| (1..=days_in(month)).map(move |day| (month, day)));
+    match len {
+        4 => {
+            pins.extend(YEARS.map(|year| year.to_string()));
+            pins.extend(days().map(|(m, d)| format!("{:02}{:02}", m, d)));
+            pins.extend(days().map(|(m, d)| format!("{:02}{:02}", d, m)));
+        }
+        6 => {
+            for year in YEARS {
+                let y = year % 100;
+                for (m, d) in days() {
+                    pins.push(format!("{:02}{:02}{:02}", d, m, y));
+                    pins.push(format!("{:02}{:02}{:02}", m, d, y));
+                    pins.push(format!("{:02}{:02}{:02}", y, m, d));
+                }
+            }
+        }
+        8 => {
+            for year in YEARS {
+                for (m, d) in days() {
+                    pins.push(format!("{:02}{:02}{}", d, m, year));
+                    pins.push(format!("{:02}{:02}{}", m, d, year));
+                    pins.push(format!("{}{:02}{:02}", year, m, d));
+                }
+            }
+        }
+        _ => {}
+    }
+    pins
+}
+
+/// Days in `month`, counting February 29.
+fn days_in(month: u32) -> u32 {
+    match month {
+        2 => 29,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pins() {
+        let pins = Pins::new(4..=4);
+        assert!(pins.total() == 10_000);
+        let all: Vec<String> = pins.clone().collect();
+        assert!(all.len() == 10_000);
+        assert!(all[..3] == ["0000", "1111", "2222"]);
+        assert!(all[11] == "1234" && all[21] == "8765");
+        let unique: HashSet<&String> = all.iter().collect();
+        assert!(unique.len() == 10_000);
+        let year = all.iter().position(|p| p == "1987").unwrap();
+        let date = all.iter().position(|p| p == "3112").unwrap();
+        assert!(year < date && date < all.iter().position(|p| p == "5381").unwrap());
+        assert!(all.contains(&"0229".to_string()));
+        assert!(pins.likely().len() < 1000);
+
+        let pins = Pins::new(1..=2);
+        assert!(pins.byte_len() == pins.clone().map(|p| p.len() as u64 + 1).sum::<u64>());
+        assert!(pins.count() == 110);
+    }
+}
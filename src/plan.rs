@@ -0,0 +1,151 @@
+use std::io::{self, Write};
+
+use log::info;
+use serde::Serialize;
+use structopt::StructOpt;
+use wlinflate::Wordlist;
+
+use crate::{error::Error, validate, Args};
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct PlanArgs {
+    #[structopt(
+        short = "n",
+        long = "nodes",
+        help = "number of nodes to split the keyspace across"
+    )]
+    nodes: usize,
+}
+
+/// Candidate range for one node, as `--start-index`/`--end-index` values.
+#[derive(Debug, Serialize)]
+struct Shard {
+    node: usize,
+    start_index: usize,
+    end_index: usize,
+    candidates: usize,
+    args: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Plan {
+    keyspace: usize,
+    nodes: Vec<Shard>,
+}
+
+/// Split `start..end` into `nodes` contiguous ranges whose sizes differ by
+/// at most one, larger ones first.
+fn partition(start: usize, end: usize, nodes: usize) -> Vec<(usize, usize)> {
+    let total = end.saturating_sub(start);
+    let (share, extra) = (total / nodes, total % nodes);
+    let mut next = start;
+    (0..nodes)
+        .map(|node| {
+            let len = share + usize::from(node < extra);
+            let range = (next, next + len);
+            next += len;
+            range
+        })
+        .collect()
+}
+
+/// Whether filters drop candidates, making the computed size an upper
+/// bound, so the pipeline has to be generated to count them.
+fn drops_candidates(wl: &Wordlist) -> bool {
+    wl.skip_degenerate || wl.directives || !wl.apply.is_empty() || !wl.lengths.is_empty()
+}
+
+/// Exact number of candidates the configured pipeline yields.
+pub fn keyspace(args: &Args) -> Result<usize, Error> {
+    let wl = args.wordlist_at(&args.wordlist)?;
+    if wl.keyspace() >= usize::MAX as u128 {
+        return Err(Error::KeyspaceTooLarge);
+    }
+    if drops_candidates(&wl) {
+        info!("Counting candidates, this pipeline drops some of them");
+        return Ok(args.wordlist_at(&args.wordlist)?.count());
+    }
+    Ok(wl.total_count)
+}
+
+/// The `--start-index`/`--end-index` range, clamped to the candidates the
+/// pipeline yields. Only the range has to fit a `usize`, so a bounded run
+/// of a keyspace too large to count still has one.
+pub fn range(args: &Args) -> Result<(usize, usize), Error> {
+    let wl = args.wordlist_at(&args.wordlist)?;
+    let bound = match args.end_index {
+        Some(end) => wl.keyspace().min(end as u128),
+        None => wl.keyspace(),
+    };
+    if bound >= usize::MAX as u128 {
+        return Err(Error::KeyspaceTooLarge);
+    }
+    let end = if drops_candidates(&wl) {
+        info!("Counting candidates, this pipeline drops some of them");
+        wl.take(bound as usize).count()
+    } else {
+        bound as usize
+    };
+    let start = args.start_index.unwrap_or(0).min(end);
+    Ok((start, end))
+}
+
+/// The `--start-index`/`--end-index` range, clamped to `keyspace`.
+pub fn index_range(args: &Args, keyspace: usize) -> (usize, usize) {
+    let start = args.start_index.unwrap_or(0).min(keyspace);
+    let end = args.end_index.unwrap_or(keyspace).clamp(start, keyspace);
+    (start, end)
+}
+
+/// Print per-node index ranges covering the configured keyspace as JSON.
+pub fn run(args: &Args, plan: &PlanArgs) -> Result<(), Error> {
+    validate(args)?;
+    if plan.nodes == 0 {
+        return Err(Error::InvalidConfig(
+            "--nodes must be at least 1".to_string(),
+        ));
+    }
+
+    let keyspace = keyspace(args)?;
+    let (start, end) = index_range(args, keyspace);
+    let nodes = partition(start, end, plan.nodes)
+        .into_iter()
+        .enumerate()
+        .map(|(node, (start_index, end_index))| Shard {
+            node,
+            start_index,
+            end_index,
+            candidates: end_index - start_index,
+            args: format!("--start-index {} --end-index {}", start_index, end_index),
+        })
+        .collect();
+
+    let mut out = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut out, &Plan { keyspace, nodes }).map_err(io::Error::from)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition() {
+        assert!(partition(0, 10, 3) == vec![(0, 4), (4, 7), (7, 10)]);
+        assert!(partition(5, 7, 3) == vec![(5, 6), (6, 7), (7, 7)]);
+    }
+
+    #[test]
+    fn test_range() {
+        // 26^16 candidates, more than a usize counts on any target
+        let huge = ["wlinflate", "--pattern", "aaaaaaaaaaaaaaaa"];
+        let args = Args::from_iter(
+            huge.iter()
+                .chain(&["--start-index", "2", "--end-index", "5"]),
+        );
+        assert!(range(&args).unwrap() == (2, 5));
+        let args = Args::from_iter(huge);
+        assert!(matches!(range(&args), Err(Error::KeyspaceTooLarge)));
+    }
+}
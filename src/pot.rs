@@ -0,0 +1,70 @@
+//! Reading cracked plaintexts from hashcat and john potfiles.
+
+use std::io::{self, BufRead};
+
+/// The plaintext of a potfile line (`hash:plaintext`), decoding
+/// `$HEX[...]` entries.
+///
+/// The hash may hold colons of its own (salts, NetNTLM), so the plaintext
+/// is taken after the last one. Plaintexts that aren't valid UTF-8 can't
+/// match a candidate and give `None`.
+pub fn plaintext(line: &[u8]) -> Option<String> {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let start = line.iter().rposition(|&b| b == b':')? + 1;
+    let plain = &line[start..];
+    let plain = match plain
+        .strip_prefix(b"$HEX[")
+        .and_then(|hex| hex.strip_suffix(b"]"))
+    {
+        Some(hex) => decode_hex(hex)?,
+        None => plain.to_vec(),
+    };
+    String::from_utf8(plain).ok()
+}
+
+fn decode_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    hex.chunks(2)
+        .map(|pair| {
+            let digits = std::str::from_utf8(pair).ok()?;
+            u8::from_str_radix(digits, 16).ok()
+        })
+        .collect()
+}
+
+/// Every plaintext in a potfile, in file order, skipping lines without one.
+pub fn plaintexts<R: BufRead>(mut reader: R) -> impl Iterator<Item = io::Result<String>> {
+    let mut line = vec![];
+    std::iter::from_fn(move || loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => return None,
+            Ok(_) => {
+                if let Some(plain) = plaintext(&line) {
+                    return Some(Ok(plain));
+                }
+            }
+            Err(e) => return Some(Err(e)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plaintexts() {
+        let pot = b"8846f7eaee8fb117ad06bdd830b7586c:password\r\n\
+            admin::DOMAIN:1122334455667788:aabb:0101:Summer2024!\n\
+            5f4dcc3b5aa765d61d8327deb882cf99:$HEX[70613a7373]\n\
+            no plaintext here\n\
+            hash:$HEX[ff]\n\
+            hash:\n";
+        let plains: Vec<String> = plaintexts(&pot[..]).map(Result::unwrap).collect();
+        assert!(plains == ["password", "Summer2024!", "pa:ss", ""]);
+    }
+}
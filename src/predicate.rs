@@ -0,0 +1,186 @@
+//! Rules and inline directives limiting which base words an affix stage
+//! applies to.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Affix stage a rule controls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affix {
+    Prepend,
+    Append,
+    Extension,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Test {
+    Contains,
+    StartsWith,
+    EndsWith,
+    Equals,
+}
+
+/// `<affix>: if [not] <test> "<text>"`, e.g. `ext: if not contains "."`.
+///
+/// The affix is applied to a base word only if the test on that word
+/// passes; several rules for the same affix must all pass.
+///
+/// Serialized in this same text form.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ApplyRule {
+    pub affix: Affix,
+    pub negate: bool,
+    pub test: Test,
+    pub text: String,
+}
+
+impl ApplyRule {
+    /// Whether this rule lets its affix apply to `base`.
+    pub fn allows(&self, base: &str) -> bool {
+        let hit = match self.test {
+            Test::Contains => base.contains(&self.text),
+            Test::StartsWith => base.starts_with(&self.text),
+            Test::EndsWith => base.ends_with(&self.text),
+            Test::Equals => base == self.text,
+        };
+        hit != self.negate
+    }
+}
+
+impl FromStr for ApplyRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid rule '{}', expected e.g. 'ext: if not contains \".\"'",
+                s
+            )
+        };
+        let (affix, condition) = s.split_once(':').ok_or_else(invalid)?;
+        let affix = match affix.trim() {
+            "prepend" => Affix::Prepend,
+            "append" => Affix::Append,
+            "ext" | "extension" => Affix::Extension,
+            other => return Err(format!("unknown transform '{}' in rule '{}'", other, s)),
+        };
+
+        let condition = condition
+            .trim_start()
+            .strip_prefix("if ")
+            .ok_or_else(invalid)?;
+        let (negate, condition) = match condition.trim_start().strip_prefix("not ") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, condition.trim_start()),
+        };
+        let (test, text) = condition.split_once(' ').ok_or_else(invalid)?;
+        let test = match test {
+            "contains" => Test::Contains,
+            "starts-with" | "startswith" => Test::StartsWith,
+            "ends-with" | "endswith" => Test::EndsWith,
+            "equals" | "is" => Test::Equals,
+            other => return Err(format!("unknown test '{}' in rule '{}'", other, s)),
+        };
+        let text = text.trim();
+        let text = text
+            .strip_prefix('"')
+            .and_then(|t| t.strip_suffix('"'))
+            .unwrap_or(text);
+
+        Ok(Self {
+            affix,
+            negate,
+            test,
+            text: text.to_string(),
+        })
+    }
+}
+
+impl TryFrom<String> for ApplyRule {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ApplyRule> for String {
+    fn from(rule: ApplyRule) -> Self {
+        rule.to_string()
+    }
+}
+
+impl fmt::Display for ApplyRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let affix = match self.affix {
+            Affix::Prepend => "prepend",
+            Affix::Append => "append",
+            Affix::Extension => "ext",
+        };
+        let test = match self.test {
+            Test::Contains => "contains",
+            Test::StartsWith => "starts-with",
+            Test::EndsWith => "ends-with",
+            Test::Equals => "equals",
+        };
+        let not = if self.negate { "not " } else { "" };
+        write!(f, "{}: if {}{} \"{}\"", affix, not, test, self.text)
+    }
+}
+
+/// Split inline directives off a wordlist line, `admin.php #!noext`
+/// becoming `admin.php` with extensions disabled.
+///
+/// Directives follow ` #!`, separated by commas or spaces: `noprepend`,
+/// `noappend`, `noext`, and `swaponly` for none of the affixes. Unknown
+/// directives are ignored.
+pub fn split_directives(line: &str) -> (&str, Vec<Affix>) {
+    let (word, directives) = match line.split_once(" #!") {
+        Some(split) => split,
+        None => return (line, vec![]),
+    };
+    let mut disabled = vec![];
+    for directive in directives.split(|c: char| c == ',' || c.is_whitespace()) {
+        match directive {
+            "noprepend" => disabled.push(Affix::Prepend),
+            "noappend" => disabled.push(Affix::Append),
+            "noext" => disabled.push(Affix::Extension),
+            "swaponly" => disabled.extend([Affix::Prepend, Affix::Append, Affix::Extension]),
+            _ => {}
+        }
+    }
+    (word.trim_end(), disabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_rule() {
+        let rule: ApplyRule = "ext: if not contains \".\"".parse().unwrap();
+        assert!(rule.affix == Affix::Extension && rule.negate);
+        assert!(rule.allows("admin"));
+        assert!(!rule.allows("index.html"));
+
+        let rule: ApplyRule = "prepend: if not starts-with /".parse().unwrap();
+        assert!(!rule.allows("/etc"));
+        assert!(rule.to_string() == "prepend: if not starts-with \"/\"");
+
+        assert!("swap: if contains x".parse::<ApplyRule>().is_err());
+        assert!("ext contains x".parse::<ApplyRule>().is_err());
+    }
+
+    #[test]
+    fn test_split_directives() {
+        assert!(split_directives("admin.php #!noext") == ("admin.php", vec![Affix::Extension]));
+        assert!(
+            split_directives("a  #!noprepend, noappend")
+                == ("a", vec![Affix::Prepend, Affix::Append])
+        );
+        assert!(split_directives("{SWAP} #!swaponly").1.len() == 3);
+        assert!(split_directives("c#!noext") == ("c#!noext", vec![]));
+    }
+}
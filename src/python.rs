@@ -0,0 +1,196 @@
+//! Python bindings, built with the `python` feature (see `pyproject.toml`).
+//!
+//! ```python
+//! import wlinflate
+//!
+//! wl = wlinflate.Wordlist(["words.txt"]).prepend(["dev-"]).extensions([".bak"])
+//! print(wl.keyspace())
+//! for word in wl:
+//!     ...
+//! ```
+
+// the wrappers pyo3 0.22 generates for `PyResult` methods trip this lint
+#![allow(clippy::useless_conversion)]
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{spec::WordlistSpec, Wordlist};
+
+/// Configuration for an inflated wordlist; every iteration starts over
+/// from the first candidate.
+#[pyclass(name = "Wordlist", module = "wlinflate")]
+#[derive(Clone)]
+struct PyWordlist {
+    spec: WordlistSpec,
+}
+
+/// One pass over the candidates.
+#[pyclass(name = "WordlistIterator", module = "wlinflate")]
+struct PyWordlistIter {
+    wordlist: Wordlist,
+}
+
+fn build(spec: &WordlistSpec) -> PyResult<Wordlist> {
+    Ok(spec.build()?)
+}
+
+#[pymethods]
+impl PyWordlist {
+    #[new]
+    fn new(wordlists: Vec<String>) -> Self {
+        Self {
+            spec: WordlistSpec::new(wordlists),
+        }
+    }
+
+    fn prepend(mut slf: PyRefMut<'_, Self>, values: Vec<String>) -> PyRefMut<'_, Self> {
+        slf.spec.prepend = values;
+        slf
+    }
+
+    fn append(mut slf: PyRefMut<'_, Self>, values: Vec<String>) -> PyRefMut<'_, Self> {
+        slf.spec.append = values;
+        slf
+    }
+
+    fn swap(mut slf: PyRefMut<'_, Self>, values: Vec<String>) -> PyRefMut<'_, Self> {
+        slf.spec.swap = values;
+        slf
+    }
+
+    fn extensions(mut slf: PyRefMut<'_, Self>, values: Vec<String>) -> PyRefMut<'_, Self> {
+        slf.spec.extensions = values;
+        slf
+    }
+
+    fn swap_token(mut slf: PyRefMut<'_, Self>, token: String) -> PyRefMut<'_, Self> {
+        slf.spec.swap_token = Some(token);
+        slf
+    }
+
+    fn token_delimiters(
+        mut slf: PyRefMut<'_, Self>,
+        open: String,
+        close: String,
+    ) -> PyRefMut<'_, Self> {
+        slf.spec.token_delimiters = Some((open, close));
+        slf
+    }
+
+    fn nest_depth(mut slf: PyRefMut<'_, Self>, depth: usize) -> PyRefMut<'_, Self> {
+        slf.spec.nest_depth = depth;
+        slf
+    }
+
+    #[pyo3(signature = (count, seed = 0))]
+    fn random(mut slf: PyRefMut<'_, Self>, count: usize, seed: u64) -> PyRefMut<'_, Self> {
+        slf.spec.random_count = count;
+        slf.spec.seed = seed;
+        slf
+    }
+
+    fn var(mut slf: PyRefMut<'_, Self>, name: String, value: String) -> PyRefMut<'_, Self> {
+        slf.spec.vars.insert(name, value);
+        slf
+    }
+
+    fn templates(mut slf: PyRefMut<'_, Self>, lines: Vec<String>) -> PyRefMut<'_, Self> {
+        slf.spec.templates = lines;
+        slf
+    }
+
+    fn word2(mut slf: PyRefMut<'_, Self>, words: Vec<String>) -> PyRefMut<'_, Self> {
+        slf.spec.word2 = words;
+        slf
+    }
+
+    /// Rules such as `ext: if not contains "."`; raises `ValueError` for
+    /// one that does not parse.
+    fn apply(mut slf: PyRefMut<'_, Self>, rules: Vec<String>) -> PyResult<PyRefMut<'_, Self>> {
+        slf.spec.apply = rules
+            .iter()
+            .map(|rule| rule.parse().map_err(PyValueError::new_err))
+            .collect::<PyResult<_>>()?;
+        Ok(slf)
+    }
+
+    #[pyo3(signature = (min_count = 0))]
+    fn weighted(mut slf: PyRefMut<'_, Self>, min_count: u64) -> PyRefMut<'_, Self> {
+        slf.spec.weighted = Some(min_count);
+        slf
+    }
+
+    #[pyo3(signature = (enabled = true))]
+    fn likelihood_order(mut slf: PyRefMut<'_, Self>, enabled: bool) -> PyRefMut<'_, Self> {
+        slf.spec.likelihood_order = enabled;
+        slf
+    }
+
+    #[pyo3(signature = (enabled = true))]
+    fn interleave(mut slf: PyRefMut<'_, Self>, enabled: bool) -> PyRefMut<'_, Self> {
+        slf.spec.interleave = enabled;
+        slf
+    }
+
+    #[pyo3(signature = (enabled = true))]
+    fn skip_degenerate(mut slf: PyRefMut<'_, Self>, enabled: bool) -> PyRefMut<'_, Self> {
+        slf.spec.skip_degenerate = enabled;
+        slf
+    }
+
+    #[pyo3(signature = (enabled = true))]
+    fn ext_strip(mut slf: PyRefMut<'_, Self>, enabled: bool) -> PyRefMut<'_, Self> {
+        slf.spec.ext_strip = enabled;
+        slf
+    }
+
+    #[pyo3(signature = (enabled = true))]
+    fn directives(mut slf: PyRefMut<'_, Self>, enabled: bool) -> PyRefMut<'_, Self> {
+        slf.spec.directives = enabled;
+        slf
+    }
+
+    #[pyo3(signature = (enabled = true))]
+    fn reverse(mut slf: PyRefMut<'_, Self>, enabled: bool) -> PyRefMut<'_, Self> {
+        slf.spec.reverse = enabled;
+        slf
+    }
+
+    /// Number of candidates (an upper bound when filters are in use).
+    fn keyspace(&self) -> PyResult<u128> {
+        Ok(build(&self.spec)?.keyspace())
+    }
+
+    /// The candidate at `index`, or `None` past the end.
+    fn nth(&self, index: usize) -> PyResult<Option<String>> {
+        Ok(build(&self.spec)?.nth_candidate(index))
+    }
+
+    fn __iter__(&self) -> PyResult<PyWordlistIter> {
+        Ok(PyWordlistIter {
+            wordlist: build(&self.spec)?,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Wordlist({:?})", self.spec.wordlists)
+    }
+}
+
+#[pymethods]
+impl PyWordlistIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<String> {
+        self.wordlist.next()
+    }
+}
+
+#[pymodule]
+fn wlinflate(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyWordlist>()?;
+    m.add_class::<PyWordlistIter>()?;
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::BTreeMap,
+    ffi::{OsStr, OsString},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use structopt::{clap::ArgMatches, StructOpt};
+
+use crate::{config, error::Error, Args};
+
+/// A named set of options, read from `<name>.toml` in the recipe
+/// directory and invoked with `--recipe name`.
+///
+/// Keys of `[options]` are long option names, their values given as on
+/// the command line: `true` for a flag, a string or number for a value,
+/// and an array for an option repeated once per value.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Recipe {
+    pub description: Option<String>,
+    pub options: BTreeMap<String, toml::Value>,
+}
+
+impl Recipe {
+    fn from_file(path: &Path) -> Result<Self, Error> {
+        let text = fs::read_to_string(path).map_err(|e| {
+            Error::InvalidConfig(format!("failed to read {}: {}", path.display(), e))
+        })?;
+        toml::from_str(&text)
+            .map_err(|e| Error::InvalidConfig(format!("{}: {}", path.display(), e)))
+    }
+
+    /// The options as command line arguments, leaving out those `given`
+    /// says were set already.
+    fn args(&self, path: &Path, given: impl Fn(&str) -> bool) -> Result<Vec<OsString>, Error> {
+        let mut args = vec![];
+        for (key, value) in &self.options {
+            if key == "recipe" || key == "recipe-dir" {
+                return Err(Error::InvalidConfig(format!(
+                    "{}: recipes can't invoke other recipes",
+                    path.display()
+                )));
+            }
+            if given(key) {
+                continue;
+            }
+            let values = match value {
+                toml::Value::Array(values) => values.as_slice(),
+                value => std::slice::from_ref(value),
+            };
+            for value in values {
+                let arg = match value {
+                    toml::Value::Boolean(true) => format!("--{}", key),
+                    toml::Value::Boolean(false) => continue,
+                    toml::Value::String(s) => format!("--{}={}", key, s),
+                    toml::Value::Integer(n) => format!("--{}={}", key, n),
+                    toml::Value::Float(f) => format!("--{}={}", key, f),
+                    _ => {
+                        return Err(Error::InvalidConfig(format!(
+                            "{}: option '{}' must be a boolean, string, number or array of those",
+                            path.display(),
+                            key
+                        )))
+                    }
+                };
+                args.push(arg.into());
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Where recipes are looked up when `--recipe-dir` isn't given.
+pub fn default_dir() -> Option<PathBuf> {
+    Some(config::dir()?.join("recipes"))
+}
+
+/// Names and descriptions of the recipes in `dir`, sorted by name.
+fn available(dir: &Path) -> Vec<(String, Option<String>)> {
+    let mut recipes: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension() == Some(OsStr::new("toml")))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            let description = Recipe::from_file(&path).ok()?.description;
+            Some((name, description))
+        })
+        .collect();
+    recipes.sort();
+    recipes
+}
+
+/// The file of the recipe `args` name with `--recipe`, if any.
+pub fn file(args: &Args) -> Result<Option<PathBuf>, Error> {
+    let name = match &args.recipe {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(Error::InvalidConfig(format!(
+            "invalid recipe name '{}'",
+            name
+        )));
+    }
+    let dir = args
+        .recipe_dir
+        .clone()
+        .or_else(default_dir)
+        .ok_or_else(|| Error::InvalidConfig("no recipe directory, set --recipe-dir".to_string()))?;
+    Ok(Some(dir.join(format!("{}.toml", name))))
+}
+
+/// Whether option `key` was given on the command line in `args`.
+fn given(matches: &ArgMatches, args: &[OsString], key: &str) -> bool {
+    let long = format!("--{}", key);
+    let with_value = format!("{}=", long);
+    matches.occurrences_of(key) > 0
+        || matches.occurrences_of(key.replace('-', "_")) > 0
+        || args
+            .iter()
+            .filter_map(|a| a.to_str())
+            .any(|a| a == long || a.starts_with(&with_value))
+}
+
+/// Expand `--recipe name` in the command line `args` into the options of
+/// the recipe, placed before the others so options given on the command
+/// line win. Arguments that don't parse are returned as they are, for
+/// the parser to report.
+pub fn expand(mut args: Vec<OsString>) -> Result<Vec<OsString>, Error> {
+    let matches = match Args::clap().get_matches_from_safe(&args) {
+        Ok(matches) => matches,
+        Err(_) => return Ok(args),
+    };
+    let path = match file(&Args::from_clap(&matches))? {
+        Some(path) => path,
+        None => return Ok(args),
+    };
+    if !path.is_file() {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let names: Vec<String> = available(dir)
+            .into_iter()
+            .map(|(name, description)| match description {
+                Some(description) => format!("{} ({})", name, description),
+                None => name,
+            })
+            .collect();
+        return Err(Error::InvalidConfig(if names.is_empty() {
+            format!("unknown recipe '{}', {} has none", name, dir.display())
+        } else {
+            format!(
+                "unknown recipe '{}', expected one of {}",
+                name,
+                names.join(", ")
+            )
+        }));
+    }
+    let recipe = Recipe::from_file(&path)?;
+    let inserted = recipe.args(&path, |key| given(&matches, &args, key))?;
+    args.splice(1..1, inserted);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let dir =
+            std::env::temp_dir().join(format!("wlinflate_test_recipes_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("hosts.toml"),
+            "description = \"dev hosts\"\n\
+             [options]\n\
+             prepend = [\"dev-\", \"stg-\"]\n\
+             append = \"-01\"\n\
+             reverse-order = true\n\
+             unique = false\n",
+        )
+        .unwrap();
+        let command = |extra: &[&str]| -> Vec<OsString> {
+            let mut args: Vec<OsString> = ["wlinflate", "-w", "words.txt", "--recipe-dir"]
+                .iter()
+                .map(OsString::from)
+                .collect();
+            args.push(dir.clone().into());
+            args.extend(extra.iter().map(OsString::from));
+            args
+        };
+
+        let args = expand(command(&["--recipe", "hosts", "-a", "_02"])).unwrap();
+        let parsed = Args::from_iter(&args);
+        assert!(parsed.prepend == ["dev-", "stg-"]);
+        assert!(parsed.append == ["_02"]);
+        assert!(parsed.reverse_order && !parsed.unique);
+        assert!(expand(command(&[])).unwrap() == command(&[]));
+
+        let unknown = expand(command(&["--recipe", "web"])).unwrap_err();
+        assert!(unknown.to_string().contains("hosts (dev hosts)"));
+        assert!(expand(command(&["--recipe", "../hosts"])).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// What a previous, possibly interrupted, run left in its output file.
+pub struct Tail {
+    /// Number of complete (newline-terminated) lines.
+    pub lines: usize,
+    /// Byte length of those complete lines; anything after is a torn write.
+    pub complete_len: u64,
+    /// The last complete line, without its terminator.
+    pub last: Option<String>,
+}
+
+pub fn inspect(path: &Path) -> io::Result<Tail> {
+    let mut file = File::open(path)?;
+    let mut tail = Tail {
+        lines: 0,
+        complete_len: 0,
+        last: None,
+    };
+    // only the newlines are counted; the last line is read back after
+    let mut last_start = 0;
+    let mut offset = 0;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for (i, _) in buf[..n].iter().enumerate().filter(|(_, b)| **b == b'\n') {
+            tail.lines += 1;
+            last_start = tail.complete_len;
+            tail.complete_len = offset + i as u64 + 1;
+        }
+        offset += n as u64;
+    }
+    if tail.lines > 0 {
+        let mut line = vec![0u8; (tail.complete_len - last_start) as usize];
+        file.seek(SeekFrom::Start(last_start))?;
+        file.read_exact(&mut line)?;
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        tail.last = Some(String::from_utf8_lossy(&line).into_owned());
+    }
+    Ok(tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structopt::StructOpt;
+
+    #[test]
+    fn test_inspect() {
+        let path =
+            std::env::temp_dir().join(format!("wlinflate_test_resume_{}.txt", std::process::id()));
+        std::fs::write(&path, "admin\r\nlogin\npass").unwrap();
+        let tail = inspect(&path).unwrap();
+        assert!(tail.lines == 2 && tail.complete_len == 13);
+        assert!(tail.last.as_deref() == Some("login"));
+
+        std::fs::write(&path, "torn").unwrap();
+        let tail = inspect(&path).unwrap();
+        assert!(tail.lines == 0 && tail.complete_len == 0 && tail.last.is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_resume_filtered() {
+        let dir =
+            std::env::temp_dir().join(format!("wlinflate_test_resume_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let words = dir.join("words.txt");
+        std::fs::write(&words, "admin\nlogin\n").unwrap();
+        let out = dir.join("out.txt");
+        std::fs::write(&out, "admin\n").unwrap();
+        for filter in [
+            &["--unique"][..],
+            &["--unique-window", "10"],
+            &["--filter-exec", "cat"],
+        ] {
+            let mut command = vec!["wlinflate", "-w", words.to_str().unwrap()];
+            command.extend(filter);
+            for resume in [&["--resume-from-output"][..], &["--append-output", "-o"]] {
+                let mut command = command.clone();
+                command.extend(resume);
+                command.push(out.to_str().unwrap());
+                let args = crate::Args::from_iter(command);
+                let e = crate::run(&args).err().unwrap();
+                assert!(e.exit_code() == 2, "{:?} {:?}", filter, resume);
+            }
+            assert!(std::fs::read_to_string(&out).unwrap() == "admin\n");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,38 @@
+/// SplitMix64 generator.
+///
+/// Small and fast, and the sequence for a seed never changes, which is all
+/// reproducible candidate generation needs. Not suitable for secrets.
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform-ish value in `0..n`; the modulo bias is negligible for the
+    /// small ranges used here.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splitmix64_known_values() {
+        let mut rng = SplitMix64::new(0);
+        assert!(rng.next_u64() == 0xe220a8397b1dcdaf);
+        assert!(rng.next_u64() == 0x6e789e6aa1b965f4);
+    }
+}
@@ -0,0 +1,105 @@
+//! Substitution rules read with `--rules` from a file or standard input.
+
+use std::{
+    fs,
+    io::{self, BufRead},
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use log::warn;
+use wlinflate::mutate::SubRule;
+
+use crate::error::Error;
+
+/// The rule on `line`, if it isn't blank or a `#` comment.
+fn rule(line: &str) -> Option<Result<SubRule, String>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    Some(line.parse())
+}
+
+/// Rules in `path`, one per line, or on standard input if it is `-`.
+///
+/// Standard input is read up to its first blank line, so rules typed in
+/// can be started on while more are added with [`follow`]; whether input
+/// is left after that is returned too.
+pub fn read(path: &Path) -> Result<(Vec<String>, bool), Error> {
+    if path.as_os_str() != "-" {
+        let text = fs::read_to_string(path).map_err(|e| Error::Input(path.to_path_buf(), e))?;
+        return Ok((parse(path, text.lines())?, false));
+    }
+    let mut lines = vec![];
+    let mut more = false;
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|e| Error::Input(path.to_path_buf(), e))?;
+        if line.trim().is_empty() {
+            more = true;
+            break;
+        }
+        lines.push(line);
+    }
+    Ok((parse(path, lines.iter().map(String::as_str))?, more))
+}
+
+fn parse<'a>(path: &Path, lines: impl Iterator<Item = &'a str>) -> Result<Vec<String>, Error> {
+    let mut rules = vec![];
+    let name = match path.to_str() {
+        Some("-") => "standard input".into(),
+        _ => path.display().to_string(),
+    };
+    for (i, line) in lines.enumerate() {
+        if let Some(parsed) = rule(line) {
+            parsed.map_err(|e| Error::InvalidConfig(format!("{} line {}: {}", name, i + 1, e)))?;
+            rules.push(line.trim().to_string());
+        }
+    }
+    Ok(rules)
+}
+
+/// Rules still arriving on standard input, passed on as they are read.
+/// Lines that aren't valid rules are reported and skipped.
+pub fn follow() -> Receiver<SubRule> {
+    let (sender, rules) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            let rule = match rule(&line) {
+                Some(Ok(rule)) => rule,
+                Some(Err(e)) => {
+                    warn!("Ignoring rule '{}': {}", line.trim(), e);
+                    continue;
+                }
+                None => continue,
+            };
+            if sender.send(rule).is_err() {
+                break;
+            }
+        }
+    });
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let path =
+            std::env::temp_dir().join(format!("wlinflate_test_rules_{}.txt", std::process::id()));
+        fs::write(&path, "# leet\n s/a/4/g \n\ns/o/0/k\n").unwrap();
+        let (rules, more) = read(&path).unwrap();
+        assert!(rules == ["s/a/4/g", "s/o/0/k"] && !more);
+        fs::write(&path, "s/a/4/\nnope\n").unwrap();
+        let e = read(&path).unwrap_err();
+        assert!(e.to_string().contains("line 2"));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+//! Candidates passed through a user's [Rhai](https://rhai.rs) script, for
+//! expansion logic no option covers.
+
+use std::{cell::Cell, hash::Hasher, path::Path, rc::Rc};
+
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+use wlinflate::hash::Fnv64;
+
+use crate::error::Error;
+
+/// A script run once per candidate, with the candidate in `word`.
+///
+/// What the script evaluates to decides what is written: a string
+/// replaces the candidate, an array of strings is written in its place,
+/// `true` keeps it, and `false` or `()` drops it.
+///
+/// `rand(n)` gives a number in `0..n` fixed by the seed, the candidate and
+/// how many times it was called for it, so a script picks the same
+/// numbers wherever a run starts or however many threads it uses.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    seed: u64,
+    /// Hash of the seed and the current candidate, and `rand` calls on it.
+    state: Rc<Cell<(u64, u64)>>,
+}
+
+impl Script {
+    /// Compile the script at `path`, with `seed` behind `rand`.
+    pub fn load(path: &Path, seed: u64) -> Result<Self, Error> {
+        let mut engine = Engine::new();
+        let state = Rc::new(Cell::new((seed, 0)));
+        let rand_state = state.clone();
+        engine.register_fn("rand", move |n: i64| -> Result<i64, Box<EvalAltResult>> {
+            if n <= 0 {
+                return Err(format!("rand({}) needs a positive bound", n).into());
+            }
+            let (word, calls) = rand_state.get();
+            rand_state.set((word, calls + 1));
+            let mut hasher = Fnv64::default();
+            hasher.write_u64(word);
+            hasher.write_u64(calls);
+            Ok((hasher.finish() % n as u64) as i64)
+        });
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| Error::InvalidConfig(format!("{}: {}", path.display(), e)))?;
+        Ok(Self {
+            engine,
+            ast,
+            scope: Scope::new(),
+            seed,
+            state,
+        })
+    }
+
+    /// Run the script on `word`, passing each candidate it gives to `f`.
+    pub fn apply(&mut self, word: &str, mut f: impl FnMut(&str)) -> Result<(), Error> {
+        let mut hasher = Fnv64::default();
+        hasher.write_u64(self.seed);
+        hasher.write(word.as_bytes());
+        self.state.set((hasher.finish(), 0));
+        self.scope.clear();
+        self.scope.push("word", word.to_string());
+        let result: Dynamic = self
+            .engine
+            .eval_ast_with_scope(&mut self.scope, &self.ast)
+            .map_err(|e| Error::InvalidConfig(format!("script failed on '{}': {}", word, e)))?;
+        let invalid = |value: &Dynamic| {
+            Error::InvalidConfig(format!(
+                "script returned {} for '{}', expected a string, an array of strings, a boolean or ()",
+                value.type_name(),
+                word
+            ))
+        };
+        if result.is_unit() {
+            return Ok(());
+        }
+        if let Ok(keep) = result.as_bool() {
+            if keep {
+                f(word);
+            }
+            return Ok(());
+        }
+        if let Some(candidate) = result.read_lock::<rhai::ImmutableString>() {
+            f(candidate.as_str());
+            return Ok(());
+        }
+        if let Some(candidates) = result.read_lock::<rhai::Array>() {
+            for candidate in candidates.iter() {
+                let candidate = candidate
+                    .read_lock::<rhai::ImmutableString>()
+                    .ok_or_else(|| invalid(candidate))?;
+                f(candidate.as_str());
+            }
+            return Ok(());
+        }
+        Err(invalid(&result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script() {
+        let path =
+            std::env::temp_dir().join(format!("wlinflate_test_script_{}.rhai", std::process::id()));
+        std::fs::write(
+            &path,
+            "if word.len() < 3 { () }\n\
+             else if word.starts_with(\"x\") { [word, word + \"!\"] }\n\
+             else if word == \"keep\" { true }\n\
+             else { word.to_upper() }\n",
+        )
+        .unwrap();
+        let mut script = Script::load(&path, 7).unwrap();
+        let mut out = vec![];
+        for word in ["ab", "xyz", "keep", "abc"] {
+            script.apply(word, |c| out.push(c.to_string())).unwrap();
+        }
+        assert!(out == ["xyz", "xyz!", "keep", "ABC"]);
+
+        std::fs::write(&path, "42").unwrap();
+        let mut script = Script::load(&path, 7).unwrap();
+        assert!(script.apply("word", |_| {}).is_err());
+        std::fs::write(&path, "`${word}${rand(1000)}`").unwrap();
+        let mut script = Script::load(&path, 7).unwrap();
+        let mut picks = vec![];
+        for word in ["a", "b", "a"] {
+            script.apply(word, |c| picks.push(c.to_string())).unwrap();
+        }
+        assert!(picks[0] == picks[2] && picks[0] != picks[1]);
+        let mut other = Script::load(&path, 8).unwrap();
+        other.apply("a", |c| picks.push(c.to_string())).unwrap();
+        assert!(picks[3] != picks[0]);
+
+        std::fs::write(&path, "if {").unwrap();
+        assert!(Script::load(&path, 7).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
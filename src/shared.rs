@@ -0,0 +1,122 @@
+//! A wordlist several threads can draw candidates from.
+
+use std::sync::Mutex;
+
+use crate::Wordlist;
+
+/// Candidates handed out in order to any number of consumer threads.
+///
+/// Each [`SharedWordlist::next_batch`] call takes the next run of
+/// candidates under a lock, so every candidate goes to exactly one caller.
+/// Larger batches mean less time waiting on the lock.
+///
+/// ```no_run
+/// use std::thread;
+/// use wlinflate::{shared::SharedWordlist, spec::WordlistSpec};
+///
+/// let shared = SharedWordlist::new(WordlistSpec::new(["words.txt"]).build()?);
+/// thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             while let Some(batch) = shared.next_batch(1024) {
+///                 for word in batch.words {
+///                     // probe `word`
+///                 }
+///             }
+///         });
+///     }
+/// });
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct SharedWordlist {
+    inner: Mutex<Wordlist>,
+}
+
+/// A run of consecutive candidates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Batch {
+    /// Index of the first word in the sequence.
+    pub start: usize,
+    pub words: Vec<String>,
+}
+
+impl SharedWordlist {
+    pub fn new(wordlist: Wordlist) -> Self {
+        Self {
+            inner: Mutex::new(wordlist),
+        }
+    }
+
+    /// Take up to `size` candidates, or `None` once all have been taken.
+    pub fn next_batch(&self, size: usize) -> Option<Batch> {
+        let mut wl = self.inner.lock().unwrap();
+        let start = wl.position();
+        let words: Vec<String> = wl.by_ref().take(size).collect();
+        if words.is_empty() {
+            return None;
+        }
+        Some(Batch { start, words })
+    }
+
+    /// Index of the next candidate to be handed out.
+    pub fn position(&self) -> usize {
+        self.inner.lock().unwrap().position()
+    }
+
+    pub fn into_inner(self) -> Wordlist {
+        self.inner.into_inner().unwrap()
+    }
+}
+
+impl From<Wordlist> for SharedWordlist {
+    fn from(wordlist: Wordlist) -> Self {
+        Self::new(wordlist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::spec::WordlistSpec;
+
+    #[test]
+    fn test_shared_batches() {
+        let path =
+            &std::env::temp_dir().join(format!("wlinflate_test_shared_{}.txt", std::process::id()));
+        let words: String = (0..100).map(|i| format!("w{}\n", i)).collect();
+        std::fs::write(path, words).unwrap();
+        let spec = WordlistSpec {
+            append: vec!["1".to_string(), "2".to_string()],
+            ..WordlistSpec::new([path])
+        };
+        let full: Vec<String> = spec.build().unwrap().collect();
+
+        let shared = SharedWordlist::new(spec.build().unwrap());
+        let mut batches: Vec<Batch> = thread::scope(|s| {
+            let consumers: Vec<_> = (0..4)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut taken = vec![];
+                        while let Some(batch) = shared.next_batch(7) {
+                            taken.push(batch);
+                        }
+                        taken
+                    })
+                })
+                .collect();
+            consumers
+                .into_iter()
+                .flat_map(|c| c.join().unwrap())
+                .collect()
+        });
+        std::fs::remove_file(path).unwrap();
+
+        batches.sort_by_key(|batch| batch.start);
+        let joined: Vec<String> = batches.iter().flat_map(|b| b.words.clone()).collect();
+        assert!(joined == full);
+        assert!(batches.iter().all(|b| full[b.start] == b.words[0]));
+        assert!(shared.position() == full.len());
+    }
+}
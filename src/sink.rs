@@ -0,0 +1,262 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufReader, Write},
+};
+
+use log::{debug, log_enabled, trace, Level};
+use wlinflate::{
+    filter::{Dedup, WindowDedup},
+    parallel::Chunk,
+    pot, Origin, Wordlist,
+};
+
+use crate::{
+    error::Error,
+    external::External,
+    script::Script,
+    stats::{Coverage, Stats},
+    Args, DEFAULT_BUFFER_SIZE,
+};
+
+/// Filters, counts and writes candidates on their way to the output.
+pub struct Sink<W: Write> {
+    pub writer: W,
+    dedup: Option<Dedup>,
+    window: Option<WindowDedup>,
+    /// Words never to write, such as plaintexts already cracked.
+    excluded: Option<HashSet<String>>,
+    script: Option<Script>,
+    external: Option<External>,
+    annotate: bool,
+    pub stats: Stats,
+    pub coverage: Option<Coverage>,
+    /// Candidates the wordlist produced, before any filters.
+    pub generated: usize,
+    /// Candidates written, and bytes including separators and tags.
+    pub count: usize,
+    pub bytes: u64,
+    pub duplicates_removed: usize,
+    /// Candidates dropped for being excluded.
+    pub filtered: usize,
+}
+
+impl<W: Write> Sink<W> {
+    pub fn new(args: &Args, wl: &Wordlist, writer: W) -> Self {
+        let dedup = if args.unique || args.unique_insensitive {
+            let dedup = Dedup::new(args.unique_insensitive);
+            Some(match args.memory_limit {
+                Some(limit) => dedup.with_memory_limit(limit),
+                None => dedup,
+            })
+        } else {
+            None
+        };
+        Self {
+            writer,
+            dedup,
+            window: args.unique_window.map(WindowDedup::new),
+            excluded: None,
+            script: None,
+            external: None,
+            annotate: args.annotate,
+            stats: Stats::new(wl),
+            coverage: args.coverage.then(Coverage::default),
+            generated: 0,
+            count: 0,
+            bytes: 0,
+            duplicates_removed: 0,
+            filtered: 0,
+        }
+    }
+
+    /// Set up the filters `args` ask for beyond deduplication:
+    /// `--potfile`, `--script` and `--filter-exec`.
+    pub fn configure(&mut self, args: &Args) -> Result<(), Error> {
+        if let Some(path) = &args.potfile {
+            let file = File::open(path).map_err(|e| Error::Input(path.clone(), e))?;
+            let cracked = pot::plaintexts(BufReader::new(file))
+                .collect::<io::Result<HashSet<_>>>()
+                .map_err(|e| Error::Input(path.clone(), e))?;
+            debug!("Excluding {} cracked plaintexts", cracked.len());
+            self.exclude(cracked);
+        }
+        if let Some(path) = &args.script {
+            self.script(Script::load(path, args.seed.unwrap_or_default())?);
+        }
+        if let Some(command) = &args.filter_exec {
+            let buffer_size = args.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
+            self.external(External::spawn(
+                command,
+                buffer_size,
+                args.seed.unwrap_or_default(),
+            )?);
+        }
+        Ok(())
+    }
+
+    /// Drop candidates found in `words`.
+    pub fn exclude(&mut self, words: HashSet<String>) {
+        self.excluded = Some(words);
+    }
+
+    /// Pass candidates through `script` before the filters.
+    pub fn script(&mut self, script: Script) {
+        self.script = Some(script);
+    }
+
+    /// Keep only the candidates `external` prints back.
+    pub fn external(&mut self, external: External) {
+        self.external = Some(external);
+    }
+
+    /// Whether candidates go out unchanged, so they can be written without
+    /// looking at them.
+    pub fn is_plain(&self) -> bool {
+        self.dedup.is_none()
+            && self.excluded.is_none()
+            && self.script.is_none()
+            && self.external.is_none()
+            && self.coverage.is_none()
+            && self.window.is_none()
+            && !self.annotate
+            && !log_enabled!(Level::Trace)
+    }
+
+    /// Write the next candidate of `wl` straight to the output, returning
+    /// `false` once it is exhausted. Only for plain sinks.
+    pub fn write_next(&mut self, wl: &mut Wordlist) -> io::Result<bool> {
+        match wl.write_next(&mut self.writer, b"\n")? {
+            Some((len, origin)) => {
+                self.stats.record(&origin);
+                self.bytes += len as u64;
+                self.generated += 1;
+                self.count += 1;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Copy up to `limit` candidates of a passthrough `wl` to the output in
+    /// bulk, returning `false` once it is exhausted. Only for plain sinks.
+    pub fn write_block(&mut self, wl: &mut Wordlist, limit: usize) -> io::Result<bool> {
+        match wl.write_block(&mut self.writer, limit)? {
+            Some((count, len)) => {
+                self.bytes += len as u64;
+                self.generated += count;
+                self.count += count;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Write every candidate in `chunk` at once. Only for plain sinks.
+    pub fn write_chunk(&mut self, chunk: &Chunk) -> io::Result<()> {
+        self.writer.write_all(chunk.lines().as_bytes())?;
+        for (_, origin) in chunk.iter() {
+            self.stats.record(origin);
+        }
+        self.bytes += chunk.lines().len() as u64;
+        self.generated += chunk.len();
+        self.count += chunk.len();
+        Ok(())
+    }
+
+    /// Write `word`, or what the script turns it into, unless a filter
+    /// drops it.
+    pub fn emit(&mut self, wl: &Wordlist, word: &str, origin: &Origin) -> Result<(), Error> {
+        self.generated += 1;
+        if let Some(script) = &mut self.script {
+            let mut candidates = vec![];
+            script.apply(word, |candidate| candidates.push(candidate.to_string()))?;
+            for candidate in &candidates {
+                self.pass(wl, candidate, origin)?;
+            }
+            return Ok(());
+        }
+        self.pass(wl, word, origin)
+    }
+
+    /// Hand `word` to the filter command, if any, and write what it keeps.
+    fn pass(&mut self, wl: &Wordlist, word: &str, origin: &Origin) -> Result<(), Error> {
+        match &mut self.external {
+            Some(external) => {
+                for (word, origin) in external.send(word, origin)? {
+                    self.admit(wl, &word, &origin)?;
+                }
+                Ok(())
+            }
+            None => Ok(self.admit(wl, word, origin)?),
+        }
+    }
+
+    /// Write `word` unless a filter drops it.
+    fn admit(&mut self, wl: &Wordlist, word: &str, origin: &Origin) -> io::Result<()> {
+        if let Some(excluded) = &self.excluded {
+            if excluded.contains(word) {
+                self.filtered += 1;
+                return Ok(());
+            }
+        }
+        if let Some(window) = &mut self.window {
+            if !window.insert(word) {
+                self.duplicates_removed += 1;
+                return Ok(());
+            }
+        }
+        if let Some(dedup) = &mut self.dedup {
+            if !dedup.admit(word)? {
+                self.duplicates_removed += 1;
+                return Ok(());
+            }
+        }
+        self.write(wl, word, origin)
+    }
+
+    /// Write out what is buffered, for when no candidates are coming for
+    /// a while.
+    pub fn flush(&mut self, wl: &Wordlist) -> Result<(), Error> {
+        if let Some(external) = &mut self.external {
+            for (word, origin) in external.flush()? {
+                self.admit(wl, &word, &origin)?;
+            }
+        }
+        Ok(self.writer.flush()?)
+    }
+
+    /// Write what the filter command still keeps, once every other word
+    /// has been written.
+    pub fn finish(&mut self, wl: &Wordlist) -> Result<(), Error> {
+        if let Some(external) = self.external.take() {
+            for (word, origin) in external.finish()? {
+                self.admit(wl, &word, &origin)?;
+            }
+        }
+        if self.dedup.as_ref().is_some_and(Dedup::is_spilled) {
+            debug!("--unique kept words past the memory limit in temporary files");
+        }
+        Ok(())
+    }
+
+    /// Write `word` and its tag, past any filters.
+    fn write(&mut self, wl: &Wordlist, word: &str, origin: &Origin) -> io::Result<()> {
+        self.stats.record(origin);
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(word);
+        }
+        trace!("{}", word);
+        self.writer.write_all(word.as_bytes())?;
+        self.bytes += word.len() as u64 + 1;
+        if self.annotate {
+            let tag = wl.describe(origin);
+            self.writer.write_all(b"\t")?;
+            self.writer.write_all(tag.as_bytes())?;
+            self.bytes += tag.len() as u64 + 1;
+        }
+        self.writer.write_all(b"\n")?;
+        self.count += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,430 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    format::InputFormat, matches::Matches, pattern::Patterns, phone::Phones, phrase::Phrases,
+    pin::Pins,
+};
+
+/// Where base words are read from.
+pub(crate) enum Source {
+    /// Streamed line by line from the wordlist file.
+    File(BufReader<File>),
+    /// Preloaded (and possibly reordered) words.
+    Memory { words: Vec<String>, next: usize },
+    /// Multi-word phrases made up as they are read.
+    Phrases(Phrases),
+    /// Numbers made up as they are read.
+    Pins(Pins),
+    /// Phone numbers made up as they are read.
+    Phones(Phones),
+    /// Strings matching patterns, made up as they are read.
+    Patterns(Patterns),
+    /// Strings matching a regex or grammar, made up as they are read.
+    Matches(Matches),
+}
+
+pub(crate) fn trim_newline(s: &mut String) {
+    if s.ends_with('\n') {
+        s.pop();
+        if s.ends_with('\r') {
+            s.pop();
+        }
+    }
+}
+
+impl Source {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Source::File(BufReader::new(File::open(path)?)))
+    }
+
+    /// Next base word without its line terminator, `None` at the end or on
+    /// a read error.
+    pub fn next_line(&mut self) -> Option<String> {
+        match self {
+            Source::File(reader) => {
+                let mut line = String::new();
+                match reader.read_line(&mut line) {
+                    Ok(0) | Err(_) => None,
+                    Ok(_) => {
+                        trim_newline(&mut line);
+                        Some(line)
+                    }
+                }
+            }
+            Source::Memory { words, next } => {
+                let word = words.get(*next).cloned();
+                *next += 1;
+                word
+            }
+            Source::Phrases(phrases) => phrases.next(),
+            Source::Pins(pins) => pins.next(),
+            Source::Phones(phones) => phones.next(),
+            Source::Patterns(patterns) => patterns.next(),
+            Source::Matches(matches) => matches.next(),
+        }
+    }
+
+    /// Like [`Source::next_line`], but a file's last line is only read
+    /// once it ends in a newline, keeping what there is of it in
+    /// `partial` until then.
+    fn next_complete_line(&mut self, partial: &mut String) -> Option<String> {
+        match self {
+            Source::File(reader) => match reader.read_line(partial) {
+                Ok(_) if partial.ends_with('\n') => {
+                    let mut line = std::mem::take(partial);
+                    trim_newline(&mut line);
+                    Some(line)
+                }
+                _ => None,
+            },
+            source => source.next_line(),
+        }
+    }
+
+    /// Start over from the first word.
+    pub fn rewind(&mut self, path: &Path) -> io::Result<()> {
+        match self {
+            Source::File(reader) => *reader = BufReader::new(File::open(path)?),
+            Source::Memory { next, .. } => *next = 0,
+            Source::Phrases(phrases) => phrases.rewind(),
+            Source::Pins(pins) => pins.rewind(),
+            Source::Phones(phones) => phones.rewind(),
+            Source::Patterns(patterns) => patterns.rewind(),
+            Source::Matches(matches) => matches.rewind(),
+        }
+        Ok(())
+    }
+}
+
+/// All configured wordlists, read one after another or round-robin.
+pub(crate) struct Inputs {
+    sources: Vec<(PathBuf, Source)>,
+    current: usize,
+    done: Vec<bool>,
+    /// How lines of files are read; preloaded words are used as they are.
+    format: InputFormat,
+    /// Lines read so far from each input.
+    read: Vec<usize>,
+    /// Whether the last input is kept open past its end, and the start of
+    /// a line written to it but not yet finished.
+    follow: bool,
+    partial: String,
+}
+
+impl Inputs {
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let mut inputs = Self {
+            sources: vec![],
+            current: 0,
+            done: vec![],
+            format: InputFormat::Lines,
+            read: vec![],
+            follow: false,
+            partial: String::new(),
+        };
+        inputs.push(path)?;
+        Ok(inputs)
+    }
+
+    /// Inputs made of `words` alone, with no file behind them.
+    pub fn from_words(words: Vec<String>) -> Self {
+        Self {
+            sources: vec![(PathBuf::new(), Source::Memory { words, next: 0 })],
+            current: 0,
+            done: vec![false],
+            format: InputFormat::Lines,
+            read: vec![0],
+            follow: false,
+            partial: String::new(),
+        }
+    }
+
+    pub fn push(&mut self, path: &Path) -> io::Result<()> {
+        self.sources.push((path.to_path_buf(), Source::open(path)?));
+        self.done.push(false);
+        self.read.push(0);
+        Ok(())
+    }
+
+    pub fn format(&self) -> &InputFormat {
+        &self.format
+    }
+
+    /// Read files in `format` from now on, starting over from the first
+    /// line.
+    pub fn set_format(&mut self, format: InputFormat) -> io::Result<()> {
+        self.format = format;
+        self.rewind()
+    }
+
+    /// The same inputs, read again from the start.
+    pub fn reopen(&self) -> io::Result<Self> {
+        let sources = self
+            .sources
+            .iter()
+            .map(|(path, source)| {
+                let source = match source {
+                    Source::File(_) => Source::open(path)?,
+                    Source::Memory { words, .. } => Source::Memory {
+                        words: words.clone(),
+                        next: 0,
+                    },
+                    Source::Phrases(phrases) => {
+                        let mut phrases = phrases.clone();
+                        phrases.rewind();
+                        Source::Phrases(phrases)
+                    }
+                    Source::Pins(pins) => {
+                        let mut pins = pins.clone();
+                        pins.rewind();
+                        Source::Pins(pins)
+                    }
+                    Source::Phones(phones) => {
+                        let mut phones = phones.clone();
+                        phones.rewind();
+                        Source::Phones(phones)
+                    }
+                    Source::Patterns(patterns) => {
+                        let mut patterns = patterns.clone();
+                        patterns.rewind();
+                        Source::Patterns(patterns)
+                    }
+                    Source::Matches(matches) => {
+                        let mut matches = matches.clone();
+                        matches.rewind();
+                        Source::Matches(matches)
+                    }
+                };
+                Ok((path.clone(), source))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Self {
+            done: vec![false; sources.len()],
+            read: vec![0; sources.len()],
+            sources,
+            current: 0,
+            format: self.format.clone(),
+            follow: self.follow,
+            partial: String::new(),
+        })
+    }
+
+    /// Size of every input in bytes, newlines included.
+    pub fn byte_len(&self) -> u64 {
+        self.sources
+            .iter()
+            .map(|(path, source)| match source {
+                Source::File(_) => std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+                Source::Memory { words, .. } => words.iter().map(|w| w.len() as u64 + 1).sum(),
+                Source::Phrases(phrases) => phrases.byte_len(),
+                Source::Pins(pins) => pins.byte_len(),
+                Source::Phones(phones) => phones.byte_len(),
+                Source::Patterns(patterns) => patterns.byte_len(),
+                Source::Matches(matches) => matches.byte_len(),
+            })
+            .sum()
+    }
+
+    /// Call `f` with the index of each input and every base word in it,
+    /// without moving the read position.
+    pub fn scan(&self, mut f: impl FnMut(usize, &str)) -> io::Result<()> {
+        for (i, (path, source)) in self.sources.iter().enumerate() {
+            match source {
+                Source::File(_) => {
+                    let lines = BufReader::new(File::open(path)?).lines();
+                    for (index, line) in lines.enumerate() {
+                        let line = line?;
+                        match &self.format {
+                            InputFormat::Lines => f(i, &line),
+                            format => {
+                                if let Some(word) = format.word(&line, index) {
+                                    f(i, &word);
+                                }
+                            }
+                        }
+                    }
+                }
+                Source::Memory { words, .. } => words.iter().for_each(|w| f(i, w)),
+                // the words stand in for the phrases, which may be too many
+                // to go through
+                Source::Phrases(phrases) => phrases.words().iter().for_each(|w| f(i, w)),
+                Source::Pins(pins) => pins.likely().iter().for_each(|w| f(i, w)),
+                Source::Phones(phones) => phones.area_codes().iter().for_each(|w| f(i, w)),
+                Source::Patterns(patterns) => patterns.patterns().iter().for_each(|w| f(i, w)),
+                Source::Matches(matches) => f(i, matches.source()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Read `phrases` in place of every input.
+    pub fn set_phrases(&mut self, phrases: Phrases) {
+        self.set_generated(Source::Phrases(phrases));
+    }
+
+    /// Read `pins` in place of every input.
+    pub fn set_pins(&mut self, pins: Pins) {
+        self.set_generated(Source::Pins(pins));
+    }
+
+    /// Read `phones` in place of every input.
+    pub fn set_phones(&mut self, phones: Phones) {
+        self.set_generated(Source::Phones(phones));
+    }
+
+    /// Read `patterns` in place of every input.
+    pub fn set_patterns(&mut self, patterns: Patterns) {
+        self.set_generated(Source::Patterns(patterns));
+    }
+
+    /// Read `matches` in place of every input.
+    pub fn set_matches(&mut self, matches: Matches) {
+        self.set_generated(Source::Matches(matches));
+    }
+
+    fn set_generated(&mut self, source: Source) {
+        self.sources = vec![(PathBuf::new(), source)];
+        self.current = 0;
+        self.done = vec![false];
+        self.format = InputFormat::Lines;
+        self.read = vec![0];
+    }
+
+    /// Keep reading the last input past its end, so lines appended to it
+    /// later are read too, each once it is complete.
+    pub fn set_follow(&mut self, follow: bool) {
+        self.follow = follow;
+    }
+
+    pub fn sources_mut(&mut self) -> impl Iterator<Item = &mut (PathBuf, Source)> {
+        self.sources.iter_mut()
+    }
+
+    /// Next base word, exhausting each input in turn or, when
+    /// `interleave` is set, taking one word from each input in rotation.
+    pub fn next_line(&mut self, interleave: bool) -> Option<String> {
+        let n = self.sources.len();
+        if !interleave {
+            while self.current < n {
+                if let Some(line) = self.next_word(self.current) {
+                    return Some(line);
+                }
+                if self.follow && self.current + 1 == n {
+                    return None;
+                }
+                self.current += 1;
+            }
+            return None;
+        }
+
+        for _ in 0..n {
+            let i = self.current;
+            self.current = (self.current + 1) % n;
+            if self.done[i] {
+                continue;
+            }
+            match self.next_word(i) {
+                Some(line) => return Some(line),
+                None => self.done[i] = true,
+            }
+        }
+        None
+    }
+
+    /// Next base word of input `i`, skipping lines the format has no word
+    /// in.
+    fn next_word(&mut self, i: usize) -> Option<String> {
+        let last = self.follow && i + 1 == self.sources.len();
+        loop {
+            let source = &mut self.sources[i].1;
+            let line = if last {
+                source.next_complete_line(&mut self.partial)?
+            } else {
+                source.next_line()?
+            };
+            if matches!(
+                source,
+                Source::Memory { .. }
+                    | Source::Phrases(_)
+                    | Source::Pins(_)
+                    | Source::Phones(_)
+                    | Source::Patterns(_)
+                    | Source::Matches(_)
+            ) {
+                return Some(line);
+            }
+            let index = self.read[i];
+            self.read[i] += 1;
+            match &self.format {
+                InputFormat::Lines => return Some(line),
+                format => {
+                    if let Some(word) = format.word(&line, index) {
+                        return Some(word);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reader of the input read from next when not interleaving, if it is
+    /// a file read line by line. It is always at the start of a line.
+    pub fn current_file(&mut self) -> Option<&mut BufReader<File>> {
+        // a partly read line must be finished line by line
+        if self.format != InputFormat::Lines || !self.partial.is_empty() {
+            return None;
+        }
+        match self.sources.get_mut(self.current) {
+            Some((_, Source::File(reader))) => Some(reader),
+            _ => None,
+        }
+    }
+
+    pub fn rewind(&mut self) -> io::Result<()> {
+        for (path, source) in self.sources.iter_mut() {
+            source.rewind(path)?;
+        }
+        self.current = 0;
+        self.done.iter_mut().for_each(|done| *done = false);
+        self.read.iter_mut().for_each(|read| *read = 0);
+        self.partial.clear();
+        Ok(())
+    }
+}
+
+/// Load a `word<TAB>count` list, most frequent first, dropping words seen
+/// fewer than `min_count` times.
+///
+/// Lines without a parseable count are treated as seen once. Words with
+/// equal counts keep their input order.
+pub(crate) fn load_weighted(path: &Path, min_count: u64) -> io::Result<Vec<(String, u64)>> {
+    let reader = BufReader::new(File::open(path)?);
+    weigh(reader.lines(), min_count)
+}
+
+/// [`load_weighted`] over lines already read.
+pub(crate) fn weigh(
+    lines: impl Iterator<Item = io::Result<String>>,
+    min_count: u64,
+) -> io::Result<Vec<(String, u64)>> {
+    let mut weighted = vec![];
+    for line in lines {
+        let mut line = line?;
+        trim_newline(&mut line);
+        let (word, count) = match line.rsplit_once('\t') {
+            Some((word, count)) => match count.trim().parse::<u64>() {
+                Ok(count) => (word.to_string(), count),
+                Err(_) => (line.clone(), 1),
+            },
+            None => (line.clone(), 1),
+        };
+        if count >= min_count {
+            weighted.push((word, count));
+        }
+    }
+    weighted.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    Ok(weighted)
+}
@@ -0,0 +1,354 @@
+//! Serializable wordlist configuration, kept apart from iteration state.
+
+use std::{collections::BTreeMap, io, ops::RangeInclusive, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    encode::{EncodeScope, Encoder},
+    format::InputFormat,
+    mutate::{Mutation, SubRule},
+    phone::Region,
+    phrase::Casing,
+    predicate::ApplyRule,
+    Wordlist,
+};
+
+/// Everything that determines a [`Wordlist`]'s output, without any of its
+/// reading position.
+///
+/// A spec can be cloned, stored or sent elsewhere, and turned into any
+/// number of independent iterators with [`WordlistSpec::build`] or
+/// `IntoIterator`:
+///
+/// ```no_run
+/// use wlinflate::spec::WordlistSpec;
+///
+/// let spec = WordlistSpec {
+///     prepend: vec!["dev-".to_string()],
+///     ..WordlistSpec::new(["words.txt"])
+/// };
+/// let first: Vec<String> = (&spec).into_iter().take(10).collect();
+/// let all = spec.build()?.count();
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WordlistSpec {
+    /// Wordlists read one after another (or in rotation with `interleave`).
+    pub wordlists: Vec<PathBuf>,
+    /// See [`Wordlist::set_input_format`].
+    pub input_format: InputFormat,
+    pub prepend: Vec<String>,
+    pub append: Vec<String>,
+    pub swap: Vec<String>,
+    pub extensions: Vec<String>,
+    /// See [`Wordlist::set_swap_token`].
+    pub swap_token: Option<String>,
+    /// Opening and closing token delimiters, see
+    /// [`Wordlist::set_token_delimiters`]. Ignored if `swap_token` is set.
+    pub token_delimiters: Option<(String, String)>,
+    /// See [`Wordlist::set_nest_depth`].
+    pub nest_depth: usize,
+    /// See [`Wordlist::set_random`].
+    pub random_count: usize,
+    pub seed: u64,
+    /// See [`Wordlist::set_var`].
+    pub vars: BTreeMap<String, String>,
+    /// See [`Wordlist::set_templates`].
+    pub templates: Vec<String>,
+    /// See [`Wordlist::set_word2`].
+    pub word2: Vec<String>,
+    /// Read the wordlists as `word<TAB>count` lines, dropping words seen
+    /// fewer times than this (see [`Wordlist::load_weighted`]).
+    pub weighted: Option<u64>,
+    /// Only keep this many of the most frequent weighted words, see
+    /// [`Wordlist::load_weighted_top`].
+    pub top: Option<usize>,
+    /// Words per passphrase, see [`Wordlist::set_passphrases`].
+    pub passphrase_words: Option<RangeInclusive<usize>>,
+    pub passphrase_casings: Vec<Casing>,
+    pub passphrase_joiners: Vec<String>,
+    /// Digits per PIN, replacing the base words, see [`Wordlist::set_pins`].
+    pub pin_lengths: Option<RangeInclusive<usize>>,
+    /// Region of phone numbers replacing the base words, see
+    /// [`Wordlist::set_phones`].
+    pub phone_region: Option<Region>,
+    pub phone_area_codes: Vec<String>,
+    pub phone_formats: Vec<String>,
+    /// Patterns whose strings replace the base words, see
+    /// [`Wordlist::set_patterns`].
+    pub patterns: Vec<String>,
+    /// Regex whose matches replace the base words, see
+    /// [`Wordlist::set_regex`].
+    pub regex: Option<String>,
+    /// Grammar file whose strings replace the base words, see
+    /// [`Wordlist::set_grammar`].
+    pub grammar: Option<PathBuf>,
+    pub apply: Vec<ApplyRule>,
+    /// See [`Wordlist::set_mutations`].
+    pub mutations: Vec<Mutation>,
+    /// See [`Wordlist::set_subs`].
+    pub subs: Vec<SubRule>,
+    /// Masks around every base word, see [`Wordlist::set_hybrid_masks`].
+    pub hybrid_prepend: Option<String>,
+    pub hybrid_append: Option<String>,
+    /// See [`Wordlist::set_wraps`].
+    pub wraps: Vec<(String, String)>,
+    /// See [`Wordlist::set_encoders`].
+    pub encoders: Vec<Encoder>,
+    pub encode_scope: EncodeScope,
+    /// See [`Wordlist::set_metadata_columns`].
+    pub metadata_columns: bool,
+    pub likelihood_order: bool,
+    pub interleave: bool,
+    pub skip_degenerate: bool,
+    /// See [`Wordlist::lengths`].
+    pub lengths: Vec<RangeInclusive<usize>>,
+    /// Keep only the most common lengths of the base words, see
+    /// [`Wordlist::top_length_buckets`]. Ignored if `lengths` is set.
+    pub top_length_buckets: Option<usize>,
+    pub ext_strip: bool,
+    pub directives: bool,
+    pub reverse: bool,
+}
+
+impl Default for WordlistSpec {
+    fn default() -> Self {
+        Self {
+            wordlists: vec![],
+            input_format: InputFormat::Lines,
+            prepend: vec![],
+            append: vec![],
+            swap: vec![],
+            extensions: vec![],
+            swap_token: None,
+            token_delimiters: None,
+            nest_depth: 0,
+            random_count: 1,
+            seed: 0,
+            vars: BTreeMap::new(),
+            templates: vec![],
+            word2: vec![],
+            weighted: None,
+            top: None,
+            passphrase_words: None,
+            passphrase_casings: vec![],
+            passphrase_joiners: vec![],
+            pin_lengths: None,
+            phone_region: None,
+            phone_area_codes: vec![],
+            phone_formats: vec![],
+            patterns: vec![],
+            regex: None,
+            grammar: None,
+            apply: vec![],
+            mutations: vec![],
+            subs: vec![],
+            hybrid_prepend: None,
+            hybrid_append: None,
+            wraps: vec![],
+            encoders: vec![],
+            encode_scope: EncodeScope::Final,
+            metadata_columns: false,
+            likelihood_order: false,
+            interleave: false,
+            skip_degenerate: false,
+            lengths: vec![],
+            top_length_buckets: None,
+            ext_strip: false,
+            directives: false,
+            reverse: false,
+        }
+    }
+}
+
+impl WordlistSpec {
+    /// A spec reading `wordlists` with no transforms configured.
+    pub fn new<P: Into<PathBuf>>(wordlists: impl IntoIterator<Item = P>) -> Self {
+        Self {
+            wordlists: wordlists.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Open the wordlists and return a fresh iterator over the candidates.
+    pub fn build(&self) -> io::Result<Wordlist> {
+        let (first, rest) = self
+            .wordlists
+            .split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no wordlist configured"))?;
+        let mut wl = Wordlist::try_with_values(
+            first,
+            self.prepend.clone(),
+            self.append.clone(),
+            self.swap.clone(),
+            self.extensions.clone(),
+        )?;
+        for path in rest {
+            wl.add_wordlist(path)?;
+        }
+        self.configure(wl)
+    }
+
+    /// Like [`WordlistSpec::build`], but over `words` in memory instead of
+    /// the configured wordlists.
+    pub fn build_from_words(
+        &self,
+        words: impl IntoIterator<Item = String>,
+    ) -> io::Result<Wordlist> {
+        let wl = Wordlist::with_words(
+            words,
+            self.prepend.clone(),
+            self.append.clone(),
+            self.swap.clone(),
+            self.extensions.clone(),
+        );
+        self.configure(wl)
+    }
+
+    /// Apply everything but the inputs and value lists to `wl`.
+    fn configure(&self, mut wl: Wordlist) -> io::Result<Wordlist> {
+        if self.input_format != InputFormat::Lines {
+            wl.set_input_format(self.input_format.clone())?;
+        }
+        if let Some(token) = &self.swap_token {
+            wl.set_swap_token(token)?;
+        } else if let Some((open, close)) = &self.token_delimiters {
+            wl.set_token_delimiters(open, close)?;
+        }
+        wl.set_random(self.random_count, self.seed)?;
+        if !self.templates.is_empty() {
+            wl.set_templates(self.templates.clone())?;
+        }
+        if !self.word2.is_empty() {
+            wl.set_word2(self.word2.clone())?;
+        }
+        for (name, value) in &self.vars {
+            wl.set_var(name, value);
+        }
+        if self.nest_depth > 0 {
+            wl.set_nest_depth(self.nest_depth)?;
+        }
+        wl.apply = self.apply.clone();
+        wl.directives = self.directives;
+        wl.likelihood_order = self.likelihood_order;
+        wl.interleave = self.interleave;
+        wl.reverse = self.reverse;
+        wl.skip_degenerate = self.skip_degenerate;
+        wl.ext_strip = self.ext_strip;
+        if !self.mutations.is_empty() {
+            // counted per line, so after anything changing how lines are read
+            wl.set_mutations(self.mutations.clone())?;
+        }
+        if !self.subs.is_empty() {
+            wl.set_subs(self.subs.clone())?;
+        }
+        wl.set_hybrid_masks(
+            self.hybrid_prepend.as_deref(),
+            self.hybrid_append.as_deref(),
+        )?;
+        wl.set_wraps(self.wraps.clone());
+        wl.set_encoders(self.encoders.clone(), self.encode_scope);
+        if self.metadata_columns {
+            wl.set_metadata_columns(true)?;
+        }
+        if let Some(min_count) = self.weighted {
+            wl.load_weighted_top(min_count, self.top.unwrap_or(usize::MAX))?;
+        }
+        if let Some(words) = &self.passphrase_words {
+            wl.set_passphrases(
+                words.clone(),
+                &self.passphrase_casings,
+                self.passphrase_joiners.clone(),
+            )?;
+        }
+        if let Some(lengths) = &self.pin_lengths {
+            wl.set_pins(lengths.clone())?;
+        }
+        if let Some(region) = self.phone_region {
+            wl.set_phones(region, &self.phone_area_codes, &self.phone_formats)?;
+        }
+        if !self.patterns.is_empty() {
+            wl.set_patterns(&self.patterns)?;
+        }
+        if let Some(regex) = &self.regex {
+            wl.set_regex(regex)?;
+        }
+        if let Some(grammar) = &self.grammar {
+            wl.set_grammar(grammar)?;
+        }
+        wl.lengths = match self.top_length_buckets {
+            Some(n) if self.lengths.is_empty() => wl.top_length_buckets(n)?,
+            _ => self.lengths.clone(),
+        };
+        Ok(wl)
+    }
+}
+
+/// # Panics
+///
+/// If a wordlist cannot be read; use [`WordlistSpec::build`] to handle
+/// that instead.
+impl IntoIterator for &WordlistSpec {
+    type Item = String;
+    type IntoIter = Wordlist;
+
+    fn into_iter(self) -> Wordlist {
+        self.build().expect("failed to open wordlist")
+    }
+}
+
+/// # Panics
+///
+/// If a wordlist cannot be read; use [`WordlistSpec::build`] to handle
+/// that instead.
+impl IntoIterator for WordlistSpec {
+    type Item = String;
+    type IntoIter = Wordlist;
+
+    fn into_iter(self) -> Wordlist {
+        (&self).into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_spec() {
+        let path =
+            &std::env::temp_dir().join(format!("wlinflate_test_spec_{}.txt", std::process::id()));
+        std::fs::write(path, "admin\n{SWAP}-api\n").unwrap();
+        let spec = WordlistSpec {
+            prepend: vec!["old".to_string()],
+            swap: vec!["v1".to_string()],
+            apply: vec!["prepend: if not contains \"-\"".parse().unwrap()],
+            ..WordlistSpec::new([path])
+        };
+
+        let json = serde_json::to_string(&spec).unwrap();
+        assert!(json.contains("\"prepend: if not contains \\\"-\\\"\""));
+        assert!(serde_json::from_str::<WordlistSpec>(&json).unwrap() == spec);
+
+        // independent iterators, one per thread
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let spec = spec.clone();
+                thread::spawn(move || spec.into_iter().collect::<Vec<String>>())
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().unwrap() == ["admin", "oldadmin", "v1-api"]);
+        }
+        std::fs::remove_file(path).unwrap();
+
+        assert!(WordlistSpec::default().build().is_err());
+        assert!(WordlistSpec::new(["/nonexistent/wordlist"])
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Remembering words on disk once they no longer fit in memory.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    hash::Hasher,
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::hash::Fnv64;
+
+/// Slots in a new table; it doubles whenever half of them are taken.
+const INITIAL_SLOTS: u64 = 1 << 16;
+
+/// Bytes a slot takes: the key's hash, then where it starts in the key
+/// file plus one, zero marking an empty slot.
+const SLOT_SIZE: u64 = 16;
+
+/// A temporary file, removed when dropped.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn create() -> io::Result<(Self, File)> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "wlinflate-spill-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok((Self(path), file))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// An open-addressed hash table of slots in a temporary file.
+struct Table {
+    file: File,
+    slots: u64,
+    _temp: TempFile,
+}
+
+impl Table {
+    fn create(slots: u64) -> io::Result<Self> {
+        let (temp, file) = TempFile::create()?;
+        // a sparse file of zeroes, every slot empty
+        file.set_len(slots * SLOT_SIZE)?;
+        Ok(Self {
+            file,
+            slots,
+            _temp: temp,
+        })
+    }
+
+    fn read(&mut self, slot: u64) -> io::Result<(u64, u64)> {
+        self.file.seek(SeekFrom::Start(slot * SLOT_SIZE))?;
+        read_slot(&mut self.file)
+    }
+
+    fn write(&mut self, slot: u64, hash: u64, offset: u64) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(slot * SLOT_SIZE))?;
+        self.file.write_all(&hash.to_le_bytes())?;
+        self.file.write_all(&offset.to_le_bytes())
+    }
+
+    /// Slots from where `hash` belongs on, wrapping around.
+    fn probe(&self, hash: u64) -> impl Iterator<Item = u64> {
+        let slots = self.slots;
+        (0..slots).map(move |i| (hash.wrapping_add(i)) & (slots - 1))
+    }
+}
+
+fn read_slot(reader: &mut impl Read) -> io::Result<(u64, u64)> {
+    let mut hash = [0u8; 8];
+    let mut offset = [0u8; 8];
+    reader.read_exact(&mut hash)?;
+    reader.read_exact(&mut offset)?;
+    Ok((u64::from_le_bytes(hash), u64::from_le_bytes(offset)))
+}
+
+/// A set of keys kept in temporary files rather than memory.
+///
+/// Keys are appended to one file, each prefixed with its length so any
+/// bytes, newlines included, come back as they went in. A hash table in
+/// another file points at them.
+pub(crate) struct DiskSet {
+    table: Table,
+    keys: File,
+    keys_len: u64,
+    len: u64,
+    _keys_temp: TempFile,
+}
+
+impl DiskSet {
+    pub fn new() -> io::Result<Self> {
+        let (temp, keys) = TempFile::create()?;
+        Ok(Self {
+            table: Table::create(INITIAL_SLOTS)?,
+            keys,
+            keys_len: 0,
+            len: 0,
+            _keys_temp: temp,
+        })
+    }
+
+    /// Add `key`, returning `false` if it was already there.
+    pub fn insert(&mut self, key: &str) -> io::Result<bool> {
+        let mut hasher = Fnv64::default();
+        hasher.write(key.as_bytes());
+        let hash = hasher.finish();
+        for slot in self.table.probe(hash) {
+            let (found, offset) = self.table.read(slot)?;
+            if offset == 0 {
+                let offset = self.append(key)?;
+                self.table.write(slot, hash, offset)?;
+                self.len += 1;
+                if self.len * 2 >= self.table.slots {
+                    self.grow()?;
+                }
+                return Ok(true);
+            }
+            if found == hash && self.key_at(offset)? == key.as_bytes() {
+                return Ok(false);
+            }
+        }
+        unreachable!("the table is never full")
+    }
+
+    /// Append `key` to the key file, returning where it starts plus one.
+    fn append(&mut self, key: &str) -> io::Result<u64> {
+        let offset = self.keys_len;
+        self.keys.seek(SeekFrom::Start(offset))?;
+        self.keys.write_all(&(key.len() as u64).to_le_bytes())?;
+        self.keys.write_all(key.as_bytes())?;
+        self.keys_len += 8 + key.len() as u64;
+        Ok(offset + 1)
+    }
+
+    fn key_at(&mut self, offset: u64) -> io::Result<Vec<u8>> {
+        let mut len = [0u8; 8];
+        self.keys.seek(SeekFrom::Start(offset - 1))?;
+        self.keys.read_exact(&mut len)?;
+        let mut key = vec![0; u64::from_le_bytes(len) as usize];
+        self.keys.read_exact(&mut key)?;
+        Ok(key)
+    }
+
+    /// Move every slot into a table twice the size.
+    fn grow(&mut self) -> io::Result<()> {
+        let mut table = Table::create(self.table.slots * 2)?;
+        self.table.file.seek(SeekFrom::Start(0))?;
+        let mut old = BufReader::new(&self.table.file);
+        for _ in 0..self.table.slots {
+            let (hash, offset) = read_slot(&mut old)?;
+            if offset == 0 {
+                continue;
+            }
+            for slot in table.probe(hash) {
+                if table.read(slot)?.1 == 0 {
+                    table.write(slot, hash, offset)?;
+                    break;
+                }
+            }
+        }
+        self.table = table;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_set() {
+        let mut set = DiskSet::new().unwrap();
+        let keys: Vec<String> = (0..INITIAL_SLOTS)
+            .map(|i| format!("word\n{}\t", i))
+            .collect();
+        for key in &keys {
+            assert!(set.insert(key).unwrap());
+        }
+        // the table has grown past its first size along the way
+        assert!(set.table.slots > INITIAL_SLOTS);
+        for key in &keys {
+            assert!(!set.insert(key).unwrap());
+        }
+        assert!(!set.insert("word\n0\t").unwrap());
+        assert!(set.insert("word\n0").unwrap());
+        assert!(set.insert("").unwrap() && !set.insert("").unwrap());
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap};
+
+use log::info;
+use serde::Serialize;
+use wlinflate::{Origin, Wordlist};
+
+use crate::human;
+
+/// Number of candidates a single transform value contributed to.
+#[derive(Debug, Clone, Serialize)]
+pub struct TransformHits {
+    pub transform: &'static str,
+    pub value: String,
+    pub candidates: usize,
+}
+
+/// Per-transform hit counters, one slot per configured value.
+pub struct Stats {
+    swap: Vec<usize>,
+    prepend: Vec<usize>,
+    append: Vec<usize>,
+    extension: Vec<usize>,
+}
+
+impl Stats {
+    pub fn new(wl: &Wordlist) -> Self {
+        Self {
+            swap: vec![0; wl.swap.len()],
+            prepend: vec![0; wl.prepend.len()],
+            append: vec![0; wl.append.len()],
+            extension: vec![0; wl.extensions.len()],
+        }
+    }
+
+    pub fn record(&mut self, origin: &Origin) {
+        let slots = [
+            (&mut self.swap, origin.swap),
+            (&mut self.prepend, origin.prepend),
+            (&mut self.append, origin.append),
+            (&mut self.extension, origin.extension),
+        ];
+        for (counts, idx) in slots {
+            if let Some(i) = idx {
+                counts[i] += 1;
+            }
+        }
+    }
+
+    /// Flatten the counters into labelled rows, in configuration order.
+    pub fn hits(&self, wl: &Wordlist) -> Vec<TransformHits> {
+        let groups = [
+            ("swap", &self.swap, &wl.swap),
+            ("prepend", &self.prepend, &wl.prepend),
+            ("append", &self.append, &wl.append),
+            ("extension", &self.extension, &wl.extensions),
+        ];
+        groups
+            .iter()
+            .flat_map(|(transform, counts, values)| {
+                counts
+                    .iter()
+                    .zip(values.iter())
+                    .map(move |(candidates, value)| TransformHits {
+                        transform,
+                        value: value.clone(),
+                        candidates: *candidates,
+                    })
+            })
+            .collect()
+    }
+
+    pub fn log(hits: &[TransformHits]) {
+        for hit in hits {
+            info!(
+                "{} {:?}: {}",
+                hit.transform,
+                hit.value,
+                human::count(hit.candidates as u128)
+            );
+        }
+    }
+}
+
+/// Hashcat-style masks of the character classes, with `?h` for anything
+/// outside printable ASCII.
+const CLASS_MASKS: [&str; 5] = ["?l", "?u", "?d", "?s", "?h"];
+const CLASS_NAMES: [&str; 5] = ["lower", "upper", "digit", "special", "other"];
+
+fn class(c: char) -> usize {
+    match c {
+        'a'..='z' => 0,
+        'A'..='Z' => 1,
+        '0'..='9' => 2,
+        ' '..='~' => 3,
+        _ => 4,
+    }
+}
+
+/// What the written candidates cover: the character classes each uses,
+/// their lengths, and the classes of their first and last two characters.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    /// Candidates by the set of classes they use, one bit per class.
+    classes: [usize; 1 << CLASS_MASKS.len()],
+    /// Candidates by length in characters.
+    lengths: Vec<usize>,
+    prefixes: HashMap<Vec<u8>, usize>,
+    suffixes: HashMap<Vec<u8>, usize>,
+    /// Scratch for the classes of the current word.
+    scratch: Vec<u8>,
+}
+
+/// [`Coverage`] as written to the JSON report.
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageReport {
+    /// e.g. `"lower+digit": 1200`
+    pub classes: BTreeMap<String, usize>,
+    pub lengths: BTreeMap<usize, usize>,
+    /// Masks of the first and last two characters, e.g. `"?d?d": 800`.
+    pub prefixes: BTreeMap<String, usize>,
+    pub suffixes: BTreeMap<String, usize>,
+}
+
+impl Coverage {
+    pub fn record(&mut self, word: &str) {
+        self.scratch.clear();
+        self.scratch.extend(word.chars().map(|c| class(c) as u8));
+        let used = self.scratch.iter().fold(0, |used, &c| used | 1 << c);
+        self.classes[used] += 1;
+        let len = self.scratch.len();
+        if self.lengths.len() <= len {
+            self.lengths.resize(len + 1, 0);
+        }
+        self.lengths[len] += 1;
+        let ends = [
+            (&mut self.prefixes, &self.scratch[..len.min(2)]),
+            (&mut self.suffixes, &self.scratch[len - len.min(2)..]),
+        ];
+        for (counts, classes) in ends {
+            match counts.get_mut(classes) {
+                Some(count) => *count += 1,
+                None => {
+                    counts.insert(classes.to_vec(), 1);
+                }
+            }
+        }
+    }
+
+    pub fn report(&self) -> CoverageReport {
+        let mask = |classes: &[u8]| -> String {
+            classes.iter().map(|&c| CLASS_MASKS[c as usize]).collect()
+        };
+        let masks = |counts: &HashMap<Vec<u8>, usize>| {
+            counts
+                .iter()
+                .map(|(classes, count)| (mask(classes), *count))
+                .collect()
+        };
+        CoverageReport {
+            classes: self
+                .classes
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(used, count)| {
+                    let names: Vec<&str> = (0..CLASS_NAMES.len())
+                        .filter(|c| used & 1 << c != 0)
+                        .map(|c| CLASS_NAMES[c])
+                        .collect();
+                    (names.join("+"), *count)
+                })
+                .collect(),
+            lengths: self
+                .lengths
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(len, count)| (len, *count))
+                .collect(),
+            prefixes: masks(&self.prefixes),
+            suffixes: masks(&self.suffixes),
+        }
+    }
+
+    pub fn log(report: &CoverageReport) {
+        for (classes, count) in &report.classes {
+            let classes = if classes.is_empty() {
+                "(empty)"
+            } else {
+                classes
+            };
+            info!("Classes {}: {}", classes, human::count(*count as u128));
+        }
+        let lengths: Vec<_> = report.lengths.keys().collect();
+        if let (Some(min), Some(max)) = (lengths.first(), lengths.last()) {
+            info!("Lengths {}-{}", min, max);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage() {
+        let mut coverage = Coverage::default();
+        for word in ["admin", "Admin1!", "admin12", "x", ""] {
+            coverage.record(word);
+        }
+        let report = coverage.report();
+        assert!(report.classes["lower"] == 2);
+        assert!(report.classes["lower+upper+digit+special"] == 1);
+        assert!(report.classes["lower+digit"] == 1);
+        assert!(report.classes[""] == 1);
+        assert!(report.lengths[&7] == 2);
+        assert!(report.prefixes["?l?l"] == 2);
+        assert!(report.prefixes["?u?l"] == 1);
+        assert!(report.suffixes["?d?d"] == 1);
+        assert!(report.suffixes["?d?s"] == 1);
+        assert!(report.suffixes["?l"] == 1);
+    }
+}
@@ -0,0 +1,53 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    time::Instant,
+};
+
+use serde::Serialize;
+
+use crate::stats::{CoverageReport, TransformHits};
+
+/// Machine-readable end-of-run report.
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub input_words: usize,
+    pub keyspace: u128,
+    /// The seed random choices were made with, to repeat the run.
+    pub seed: Option<u64>,
+    pub emitted: usize,
+    pub filtered: usize,
+    pub duplicates_removed: usize,
+    pub wall_time_secs: f64,
+    pub words_per_sec: f64,
+    pub cached: bool,
+    pub interrupted: bool,
+    pub transforms: Vec<TransformHits>,
+    /// Hash of the output, when it was checked with `--verify`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<CoverageReport>,
+}
+
+impl Summary {
+    /// Fill in the timing fields relative to `started`.
+    pub fn finish(&mut self, started: Instant) {
+        self.wall_time_secs = started.elapsed().as_secs_f64();
+        if self.wall_time_secs > 0.0 {
+            self.words_per_sec = self.emitted as f64 / self.wall_time_secs;
+        }
+    }
+
+    /// Write the report to `dest`, or to stderr when no path is given.
+    pub fn write(&self, dest: Option<PathBuf>) -> io::Result<()> {
+        let mut out: Box<dyn Write> = match dest {
+            Some(path) => Box::new(File::create(path)?),
+            None => Box::new(io::stderr()),
+        };
+        serde_json::to_writer_pretty(&mut out, self)?;
+        out.write_all(b"\n")?;
+        out.flush()
+    }
+}
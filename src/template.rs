@@ -0,0 +1,551 @@
+use std::{collections::HashMap, hash::Hasher};
+
+use crate::{hash::Fnv64, rng::SplitMix64};
+
+/// Markers recognised inside base words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Syntax {
+    /// Replaced by each swap value.
+    pub swap: String,
+    /// Replaced by each swap value, and also removed.
+    pub optional: String,
+    /// Replaced by the base word in template lines.
+    pub word: String,
+    pub word1: String,
+    /// Replaced by each word of the second list in template lines.
+    pub word2: String,
+    /// Delimiters around named tokens such as `{N:1-10}`.
+    pub open: String,
+    pub close: String,
+}
+
+impl Syntax {
+    /// `SWAP` and `SWAP?` between the given delimiters.
+    pub fn delimited(open: &str, close: &str) -> Self {
+        Self {
+            swap: format!("{}SWAP{}", open, close),
+            optional: format!("{}SWAP?{}", open, close),
+            word: format!("{}WORD{}", open, close),
+            word1: format!("{}WORD1{}", open, close),
+            word2: format!("{}WORD2{}", open, close),
+            open: open.to_string(),
+            close: close.to_string(),
+        }
+    }
+
+    /// A bare marker such as `FUZZ`, with `FUZZ?` as its optional form.
+    /// Named tokens keep their braces.
+    pub fn bare(token: &str) -> Self {
+        Self {
+            swap: token.to_string(),
+            optional: format!("{}?", token),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for Syntax {
+    fn default() -> Self {
+        Self::delimited("{", "}")
+    }
+}
+
+/// Numeric sequence from a `{N:start-end:step:pad}` token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Counter {
+    start: u64,
+    step: u64,
+    len: usize,
+    descending: bool,
+    width: usize,
+}
+
+impl Counter {
+    /// Parse the `start-end[:step[:pad]]` part of a counter token. The range
+    /// is inclusive and counts down when `end` is below `start`; `pad` is a
+    /// minimum width, filled with zeros.
+    fn parse(spec: &str) -> Option<Self> {
+        let mut fields = spec.split(':');
+        let (start, end) = fields.next()?.split_once('-')?;
+        let (start, end): (u64, u64) = (start.parse().ok()?, end.parse().ok()?);
+        let step: u64 = match fields.next() {
+            Some(step) => step.parse().ok().filter(|&step| step > 0)?,
+            None => 1,
+        };
+        let width: usize = match fields.next() {
+            Some(pad) => pad.parse().ok()?,
+            None => 0,
+        };
+        if fields.next().is_some() {
+            return None;
+        }
+        let len = usize::try_from(start.abs_diff(end) / step)
+            .ok()?
+            .checked_add(1)?;
+        Some(Self {
+            start,
+            step,
+            len,
+            descending: end < start,
+            width,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn nth(&self, i: usize) -> String {
+        let offset = i as u64 * self.step;
+        let n = if self.descending {
+            self.start - offset
+        } else {
+            self.start + offset
+        };
+        format!("{:0width$}", n, width = self.width)
+    }
+}
+
+/// Random strings from a `{RAND:len:charset}` token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Random {
+    len: usize,
+    charset: &'static [u8],
+}
+
+impl Random {
+    /// Parse the `len[:charset]` part of a random token; the charset is one
+    /// of `alnum` (the default), `alpha`, `lower`, `upper`, `digit` or
+    /// `hex`.
+    fn parse(spec: &str) -> Option<Self> {
+        let (len, charset) = match spec.split_once(':') {
+            Some((len, charset)) => (len, charset),
+            None => (spec, "alnum"),
+        };
+        let charset: &'static [u8] = match charset {
+            "alnum" => b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789",
+            "alpha" => b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            "lower" => b"abcdefghijklmnopqrstuvwxyz",
+            "upper" => b"ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            "digit" => b"0123456789",
+            "hex" => b"0123456789abcdef",
+            _ => return None,
+        };
+        Some(Self {
+            len: len.parse().ok()?,
+            charset,
+        })
+    }
+
+    /// The `k`th string for token number `slot` of the line `key`. It only
+    /// depends on its arguments, so re-reading a line gives the same values.
+    fn nth(&self, seed: u64, key: &str, slot: usize, k: usize) -> String {
+        let mut hasher = Fnv64::default();
+        hasher.write(&seed.to_le_bytes());
+        hasher.write(key.as_bytes());
+        hasher.write(&(slot as u64).to_le_bytes());
+        hasher.write(&(k as u64).to_le_bytes());
+        let mut rng = SplitMix64::new(hasher.finish());
+        (0..self.len)
+            .map(|_| self.charset[rng.below(self.charset.len())] as char)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Segment<'a> {
+    Literal(&'a str),
+    Swap {
+        optional: bool,
+    },
+    Counter(Counter),
+    Random(Random),
+    /// `{d}`, `{l}`, `{u}`, `{s}` or a named charset such as `{german}`:
+    /// one character from the class.
+    Class(&'static str),
+    /// `{ENV:name}`, kept as `raw` text if the variable is not set.
+    Var {
+        name: &'a str,
+        raw: &'a str,
+    },
+}
+
+/// What tokens are filled with.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Bindings {
+    /// Swap values, each with the index of the configured value it came
+    /// from.
+    pub swap: Vec<(usize, String)>,
+    /// Strings produced by each random token.
+    pub random_count: usize,
+    pub seed: u64,
+    /// Values for `{ENV:name}` tokens, ahead of the environment.
+    pub vars: HashMap<String, String>,
+}
+
+impl Bindings {
+    fn var(&self, name: &str) -> Option<String> {
+        match self.vars.get(name) {
+            Some(value) => Some(value.clone()),
+            None => std::env::var(name).ok(),
+        }
+    }
+}
+
+/// The token starting `rest`, if any, and its length.
+fn token_at<'a>(rest: &'a str, syntax: &Syntax) -> Option<(Segment<'a>, usize)> {
+    // the optional form is tried first since a bare `FUZZ?` starts with
+    // `FUZZ`
+    for (marker, optional) in [(&syntax.optional, true), (&syntax.swap, false)] {
+        if !marker.is_empty() && rest.starts_with(marker.as_str()) {
+            return Some((Segment::Swap { optional }, marker.len()));
+        }
+    }
+    if syntax.open.is_empty() || !rest.starts_with(syntax.open.as_str()) {
+        return None;
+    }
+    let inner = &rest[syntax.open.len()..];
+    let end = inner.find(syntax.close.as_str())?;
+    let len = syntax.open.len() + end + syntax.close.len();
+    let (name, spec) = match inner[..end].split_once(':') {
+        Some((name, spec)) => (name, spec),
+        None => return class(&inner[..end]).map(|chars| (Segment::Class(chars), len)),
+    };
+    let segment = match name {
+        "N" | "NUM" => Segment::Counter(Counter::parse(spec)?),
+        "RAND" => Segment::Random(Random::parse(spec)?),
+        "ENV" if !spec.is_empty() => Segment::Var {
+            name: spec,
+            raw: &rest[..len],
+        },
+        _ => return None,
+    };
+    Some((segment, len))
+}
+
+/// Locale charsets usable as class tokens and in masks, longest names
+/// first so none is taken for the start of another.
+pub(crate) const NAMED_CLASSES: &[&str] = &[
+    "cyrillic-upper",
+    "german-upper",
+    "latin1-upper",
+    "cyrillic",
+    "german",
+    "latin1",
+];
+
+/// Characters of a mask-style class token, named as in hashcat masks, or
+/// one of [`NAMED_CLASSES`]: the full lower or upper case alphabet of
+/// German, Russian Cyrillic, or ISO 8859-1 letters.
+pub(crate) fn class(name: &str) -> Option<&'static str> {
+    match name {
+        "d" => Some("0123456789"),
+        "l" => Some("abcdefghijklmnopqrstuvwxyz"),
+        "u" => Some("ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
+        "s" => Some(" !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~"),
+        "german" => Some("abcdefghijklmnopqrstuvwxyzäöüß"),
+        "german-upper" => Some("ABCDEFGHIJKLMNOPQRSTUVWXYZÄÖÜ"),
+        "cyrillic" => Some("абвгдеёжзийклмнопрстуфхцчшщъыьэюя"),
+        "cyrillic-upper" => Some("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ"),
+        "latin1" => Some("abcdefghijklmnopqrstuvwxyzßàáâãäåæçèéêëìíîïðñòóôõöøùúûüýþÿ"),
+        "latin1-upper" => Some("ABCDEFGHIJKLMNOPQRSTUVWXYZÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔÕÖØÙÚÛÜÝÞ"),
+        _ => None,
+    }
+}
+
+/// Split a base word into literal text and tokens. Malformed tokens are
+/// kept as literal text.
+pub(crate) fn parse<'a>(word: &'a str, syntax: &Syntax) -> Vec<Segment<'a>> {
+    let mut segments = vec![];
+    let mut literal = 0;
+    let mut i = 0;
+    while i < word.len() {
+        match token_at(&word[i..], syntax) {
+            Some((segment, len)) => {
+                if literal < i {
+                    segments.push(Segment::Literal(&word[literal..i]));
+                }
+                segments.push(segment);
+                i += len;
+                literal = i;
+            }
+            None => i += word[i..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    if literal < word.len() {
+        segments.push(Segment::Literal(&word[literal..]));
+    }
+    segments
+}
+
+/// The first token in `word` of a known kind (`N`, `NUM`, `RAND` or `ENV`)
+/// whose arguments don't parse, which [`parse`] keeps as literal text.
+pub(crate) fn malformed<'a>(word: &'a str, syntax: &Syntax) -> Option<&'a str> {
+    if syntax.open.is_empty() {
+        return None;
+    }
+    let mut from = 0;
+    while let Some(start) = word[from..].find(syntax.open.as_str()) {
+        let rest = &word[from + start..];
+        let inner = &rest[syntax.open.len()..];
+        let end = inner.find(syntax.close.as_str())?;
+        if let Some((name, _)) = inner[..end].split_once(':') {
+            if matches!(name, "N" | "NUM" | "RAND" | "ENV") && token_at(rest, syntax).is_none() {
+                return Some(&rest[..syntax.open.len() + end + syntax.close.len()]);
+            }
+        }
+        from += start + syntax.open.len();
+    }
+    None
+}
+
+/// Choices for each token in `segments`, in order. An optional token has
+/// one more choice than there are swap values: leaving it out.
+pub(crate) fn radices(segments: &[Segment], bindings: &Bindings) -> Vec<usize> {
+    segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Literal(_) | Segment::Var { .. } => None,
+            Segment::Swap { optional: false } => Some(bindings.swap.len()),
+            Segment::Swap { optional: true } => Some(bindings.swap.len() + 1),
+            Segment::Counter(counter) => Some(counter.len()),
+            Segment::Random(_) => Some(bindings.random_count),
+            Segment::Class(chars) => Some(chars.chars().count()),
+        })
+        .collect()
+}
+
+/// Whether `segments` holds any token at all.
+pub(crate) fn has_tokens(segments: &[Segment]) -> bool {
+    segments.iter().any(|s| !matches!(s, Segment::Literal(_)))
+}
+
+/// Number of words a parsed base word expands to.
+pub(crate) fn fanout(segments: &[Segment], bindings: &Bindings) -> u128 {
+    radices(segments, bindings)
+        .iter()
+        .fold(1u128, |total, &n| total.saturating_mul(n as u128))
+}
+
+/// Call `f` with every choice of one value per position, position `i`
+/// taking values below `radices[i]` and the last position varying fastest.
+/// Nothing is produced when any position has no values.
+pub(crate) fn each_combination(radices: &[usize], mut f: impl FnMut(&[usize])) {
+    if radices.contains(&0) {
+        return;
+    }
+    let mut choice = vec![0; radices.len()];
+    loop {
+        f(&choice);
+        let mut slot = radices.len();
+        loop {
+            if slot == 0 {
+                return;
+            }
+            slot -= 1;
+            choice[slot] += 1;
+            if choice[slot] < radices[slot] {
+                break;
+            }
+            choice[slot] = 0;
+        }
+    }
+}
+
+/// Call `f` with every word `segments` (parsed from `key`) expands to,
+/// along with the swap value picked for the first swap token (`None` if it
+/// was left out or there is none).
+pub(crate) fn render(
+    key: &str,
+    segments: &[Segment],
+    bindings: &Bindings,
+    mut f: impl FnMut(String, Option<usize>),
+) {
+    let radices = radices(segments, bindings);
+    each_combination(&radices, |choice| {
+        let mut word = String::new();
+        let mut choices = choice.iter();
+        let mut first = None;
+        for (slot, segment) in segments.iter().enumerate() {
+            let option = match segment {
+                Segment::Literal(text) => {
+                    word.push_str(text);
+                    continue;
+                }
+                Segment::Counter(counter) => {
+                    word.push_str(&counter.nth(*choices.next().unwrap()));
+                    continue;
+                }
+                Segment::Random(random) => {
+                    let k = *choices.next().unwrap();
+                    word.push_str(&random.nth(bindings.seed, key, slot, k));
+                    continue;
+                }
+                Segment::Class(chars) => {
+                    word.extend(chars.chars().nth(*choices.next().unwrap()));
+                    continue;
+                }
+                Segment::Var { name, raw } => {
+                    match bindings.var(name) {
+                        Some(value) => word.push_str(&value),
+                        None => word.push_str(raw),
+                    }
+                    continue;
+                }
+                // an optional token's first choice leaves it out
+                Segment::Swap { optional: true } => choices.next().unwrap().checked_sub(1),
+                Segment::Swap { optional: false } => choices.next().copied(),
+            };
+            if let Some(i) = option {
+                word.push_str(&bindings.swap[i].1);
+            }
+            first = first.or(Some(option.map(|i| bindings.swap[i].0)));
+        }
+        f(word, first.flatten());
+    });
+}
+
+/// Bind `values` as the swap values, with tokens inside them expanded up
+/// to `depth` levels deep. Tokens still left at the last level are kept
+/// literally.
+pub(crate) fn bind_values(
+    bindings: &mut Bindings,
+    values: &[String],
+    syntax: &Syntax,
+    depth: usize,
+) {
+    bindings.swap = values.iter().cloned().enumerate().collect();
+    for _ in 0..depth {
+        let mut next = vec![];
+        for (i, value) in values.iter().enumerate() {
+            let segments = parse(value, syntax);
+            if has_tokens(&segments) {
+                render(value, &segments, bindings, |word, _| next.push((i, word)));
+            } else {
+                next.push((i, value.clone()));
+            }
+        }
+        if next == bindings.swap {
+            break;
+        }
+        bindings.swap = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_malformed() {
+        let syntax = Syntax::default();
+        assert!(malformed("a{N:1-3}b{RAND:8:hex}{SWAP}{ENV:HOME}", &syntax).is_none());
+        assert!(malformed("json {\"a\": 1} {x:y}", &syntax).is_none());
+        assert!(malformed("a{N:1-3}{RAND:x}", &syntax) == Some("{RAND:x}"));
+        assert!(malformed("{N:9-}", &syntax) == Some("{N:9-}"));
+        assert!(malformed("{ENV:}", &Syntax::delimited("%%", "%%")).is_none());
+        assert!(malformed("%%N:a%%", &Syntax::delimited("%%", "%%")) == Some("%%N:a%%"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let syntax = Syntax::bare("FUZZ");
+        let segments = parse("a-FUZZ?/FUZZ", &syntax);
+        assert!(
+            segments
+                == vec![
+                    Segment::Literal("a-"),
+                    Segment::Swap { optional: true },
+                    Segment::Literal("/"),
+                    Segment::Swap { optional: false },
+                ]
+        );
+        let bindings = Bindings {
+            swap: vec![(0, "x".to_string()), (1, "y".to_string())],
+            ..Default::default()
+        };
+        assert!(fanout(&segments, &bindings) == 6);
+        assert!(fanout(&parse("plain", &Syntax::default()), &bindings) == 1);
+    }
+
+    #[test]
+    fn test_counter() {
+        let syntax = Syntax::default();
+        let words = |line| {
+            let mut words = vec![];
+            let bindings = Bindings::default();
+            render(line, &parse(line, &syntax), &bindings, |word, _| {
+                words.push(word)
+            });
+            words
+        };
+        assert!(words("p{N:1-3}") == vec!["p1", "p2", "p3"]);
+        assert!(words("{N:0-10:5:03}") == vec!["000", "005", "010"]);
+        assert!(words("{N:3-1}") == vec!["3", "2", "1"]);
+        assert!(words("{N:1-3:0}") == vec!["{N:1-3:0}"]);
+        assert!(fanout(&parse("{N:0-1000:5:04}", &syntax), &Bindings::default()) == 201);
+    }
+
+    #[test]
+    fn test_class() {
+        let syntax = Syntax::default();
+        let bindings = Bindings::default();
+        let mut words = vec![];
+        let segments = parse("pass{d}{d}", &syntax);
+        render("pass{d}{d}", &segments, &bindings, |word, _| {
+            words.push(word)
+        });
+        assert!(words.len() == 100);
+        assert!(words[0] == "pass00" && words[99] == "pass99");
+        assert!(fanout(&parse("{l}{u}{s}", &syntax), &bindings) == 26 * 26 * 33);
+        assert!(!has_tokens(&parse("{x}", &syntax)));
+
+        let mut words = vec![];
+        let segments = parse("{cyrillic-upper}{german}", &syntax);
+        assert!(fanout(&segments, &bindings) == 33 * 30);
+        render("", &segments, &bindings, |word, _| words.push(word));
+        assert!(words[29] == "Аß" && words.last().unwrap() == "Яß");
+    }
+
+    #[test]
+    fn test_random() {
+        let syntax = Syntax::default();
+        let bindings = Bindings {
+            random_count: 3,
+            seed: 7,
+            ..Default::default()
+        };
+        let words = |line| {
+            let mut words = vec![];
+            render(line, &parse(line, &syntax), &bindings, |word, _| {
+                words.push(word)
+            });
+            words
+        };
+        let first = words("x{RAND:6:hex}");
+        assert!(first.len() == 3);
+        assert!(first
+            .iter()
+            .all(|w| w.len() == 7 && w[1..].chars().all(|c| c.is_ascii_hexdigit())));
+        assert!(words("x{RAND:6:hex}") == first);
+        assert!(words("{RAND:6:nope}") == vec!["{RAND:6:nope}"]);
+    }
+
+    #[test]
+    fn test_expand_values() {
+        let values = vec!["a".to_string(), "b{SWAP}".to_string()];
+        let syntax = Syntax::default();
+        let flat = |depth| {
+            let mut bindings = Bindings::default();
+            bind_values(&mut bindings, &values, &syntax, depth);
+            bindings
+                .swap
+                .into_iter()
+                .map(|(_, word)| word)
+                .collect::<Vec<String>>()
+        };
+        assert!(flat(0) == vec!["a", "b{SWAP}"]);
+        assert!(flat(1) == vec!["a", "ba", "bb{SWAP}"]);
+        assert!(flat(2) == vec!["a", "ba", "bba", "bbb{SWAP}"]);
+    }
+}
@@ -0,0 +1,57 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+};
+
+use log::{debug, info};
+use structopt::StructOpt;
+use wlinflate::markov::MarkovModel;
+
+use crate::{error::Error, human};
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct TrainArgs {
+    #[structopt(
+        parse(from_os_str),
+        required = true,
+        help = "password corpora to train on, one word per line"
+    )]
+    corpora: Vec<PathBuf>,
+    #[structopt(
+        short = "o",
+        long = "output",
+        parse(from_os_str),
+        help = "file to write the model to (e.g. model.wlm)"
+    )]
+    output: PathBuf,
+    #[structopt(
+        long = "order",
+        default_value = "3",
+        help = "characters per n-gram; higher follows the corpus more closely"
+    )]
+    order: usize,
+}
+
+/// Count the n-grams of every corpus into a model file.
+pub fn run(train: &TrainArgs) -> Result<(), Error> {
+    let mut model =
+        MarkovModel::new(train.order).map_err(|e| Error::InvalidConfig(e.to_string()))?;
+    for path in &train.corpora {
+        if !path.is_file() {
+            return Err(Error::InputNotFound(path.clone()));
+        }
+        let file = File::open(path).map_err(|e| Error::Input(path.clone(), e))?;
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| Error::Input(path.clone(), e))?;
+            let word = line.strip_suffix('\r').unwrap_or(&line);
+            if !word.is_empty() {
+                model.add(word);
+            }
+        }
+    }
+    model.write(&train.output)?;
+    info!("Trained On: {}", human::count(model.words() as u128));
+    debug!("Model Contexts: {}", model.transitions.len());
+    Ok(())
+}
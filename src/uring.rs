@@ -0,0 +1,201 @@
+//! Output file writes through io_uring, keeping several large blocks in
+//! flight instead of blocking on each one.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    os::fd::AsRawFd,
+};
+
+use io_uring::{opcode, types, IoUring};
+
+/// Blocks that may be in flight at once.
+const DEPTH: usize = 8;
+
+/// A block handed to the kernel, and how much of it has been written.
+struct Pending {
+    buf: Vec<u8>,
+    offset: u64,
+    done: usize,
+}
+
+/// Writes to a file from the start in blocks of `block_size` bytes.
+///
+/// Blocks are queued on a ring and reaped as they complete, so filling
+/// the next block overlaps with writing the previous ones. Dropping the
+/// writer waits for every queued block, but only [`Write::flush`] reports
+/// errors.
+pub struct UringWriter {
+    file: File,
+    ring: IoUring,
+    block_size: usize,
+    current: Vec<u8>,
+    /// One slot per block that may be in flight.
+    slots: Vec<Option<Pending>>,
+    /// Emptied buffers to fill next.
+    spare: Vec<Vec<u8>>,
+    offset: u64,
+}
+
+impl UringWriter {
+    /// Set up a ring writing to a duplicate of `file`'s descriptor. Fails
+    /// where io_uring is unavailable, e.g. on older kernels or when it is
+    /// blocked by a sandbox.
+    pub fn new(file: &File, block_size: usize) -> io::Result<Self> {
+        let ring = IoUring::new(DEPTH as u32)?;
+        let block_size = block_size.max(1);
+        Ok(Self {
+            file: file.try_clone()?,
+            ring,
+            block_size,
+            current: Vec::with_capacity(block_size),
+            slots: (0..DEPTH).map(|_| None).collect(),
+            spare: vec![],
+            offset: 0,
+        })
+    }
+
+    fn in_flight(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Queue the part of the block in `slot` not yet written.
+    fn push(&mut self, slot: usize) -> io::Result<()> {
+        let pending = self.slots[slot].as_ref().expect("slot holds a block");
+        let rest = &pending.buf[pending.done..];
+        let entry = opcode::Write::new(
+            types::Fd(self.file.as_raw_fd()),
+            rest.as_ptr(),
+            rest.len() as u32,
+        )
+        .offset(pending.offset + pending.done as u64)
+        .build()
+        .user_data(slot as u64);
+        // the buffer lives in `slots` until its completion is reaped
+        unsafe { self.ring.submission().push(&entry) }
+            .map_err(|_| io::Error::other("io_uring submission queue is full"))?;
+        self.ring.submit()?;
+        Ok(())
+    }
+
+    /// Wait for at least one queued block to complete, returning each
+    /// completed slot and its result.
+    fn wait(&mut self) -> io::Result<Vec<(usize, i32)>> {
+        self.ring.submit_and_wait(1)?;
+        Ok(self
+            .ring
+            .completion()
+            .map(|cqe| (cqe.user_data() as usize, cqe.result()))
+            .collect())
+    }
+
+    /// Wait for at least one queued block, and handle every completion.
+    fn reap(&mut self) -> io::Result<()> {
+        let mut failed = None;
+        for (slot, result) in self.wait()? {
+            let pending = self.slots[slot].as_mut().expect("slot holds a block");
+            match result {
+                result if result < 0 => failed = Some(io::Error::from_raw_os_error(-result)),
+                0 => failed = Some(io::ErrorKind::WriteZero.into()),
+                result => pending.done += result as usize,
+            }
+            if failed.is_none() && pending.done < pending.buf.len() {
+                self.push(slot)?;
+            } else if let Some(Pending { mut buf, .. }) = self.slots[slot].take() {
+                buf.clear();
+                self.spare.push(buf);
+            }
+        }
+        failed.map_or(Ok(()), Err)
+    }
+
+    /// Queue the current block and start a fresh one.
+    fn submit_current(&mut self) -> io::Result<()> {
+        if self.current.is_empty() {
+            return Ok(());
+        }
+        while self.in_flight() == DEPTH {
+            self.reap()?;
+        }
+        let next = self
+            .spare
+            .pop()
+            .unwrap_or_else(|| Vec::with_capacity(self.block_size));
+        let buf = std::mem::replace(&mut self.current, next);
+        let slot = self
+            .slots
+            .iter()
+            .position(Option::is_none)
+            .expect("a slot is free");
+        let offset = self.offset;
+        self.offset += buf.len() as u64;
+        self.slots[slot] = Some(Pending {
+            buf,
+            offset,
+            done: 0,
+        });
+        self.push(slot)
+    }
+}
+
+impl Write for UringWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let room = self.block_size - self.current.len();
+        let n = data.len().min(room);
+        self.current.extend_from_slice(&data[..n]);
+        if self.current.len() >= self.block_size {
+            self.submit_current()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.submit_current()?;
+        while self.in_flight() > 0 {
+            self.reap()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for UringWriter {
+    fn drop(&mut self) {
+        // the kernel may still be reading queued buffers
+        while self.in_flight() > 0 {
+            match self.wait() {
+                Ok(completed) => {
+                    for (slot, _) in completed {
+                        self.slots[slot] = None;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uring_writer() {
+        let path =
+            &std::env::temp_dir().join(format!("wlinflate_test_uring_{}.txt", std::process::id()));
+        let file = File::create(path).unwrap();
+        let mut writer = match UringWriter::new(&file, 10) {
+            Ok(writer) => writer,
+            // not permitted in every environment
+            Err(_) => return std::fs::remove_file(path).unwrap(),
+        };
+        let mut expected = vec![];
+        for i in 0..1000 {
+            let line = format!("word{}\n", i);
+            writer.write_all(line.as_bytes()).unwrap();
+            expected.extend_from_slice(line.as_bytes());
+        }
+        writer.flush().unwrap();
+        assert!(std::fs::read(path).unwrap() == expected);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+//! Checking with `--verify` that a run wrote exactly what it should.
+
+use std::{
+    hash::Hasher,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use log::info;
+use wlinflate::hash::Fnv64;
+
+use crate::{error::Error, human};
+
+/// A hash of everything written through it, shared between its clones so
+/// one can be read while another is owned by the writer.
+#[derive(Clone, Default)]
+pub struct Digest(Arc<Mutex<Fnv64>>);
+
+impl Digest {
+    pub fn hex(&self) -> String {
+        format!("{:016x}", self.0.lock().unwrap().finish())
+    }
+}
+
+impl Write for Digest {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Check that `generated` candidates are the `expected` the keyspace
+/// says, and that the output hashes to `hash`, if given.
+pub fn check(
+    expected: u128,
+    generated: usize,
+    digest: &Digest,
+    hash: Option<&str>,
+) -> Result<(), Error> {
+    if generated as u128 != expected {
+        return Err(Error::Verify(format!(
+            "generated {} words, but the keyspace holds {}",
+            generated, expected
+        )));
+    }
+    let found = digest.hex();
+    if let Some(hash) = hash {
+        if !hash.trim().eq_ignore_ascii_case(&found) {
+            return Err(Error::Verify(format!(
+                "output hash is {}, expected {}",
+                found, hash
+            )));
+        }
+    }
+    info!("Verified {}, output hash {}", human::count(expected), found);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structopt::StructOpt;
+
+    #[test]
+    fn test_check() {
+        let digest = Digest::default();
+        let mut writer = digest.clone();
+        writer.write_all(b"a").unwrap();
+        assert!(digest.hex() == "af63dc4c8601ec8c");
+        assert!(check(3, 3, &digest, None).is_ok());
+        assert!(check(3, 3, &digest, Some("AF63DC4C8601EC8C")).is_ok());
+        assert!(check(3, 2, &digest, None).is_err());
+        assert!(check(3, 3, &digest, Some("0000000000000000")).is_err());
+    }
+
+    #[test]
+    fn test_verify_filtered() {
+        let dir =
+            std::env::temp_dir().join(format!("wlinflate_test_verify_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let words = dir.join("words.txt");
+        std::fs::write(&words, "admin\nlogin.php\nx\nadmin #!noext\n").unwrap();
+        let out = dir.join("out.txt");
+        for filter in [
+            &["--skip-degenerate"][..],
+            &["--keep-lengths", "5-6"],
+            &["--apply", "ext: if not contains \".\""],
+            &["--directives"],
+        ] {
+            let mut command = vec!["wlinflate", "-w", words.to_str().unwrap()];
+            command.extend(["-p", "admin,x", "-x", ".php", "--verify", "--force"]);
+            command.extend(["-o", out.to_str().unwrap()]);
+            command.extend(filter);
+            let args = crate::Args::from_iter(command);
+            assert!(crate::run(&args).is_ok(), "{:?}", filter);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,57 @@
+//! WebAssembly bindings, built with the `wasm` feature:
+//!
+//! ```text
+//! wasm-pack build --target web --features wasm
+//! ```
+//!
+//! Base words are passed in directly since there is no filesystem; see
+//! `js/wlinflate.js` for a wrapper taking any iterable of lines.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{spec::WordlistSpec, Wordlist};
+
+/// Inflated wordlist over lines supplied by the caller.
+#[wasm_bindgen(js_name = Wordlist)]
+pub struct WasmWordlist {
+    wordlist: Wordlist,
+}
+
+#[wasm_bindgen(js_class = Wordlist)]
+impl WasmWordlist {
+    /// Inflate `lines` as configured by `spec_json`, a JSON
+    /// [`WordlistSpec`] whose `wordlists` field is ignored.
+    #[wasm_bindgen(constructor)]
+    pub fn new(lines: Vec<String>, spec_json: &str) -> Result<WasmWordlist, JsError> {
+        let spec: WordlistSpec = serde_json::from_str(spec_json)?;
+        Ok(Self {
+            wordlist: spec.build_from_words(lines)?,
+        })
+    }
+
+    /// The next candidate, or `undefined` at the end.
+    pub fn next(&mut self) -> Option<String> {
+        self.wordlist.next()
+    }
+
+    /// Up to `size` further candidates, empty at the end.
+    #[wasm_bindgen(js_name = nextBatch)]
+    pub fn next_batch(&mut self, size: usize) -> Vec<String> {
+        self.wordlist.by_ref().take(size).collect()
+    }
+
+    /// Number of candidates, saturating at 2^64 - 1.
+    pub fn keyspace(&self) -> u64 {
+        u64::try_from(self.wordlist.keyspace()).unwrap_or(u64::MAX)
+    }
+
+    /// Index of the next candidate.
+    pub fn position(&self) -> usize {
+        self.wordlist.position()
+    }
+
+    /// The candidate at `index`, or `undefined` past the end.
+    pub fn nth(&self, index: usize) -> Option<String> {
+        self.wordlist.nth_candidate(index)
+    }
+}
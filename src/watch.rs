@@ -0,0 +1,165 @@
+//! Re-running the configured inflation whenever one of its inputs changes.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::Ordering,
+        mpsc::{self, Receiver, RecvTimeoutError},
+    },
+    time::Duration,
+};
+
+use log::{error, info, warn};
+use notify::{event::ModifyKind, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{error::Error, recipe, Args, INTERRUPTED};
+
+/// How long changes must stop for before a run starts, so an editor
+/// saving a file in several steps only triggers one.
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// How often a wait checks for Ctrl-C.
+const POLL: Duration = Duration::from_millis(200);
+
+/// `path` with `version` before its extension, as `out.3.txt`.
+fn versioned(path: &Path, version: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, version, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, version),
+    };
+    path.with_file_name(name)
+}
+
+/// The first version of `path` not written yet.
+fn next_version(path: &Path) -> u32 {
+    let mut version = 1;
+    while versioned(path, version).exists() {
+        version += 1;
+    }
+    version
+}
+
+/// Files a run of `args` depends on, as absolute paths: its inputs, the
+/// recipe and the rules file.
+fn watched(args: &Args) -> Result<Vec<PathBuf>, Error> {
+    let mut paths: Vec<PathBuf> = args.inputs().into_iter().map(|(_, path)| path).collect();
+    paths.extend(recipe::file(args)?);
+    paths.extend(args.rules.clone());
+    Ok(paths
+        .iter()
+        .filter_map(|path| {
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            Some(dir.canonicalize().ok()?.join(path.file_name()?))
+        })
+        .collect())
+}
+
+fn watch_error(e: notify::Error) -> Error {
+    Error::InvalidConfig(format!("failed to watch inputs: {}", e))
+}
+
+/// Inflate as `args` say into a new version of the output file each time
+/// an input changes, re-reading `command` (the full command line) first
+/// so changes to a recipe take effect. Runs until interrupted.
+pub fn run(command: &[OsString], mut args: Args) -> Result<(), Error> {
+    let outfile = args
+        .outfile
+        .clone()
+        .ok_or_else(|| Error::InvalidConfig("--watch needs an output file (-o)".to_string()))?;
+    let (sender, events) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    })
+    .map_err(watch_error)?;
+    let mut dirs: Vec<PathBuf> = vec![];
+    loop {
+        // editors often replace files rather than write them, so the
+        // directories holding them are watched
+        let paths = watched(&args)?;
+        for dir in paths.iter().filter_map(|path| path.parent()) {
+            if !dirs.iter().any(|d| d == dir) {
+                watcher
+                    .watch(dir, RecursiveMode::NonRecursive)
+                    .map_err(watch_error)?;
+                dirs.push(dir.to_path_buf());
+            }
+        }
+
+        let output = versioned(&outfile, next_version(&outfile));
+        let mut pass = args.clone();
+        pass.outfile = Some(output.clone());
+        match crate::run(&pass) {
+            Ok(()) => info!("Wrote {}", output.display()),
+            Err(Error::Interrupted) => return Err(Error::Interrupted),
+            Err(e) => error!("{}", e),
+        }
+
+        info!("Waiting for changes to {} files", paths.len());
+        loop {
+            wait(&events, &paths)?;
+            match Args::parse(command) {
+                Ok(mut next) => {
+                    // unseeded runs keep the seed of the first
+                    if next.seed.is_none() {
+                        next.seed = args.seed;
+                        next.seed_picked = args.seed_picked;
+                    }
+                    args = next;
+                    break;
+                }
+                Err(e) => error!("{}", e),
+            }
+        }
+    }
+}
+
+/// Block until one of `paths` changes and changes stop for [`SETTLE`].
+fn wait(events: &Receiver<notify::Result<Event>>, paths: &[PathBuf]) -> Result<(), Error> {
+    let mut changed = false;
+    loop {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return Err(Error::Interrupted);
+        }
+        match events.recv_timeout(if changed { SETTLE } else { POLL }) {
+            Ok(Ok(event)) => {
+                let modified =
+                    matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    ) && !matches!(event.kind, EventKind::Modify(ModifyKind::Metadata(_)));
+                if modified && event.paths.iter().any(|p| paths.contains(p)) {
+                    changed = true;
+                }
+            }
+            Ok(Err(e)) => warn!("Watching inputs failed: {}", e),
+            Err(RecvTimeoutError::Timeout) if changed => return Ok(()),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(Error::InvalidConfig("stopped watching inputs".to_string()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versioned() {
+        assert!(versioned(Path::new("out/words.txt"), 3) == Path::new("out/words.3.txt"));
+        assert!(versioned(Path::new("words"), 1) == Path::new("words.1"));
+        let dir = std::env::temp_dir().join(format!("wlinflate_test_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.txt");
+        std::fs::write(dir.join("out.1.txt"), "").unwrap();
+        std::fs::write(dir.join("out.2.txt"), "").unwrap();
+        assert!(next_version(&path) == 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
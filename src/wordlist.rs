@@ -1,51 +1,373 @@
+pub mod encode;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod filter;
+pub mod format;
+pub mod grammar;
+pub mod hash;
+pub mod keyboard;
+pub mod markov;
+pub mod matches;
+pub mod mutate;
+pub mod parallel;
+pub mod pattern;
+pub mod phone;
+pub mod phrase;
+pub mod pin;
+pub mod pot;
+pub mod predicate;
+pub mod presets;
+#[cfg(feature = "python")]
+mod python;
+mod rng;
+pub mod shared;
+mod source;
+pub mod spec;
+mod spill;
+mod template;
+#[cfg(feature = "wasm")]
+mod wasm;
+
 use std::{
-    collections::VecDeque,
-    fs::File,
-    io::{self, BufRead, BufReader},
-    path::PathBuf,
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    hash::Hasher,
+    io::{self, BufRead, BufReader, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
 };
 
+use encode::{EncodeScope, Encoder};
+use format::InputFormat;
+use hash::Fnv64;
+use matches::Matches;
+use mutate::{Mutation, SubRule};
+use pattern::Patterns;
+use phone::{Phones, Region};
+use phrase::{Casing, Phrases};
+use pin::Pins;
+use predicate::{Affix, ApplyRule};
+#[cfg(test)]
+use source::trim_newline;
+use source::{Inputs, Source};
+use template::{Bindings, Syntax};
+
+/// Why [`Wordlist::try_new`] couldn't build a wordlist.
+#[derive(Debug)]
+pub enum WlinflateError {
+    /// The wordlist file doesn't exist.
+    MissingFile(PathBuf),
+    /// The wordlist file exists but may not be read.
+    PermissionDenied(PathBuf),
+    /// Reading the wordlist file failed otherwise.
+    Io(PathBuf, io::Error),
+    /// A prepend, append or extension value is empty, e.g. from `a,,b`.
+    EmptyValue(&'static str),
+    /// A token of a known kind with arguments that don't parse, such as
+    /// `{RAND:x}`, on the given 1-based line of the wordlist.
+    InvalidToken { line: usize, token: String },
+}
+
+impl WlinflateError {
+    /// Classify a failure to read the wordlist at `path`.
+    fn reading(path: &Path, e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => WlinflateError::MissingFile(path.to_path_buf()),
+            io::ErrorKind::PermissionDenied => WlinflateError::PermissionDenied(path.to_path_buf()),
+            _ => WlinflateError::Io(path.to_path_buf(), e),
+        }
+    }
+}
+
+impl fmt::Display for WlinflateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WlinflateError::MissingFile(path) => {
+                write!(f, "wordlist not found: {}", path.display())
+            }
+            WlinflateError::PermissionDenied(path) => {
+                write!(f, "permission denied reading {}", path.display())
+            }
+            WlinflateError::Io(path, e) => write!(f, "failed to read {}: {}", path.display(), e),
+            WlinflateError::EmptyValue(option) => write!(f, "empty {} value", option),
+            WlinflateError::InvalidToken { line, token } => {
+                write!(f, "invalid token '{}' on line {}", token, line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WlinflateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WlinflateError::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<WlinflateError> for io::Error {
+    fn from(e: WlinflateError) -> Self {
+        let kind = match &e {
+            WlinflateError::MissingFile(_) => io::ErrorKind::NotFound,
+            WlinflateError::PermissionDenied(_) => io::ErrorKind::PermissionDenied,
+            WlinflateError::Io(_, e) => e.kind(),
+            WlinflateError::EmptyValue(_) | WlinflateError::InvalidToken { .. } => {
+                io::ErrorKind::InvalidInput
+            }
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+/// Which transform values were applied to produce a candidate, as indexes
+/// into the matching `Wordlist` value lists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Origin {
+    /// Swap value at the first swap token on the line.
+    pub swap: Option<usize>,
+    pub prepend: Option<usize>,
+    pub append: Option<usize>,
+    pub extension: Option<usize>,
+}
+
+impl Origin {
+    /// Number of affixes (prepend, append, extension) applied.
+    pub fn mutations(&self) -> usize {
+        [self.prepend, self.append, self.extension]
+            .iter()
+            .filter(|idx| idx.is_some())
+            .count()
+    }
+}
+
+/// Streaming wordlist inflator.
+///
+/// # Ordering
+///
+/// Candidates are numbered from 0 in the order they are emitted, and a given
+/// set of inputs and options always yields the same sequence:
+///
+/// 1. Base words are read from the wordlists in the order they were added
+///    (or round-robin with [`Wordlist::interleave`]); weighted input is
+///    ordered by descending count, ties in input order.
+/// 2. With [`Wordlist::set_templates`], each base word is put into every
+///    template line in turn (and, for lines with `{WORD2}`, paired with
+///    each word from [`Wordlist::set_word2`]), and the results stand in for
+///    the word below.
+/// 3. Tokens in a base word are expanded, each standing for a list of
+///    values:
+///    - the swap token (`{SWAP}` unless changed with
+///      [`Wordlist::set_swap_token`]): each swap value in the order given;
+///      the optional `{SWAP?}` is first left out, then filled with each
+///      value;
+///    - a counter (`{N:1-10:1:02}`): each number in the range;
+///    - a random token (`{RAND:8:alnum}`): each random string, fixed by the
+///      seed and the line (see [`Wordlist::set_random`]);
+///    - a character class (`{d}`, `{l}`, `{u}`, `{s}`): each character;
+///    - `{ENV:name}`: the value from [`Wordlist::set_var`] or the
+///      environment, kept as-is when neither has it.
+///
+///    With several tokens on a line, every combination is produced, the
+///    last token varying fastest. With [`Wordlist::set_nest_depth`], a swap
+///    value holding tokens stands for each of its own expansions, in the
+///    same order. Other base words are used as-is.
+/// 4. With [`Wordlist::set_mutations`], each word is followed by its
+///    variants under every mutation in the order the mutations were given.
+/// 5. With [`Wordlist::set_subs`], each word is replaced by the forms the
+///    substitution rules rewrite it into.
+/// 6. With [`Wordlist::set_hybrid_masks`], each word is replaced by every
+///    string of the prepend mask before it combined with every string of
+///    the append mask after it, the append mask varying fastest.
+/// 7. With [`Wordlist::set_wraps`], each word is replaced by every
+///    wrapped form of it in the order given.
+/// 8. Each affix stage (prepends, then appends, then extensions) keeps
+///    every word produced so far and then adds, for each of those words in
+///    order, one variant per affix value in the order given.
+/// 9. With [`Wordlist::likelihood_order`], the whole input is walked once
+///    per number of applied affixes (0, 1, 2, 3) and only candidates with
+///    that many affixes are emitted on each pass, otherwise in the order
+///    above.
+///
+/// With [`Wordlist::reverse`] the same sequence is emitted last candidate
+/// first, and indexes count from that end.
+///
+/// Candidates dropped by [`Wordlist::skip_degenerate`] or
+/// [`Wordlist::lengths`], or held back by [`Wordlist::apply`] rules or
+/// [`Wordlist::directives`], do not take up an index.
+///
+/// [`Wordlist::position`] reports the index of the next candidate and
+/// [`Wordlist::seek_to`] moves forward to a given index.
+///
+/// This order only changes in a major release, together with
+/// [`ORDERING_VERSION`], so indexes saved by one version (checkpoints,
+/// distributed plans) stay valid in the next.
+/// [`Wordlist::ordering_fingerprint`] identifies the sequence a wordlist
+/// will produce, to check that two of them agree.
 pub struct Wordlist {
     pub path: PathBuf,
     pub base_count: usize,
+    /// Number of candidates, as [`Wordlist::keyspace`] but saturating at
+    /// `usize::MAX`.
     pub total_count: usize,
-    reader: BufReader<File>,
+    reader: Inputs,
     pub prepend: Vec<String>,
     pub append: Vec<String>,
     pub swap: Vec<String>,
     pub extensions: Vec<String>,
-    word_perms: VecDeque<String>,
+    /// Emit all base words first, then single affixes, then combinations,
+    /// instead of clustering every permutation of a word together.
+    pub likelihood_order: bool,
+    /// Take words from each wordlist in rotation rather than one after
+    /// another when several are configured.
+    pub interleave: bool,
+    /// Skip candidates where a prepend or append repeats the base word, or
+    /// where two applied affixes are the same string.
+    pub skip_degenerate: bool,
+    /// Only emit candidates whose length in characters falls in one of
+    /// these ranges; all lengths if empty. See
+    /// [`Wordlist::top_length_buckets`].
+    pub lengths: Vec<RangeInclusive<usize>>,
+    /// Replace an existing extension on the word instead of appending a
+    /// second one (`index.html` becomes `index.bak`, not `index.html.bak`).
+    pub ext_strip: bool,
+    /// Only apply an affix stage to base words passing every rule for it.
+    pub apply: Vec<ApplyRule>,
+    /// Honour `#!noext`-style directives at the end of wordlist lines (see
+    /// [`predicate::split_directives`]).
+    pub directives: bool,
+    /// Emit the sequence backwards, from the last candidate to the first.
+    /// Every base word is held in memory once iteration starts.
+    pub reverse: bool,
+    /// Base words in forward order while reversed, and how many of them
+    /// are left to read this pass.
+    reversed: Option<(Vec<String>, usize)>,
+    syntax: Syntax,
+    nest_depth: usize,
+    bindings: Bindings,
+    templates: Vec<String>,
+    word2: Vec<String>,
+    mutations: Vec<Mutation>,
+    subs: Vec<SubRule>,
+    /// Strings put before and after every base word, each pair once.
+    hybrid: Option<(Vec<String>, Vec<String>)>,
+    /// Text put before and after every base word, one pair at a time.
+    wraps: Vec<(String, String)>,
+    encoders: Vec<Encoder>,
+    encode_scope: EncodeScope,
+    /// Read lines as `word<TAB>columns`, see
+    /// [`Wordlist::set_metadata_columns`].
+    metadata_columns: bool,
+    /// Columns of the line being expanded, copied after each candidate.
+    metadata: String,
+    /// Base words across all lines, variants and rewritten forms included,
+    /// while mutations or substitutions are configured.
+    mutated_bases: u128,
+    /// Words a single base word becomes across all templates.
+    template_fanout: u128,
+    /// Base words holding at least one token, and the words they expand to.
+    token_lines: usize,
+    token_bases: u128,
+    keyspace: u128,
+    /// Hash of every input line, taken while rescanning.
+    input_hash: u64,
+    /// Words the current base line expanded to, before affixes.
+    bases: Vec<String>,
+    /// Queued candidates, as an index into `bases` and the affixes to add.
+    word_perms: VecDeque<(usize, Origin)>,
+    /// Buffer candidates are formatted into by [`Wordlist::write_next`].
+    scratch: String,
+    pass: usize,
+    position: usize,
 }
 
-fn count_lines<R: io::Read>(handle: R) -> usize {
+/// Revision of the candidate order described on [`Wordlist`], bumped
+/// whenever a change makes any configuration yield a different sequence.
+pub const ORDERING_VERSION: u32 = 1;
+
+fn count_lines<R: io::Read>(handle: R) -> io::Result<usize> {
     let mut reader = BufReader::new(handle);
     let mut count = 0;
     let mut line: Vec<u8> = Vec::new();
-    while match reader.read_until(b'\n', &mut line) {
-        Ok(n) if n > 0 => true,
-        Err(e) => {
-            eprintln!("[!] Failed to read from wordlist: {}", e);
-            std::process::exit(-1);
-        }
-        _ => false,
-    } {
+    while reader.read_until(b'\n', &mut line)? > 0 {
         if *line.last().unwrap() == b'\n' {
             count += 1;
         };
     }
-    count
+    Ok(count)
 }
 
-fn trim_newline(s: &mut String) {
-    if s.ends_with('\n') {
-        s.pop();
-        if s.ends_with('\r') {
-            s.pop();
+/// Split a delimited list of values, honouring backslash escapes.
+///
+/// `\` followed by any character (including the delimiter or another
+/// backslash) yields that character literally, so `1\,2,3` splits on `,`
+/// into `1,2` and `3`.
+pub fn split_values(s: &str, delimiter: &str) -> Vec<String> {
+    let mut values = vec![];
+    let mut current = String::new();
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        if c == '\\' {
+            rest = &rest[1..];
+            match rest.chars().next() {
+                Some(escaped) => {
+                    current.push(escaped);
+                    rest = &rest[escaped.len_utf8()..];
+                }
+                None => current.push('\\'),
+            }
+        } else if !delimiter.is_empty() && rest.starts_with(delimiter) {
+            values.push(std::mem::take(&mut current));
+            rest = &rest[delimiter.len()..];
+        } else {
+            current.push(c);
+            rest = &rest[c.len_utf8()..];
         }
     }
+    values.push(current);
+    values
+}
+
+/// Parse length ranges such as `6-12`, `8`, `10-` or `6-8,12`, inclusive
+/// and in characters.
+pub fn parse_lengths(s: &str) -> Result<Vec<RangeInclusive<usize>>, String> {
+    let invalid = || format!("invalid length range '{}', expected e.g. '6-12'", s);
+    s.split(',')
+        .map(|range| {
+            let range = range.trim();
+            let (min, max) = range.split_once('-').unwrap_or((range, range));
+            let min = min.parse().map_err(|_| invalid())?;
+            let max = match max {
+                "" => usize::MAX,
+                max => max.parse().map_err(|_| invalid())?,
+            };
+            if min > max {
+                return Err(invalid());
+            }
+            Ok(min..=max)
+        })
+        .collect()
+}
+
+/// `word` without its file extension, if the last path component has one.
+///
+/// Dotfiles such as `.htaccess` are left alone.
+fn strip_extension(word: &str) -> &str {
+    let name_start = word.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match word[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => &word[..name_start + dot],
+        _ => word,
+    }
 }
 
 impl Wordlist {
+    /// Build a wordlist from comma separated value lists (see
+    /// [`split_values`] for escaping).
+    ///
+    /// # Panics
+    ///
+    /// If the wordlist can't be read; [`Wordlist::try_new`] reports that
+    /// instead.
     pub fn new(
         path: &PathBuf,
         prepend: Option<String>,
@@ -53,140 +375,2460 @@ impl Wordlist {
         swap: Option<String>,
         extensions: Option<String>,
     ) -> Self {
-        let pre_strs = match prepend {
-            Some(s) => s.split(",").map(|s| s.to_string()).collect::<Vec<String>>(),
-            None => vec![],
-        };
-        let pre_len = pre_strs.len();
-        let app_strs = match append {
-            Some(s) => s.split(",").map(|s| s.to_string()).collect::<Vec<String>>(),
-            None => vec![],
-        };
-        let app_len = app_strs.len();
-        let ext_strs = match extensions {
-            Some(s) => s.split(",").map(|s| s.to_string()).collect::<Vec<String>>(),
+        let split = |values: Option<String>| match values {
+            Some(s) => split_values(&s, ","),
             None => vec![],
         };
-        let ext_len = ext_strs.len();
-        let swap_strs = match swap {
-            Some(s) => s.split(",").map(|s| s.to_string()).collect::<Vec<String>>(),
+        Self::with_values(
+            path,
+            split(prepend),
+            split(append),
+            split(swap),
+            split(extensions),
+        )
+    }
+
+    /// Like [`Wordlist::new`], but checking the configuration rather than
+    /// panicking: the wordlist must be readable, no prepend, append or
+    /// extension value may be empty, and no line may hold a token such as
+    /// `{RAND:x}` whose arguments don't parse, which `new` would quietly
+    /// keep as text.
+    pub fn try_new(
+        path: &PathBuf,
+        prepend: Option<String>,
+        append: Option<String>,
+        swap: Option<String>,
+        extensions: Option<String>,
+    ) -> Result<Self, WlinflateError> {
+        let split = |values: Option<String>| match values {
+            Some(s) => split_values(&s, ","),
             None => vec![],
         };
-        let word_count = count_lines(std::fs::File::open(&path).unwrap());
-        Self {
-            path: path.clone(),
+        let (prepend, append, extensions) = (split(prepend), split(append), split(extensions));
+        for (option, values) in [
+            ("prepend", &prepend),
+            ("append", &append),
+            ("extension", &extensions),
+        ] {
+            if values.iter().any(String::is_empty) {
+                return Err(WlinflateError::EmptyValue(option));
+            }
+        }
+        let wl = Self::try_with_values(path, prepend, append, split(swap), extensions)?;
+        let mut invalid = None;
+        let mut line = 0;
+        wl.reader
+            .scan(|_, word| {
+                line += 1;
+                if invalid.is_none() {
+                    invalid = template::malformed(word, &wl.syntax).map(|token| {
+                        WlinflateError::InvalidToken {
+                            line,
+                            token: token.to_string(),
+                        }
+                    });
+                }
+            })
+            .map_err(|e| WlinflateError::reading(path, e))?;
+        match invalid {
+            Some(e) => Err(e),
+            None => Ok(wl),
+        }
+    }
+
+    /// Build a wordlist from already separated value lists.
+    ///
+    /// # Panics
+    ///
+    /// If the wordlist can't be read; [`Wordlist::try_with_values`]
+    /// reports that instead.
+    pub fn with_values(
+        path: &PathBuf,
+        pre_strs: Vec<String>,
+        app_strs: Vec<String>,
+        swap_strs: Vec<String>,
+        ext_strs: Vec<String>,
+    ) -> Self {
+        Self::try_with_values(path, pre_strs, app_strs, swap_strs, ext_strs)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like [`Wordlist::with_values`], but returning an error for a
+    /// wordlist that can't be read.
+    pub fn try_with_values(
+        path: &PathBuf,
+        pre_strs: Vec<String>,
+        app_strs: Vec<String>,
+        swap_strs: Vec<String>,
+        ext_strs: Vec<String>,
+    ) -> Result<Self, WlinflateError> {
+        let reading = |e| WlinflateError::reading(path, e);
+        let word_count =
+            count_lines(std::fs::File::open(path).map_err(reading)?).map_err(reading)?;
+        let reader = Inputs::new(path).map_err(reading)?;
+        Self::from_inputs(
+            path, word_count, reader, pre_strs, app_strs, swap_strs, ext_strs,
+        )
+        .map_err(reading)
+    }
+
+    /// Build a wordlist over `words` held in memory rather than read from
+    /// a file, e.g. where there is no filesystem.
+    pub fn with_words(
+        words: impl IntoIterator<Item = String>,
+        pre_strs: Vec<String>,
+        app_strs: Vec<String>,
+        swap_strs: Vec<String>,
+        ext_strs: Vec<String>,
+    ) -> Self {
+        let words: Vec<String> = words.into_iter().collect();
+        let word_count = words.len();
+        let reader = Inputs::from_words(words);
+        Self::from_inputs(
+            &PathBuf::new(),
+            word_count,
+            reader,
+            pre_strs,
+            app_strs,
+            swap_strs,
+            ext_strs,
+        )
+        .expect("words in memory are always readable")
+    }
+
+    fn from_inputs(
+        path: &Path,
+        word_count: usize,
+        reader: Inputs,
+        pre_strs: Vec<String>,
+        app_strs: Vec<String>,
+        swap_strs: Vec<String>,
+        ext_strs: Vec<String>,
+    ) -> io::Result<Self> {
+        let mut wl = Self {
+            path: path.to_path_buf(),
             base_count: word_count,
-            reader: BufReader::new(File::open(path).unwrap()),
+            reader,
             prepend: pre_strs,
             append: app_strs,
             swap: swap_strs,
             extensions: ext_strs,
-            total_count: word_count
-                + (word_count * pre_len)
-                + (word_count * app_len)
-                + (word_count * ext_len),
+            total_count: 0,
+            likelihood_order: false,
+            interleave: false,
+            skip_degenerate: false,
+            lengths: vec![],
+            ext_strip: false,
+            apply: vec![],
+            directives: false,
+            reverse: false,
+            reversed: None,
+            syntax: Syntax::default(),
+            nest_depth: 0,
+            bindings: Bindings {
+                random_count: 1,
+                ..Default::default()
+            },
+            templates: vec![],
+            word2: vec![],
+            mutations: vec![],
+            subs: vec![],
+            hybrid: None,
+            wraps: vec![],
+            encoders: vec![],
+            encode_scope: EncodeScope::Final,
+            metadata_columns: false,
+            metadata: String::new(),
+            mutated_bases: 0,
+            template_fanout: 1,
+            token_lines: 0,
+            token_bases: 0,
+            keyspace: 0,
+            input_hash: 0,
+            bases: Vec::new(),
             word_perms: VecDeque::new(),
+            scratch: String::new(),
+            pass: 0,
+            position: 0,
+        };
+        wl.rescan()?;
+        Ok(wl)
+    }
+
+    /// Read another wordlist after (or, with `interleave`, alongside) the
+    /// ones already configured.
+    pub fn add_wordlist(&mut self, path: &PathBuf) -> io::Result<()> {
+        self.base_count += count_lines(std::fs::File::open(path)?)?;
+        self.reader.push(path)?;
+        self.rescan()
+    }
+
+    /// Keep reading the last wordlist past its end, as `tail -f` does:
+    /// once the wordlist runs out, iterating again picks up lines appended
+    /// since, a line only once its newline is written. The keyspace and
+    /// base word count only cover what the files held when counted.
+    ///
+    /// Base words must be streamed, so this doesn't combine with
+    /// reversed, interleaved, weighted or likelihood-ordered input.
+    pub fn set_follow(&mut self, follow: bool) {
+        self.reader.set_follow(follow);
+    }
+
+    /// Treat the wordlists as `word<TAB>count` lines and emit words in
+    /// descending frequency, skipping any seen fewer than `min_count` times.
+    ///
+    /// Each list is loaded into memory to sort it.
+    pub fn load_weighted(&mut self, min_count: u64) -> io::Result<()> {
+        self.load_weighted_top(min_count, usize::MAX)
+    }
+
+    /// Like [`Wordlist::load_weighted`], but only keep the `top` most
+    /// frequent words across all the lists, so a large list can be put
+    /// through many rules without the keyspace exploding. Words tied with
+    /// the last one kept are taken from earlier lists first.
+    pub fn load_weighted_top(&mut self, min_count: u64, top: usize) -> io::Result<()> {
+        let mut lists = vec![];
+        for (path, source) in self.reader.sources_mut() {
+            lists.push(match source {
+                Source::File(_) => source::load_weighted(path, min_count)?,
+                Source::Memory { words, .. } => source::weigh(words.drain(..).map(Ok), min_count)?,
+                Source::Phrases(_)
+                | Source::Pins(_)
+                | Source::Phones(_)
+                | Source::Patterns(_)
+                | Source::Matches(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "generated words can't be read as weighted input",
+                    ))
+                }
+            });
+        }
+        let total: usize = lists.iter().map(Vec::len).sum();
+        if top < total {
+            // each list is sorted, so the top words are a prefix of each
+            let mut counts: Vec<(u64, usize)> = lists
+                .iter()
+                .enumerate()
+                .flat_map(|(i, words)| words.iter().map(move |(_, count)| (*count, i)))
+                .collect();
+            counts.sort_by_key(|&(count, i)| (std::cmp::Reverse(count), i));
+            let mut keep = vec![0; lists.len()];
+            for &(_, i) in &counts[..top] {
+                keep[i] += 1;
+            }
+            for (words, keep) in lists.iter_mut().zip(keep) {
+                words.truncate(keep);
+            }
+        }
+        self.base_count = 0;
+        for ((_, source), words) in self.reader.sources_mut().zip(lists) {
+            self.base_count += words.len();
+            let words = words.into_iter().map(|(word, _)| word).collect();
+            *source = Source::Memory { words, next: 0 };
         }
+        self.reader.rewind()?;
+        self.word_perms.clear();
+        self.bases.clear();
+        self.reversed = None;
+        self.pass = 0;
+        self.position = 0;
+        self.rescan()
     }
-}
 
-impl Iterator for Wordlist {
-    type Item = String;
+    /// Replace the base words with every phrase of `lengths` words made
+    /// from them, each word in each of `casings` and the words put
+    /// together with each of `joiners` (see [`Phrases`]), e.g.
+    /// `correct-horse` from `correct` and `horse`. Without joiners the
+    /// words are run together.
+    ///
+    /// The words are loaded into memory and phrases are made up as they
+    /// are read. Tokens in the words are expanded but not counted in
+    /// [`Wordlist::keyspace`].
+    pub fn set_passphrases(
+        &mut self,
+        lengths: RangeInclusive<usize>,
+        casings: &[Casing],
+        joiners: Vec<String>,
+    ) -> io::Result<()> {
+        let mut words = vec![];
+        self.reader
+            .scan(|_, line| words.push(self.split_line(line).0.to_string()))?;
+        let phrases = Phrases::new(words, casings, lengths).with_joiners(joiners);
+        self.base_count = usize::try_from(phrases.total()).unwrap_or(usize::MAX);
+        self.reader.set_phrases(phrases);
+        self.word_perms.clear();
+        self.bases.clear();
+        self.reversed = None;
+        self.pass = 0;
+        self.position = 0;
+        self.rescan()
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.word_perms.is_empty() {
-            let mut base_word = String::new();
-            match self.reader.read_line(&mut base_word) {
-                Ok(n) => {
-                    if n != 0 {
-                        trim_newline(&mut base_word);
-
-                        // handle swap and base word
-                        // words with swap are ignored if no swap keys provided
-                        if base_word.contains("{SWAP}") {
-                            for s in &self.swap {
-                                self.word_perms
-                                    .push_back(base_word.clone().replace("{SWAP}", &s))
-                            }
-                        } else {
-                            self.word_perms.push_back(base_word.clone());
-                        }
+    /// Replace the base words with every number of `lengths` digits, the
+    /// likely ones first (see [`Pins`]), e.g. `1234`, `1987` or `0101`.
+    pub fn set_pins(&mut self, lengths: RangeInclusive<usize>) -> io::Result<()> {
+        let pins = Pins::new(lengths);
+        self.base_count = usize::try_from(pins.total()).unwrap_or(usize::MAX);
+        self.reader.set_pins(pins);
+        self.word_perms.clear();
+        self.bases.clear();
+        self.reversed = None;
+        self.pass = 0;
+        self.position = 0;
+        self.rescan()
+    }
 
-                        // handle prepends
-                        for i in 0..self.word_perms.len() {
-                            for p in &self.prepend {
-                                self.word_perms
-                                    .push_back(format!("{}{}", p, self.word_perms[i]));
-                            }
-                        }
+    /// Replace the base words with every phone number of `region` in
+    /// `area_codes`, written in each of `formats` (see [`Phones`]), e.g.
+    /// `212-555-0142` or `+12125550142`.
+    pub fn set_phones(
+        &mut self,
+        region: Region,
+        area_codes: &[String],
+        formats: &[String],
+    ) -> io::Result<()> {
+        let phones = Phones::new(region, area_codes, formats)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.base_count = usize::try_from(phones.total()).unwrap_or(usize::MAX);
+        self.reader.set_phones(phones);
+        self.word_perms.clear();
+        self.bases.clear();
+        self.reversed = None;
+        self.pass = 0;
+        self.position = 0;
+        self.rescan()
+    }
 
-                        // handle appends
-                        for i in 0..self.word_perms.len() {
-                            for a in &self.append {
-                                self.word_perms
-                                    .push_back(format!("{}{}", self.word_perms[i], a));
-                            }
-                        }
+    /// Replace the base words with every string matching each of
+    /// `patterns` in turn (see [`Patterns`]), e.g. `AAA-9999` for license
+    /// plates.
+    pub fn set_patterns(&mut self, patterns: &[String]) -> io::Result<()> {
+        let patterns =
+            Patterns::new(patterns).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.base_count = usize::try_from(patterns.total()).unwrap_or(usize::MAX);
+        self.reader.set_patterns(patterns);
+        self.word_perms.clear();
+        self.bases.clear();
+        self.reversed = None;
+        self.pass = 0;
+        self.position = 0;
+        self.rescan()
+    }
 
-                        // handle extensions
-                        for i in 0..self.word_perms.len() {
-                            for e in &self.extensions {
-                                self.word_perms
-                                    .push_back(format!("{}{}", self.word_perms[i], e));
-                            }
-                        }
-                    } else {
-                        return None;
-                    }
+    /// Replace the base words with every string `regex` matches (see
+    /// [`Matches`]), e.g. `(dev|prod)-(api|web)[0-9]{2}`. Quantifiers
+    /// must be bounded.
+    pub fn set_regex(&mut self, regex: &str) -> io::Result<()> {
+        let matches =
+            Matches::regex(regex).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.base_count = usize::try_from(matches.total()).unwrap_or(usize::MAX);
+        self.reader.set_matches(matches);
+        self.word_perms.clear();
+        self.bases.clear();
+        self.reversed = None;
+        self.pass = 0;
+        self.position = 0;
+        self.rescan()
+    }
+
+    /// Replace the base words with every string the grammar file at `path`
+    /// describes (see [`grammar`]), e.g. hostnames such as `dev-web01`.
+    pub fn set_grammar(&mut self, path: &Path) -> io::Result<()> {
+        let matches = Matches::grammar(path)?;
+        self.base_count = usize::try_from(matches.total()).unwrap_or(usize::MAX);
+        self.reader.set_matches(matches);
+        self.word_perms.clear();
+        self.bases.clear();
+        self.reversed = None;
+        self.pass = 0;
+        self.position = 0;
+        self.rescan()
+    }
+
+    /// Marker replaced by each swap value, `{SWAP}` by default.
+    pub fn swap_token(&self) -> &str {
+        &self.syntax.swap
+    }
+
+    /// Use `token` (e.g. `FUZZ`) as the swap marker instead of `{SWAP}`, so
+    /// lists written for other tools can be read as-is. `FUZZ?` is then the
+    /// optional form.
+    pub fn set_swap_token(&mut self, token: &str) -> io::Result<()> {
+        self.syntax = Syntax::bare(token);
+        self.rescan()
+    }
+
+    /// Read tokens as `SWAP` between `open` and `close` (e.g. `%%SWAP%%`)
+    /// rather than between braces.
+    pub fn set_token_delimiters(&mut self, open: &str, close: &str) -> io::Result<()> {
+        self.syntax = Syntax::delimited(open, close);
+        self.rescan()
+    }
+
+    /// Expand tokens inside swap values too, up to `depth` levels, so a
+    /// value like `stage-{SWAP}` draws on the other values. At depth 0 (the
+    /// default) swap values are used literally.
+    pub fn set_nest_depth(&mut self, depth: usize) -> io::Result<()> {
+        self.nest_depth = depth;
+        self.rescan()
+    }
+
+    /// Produce `count` strings for each `{RAND:len:charset}` token (one by
+    /// default). The strings are derived from `seed` and the line, so the
+    /// same seed always gives the same output.
+    pub fn set_random(&mut self, count: usize, seed: u64) -> io::Result<()> {
+        let recount = count != self.bindings.random_count;
+        self.bindings.random_count = count;
+        self.bindings.seed = seed;
+        if recount {
+            self.rescan()
+        } else {
+            self.rebind();
+            Ok(())
+        }
+    }
+
+    /// Value for `{ENV:name}` tokens, taking precedence over the
+    /// environment variable of the same name.
+    pub fn set_var(&mut self, name: &str, value: &str) {
+        self.bindings
+            .vars
+            .insert(name.to_string(), value.to_string());
+        self.rebind();
+    }
+
+    /// Combine every base word with each template line, in order, by
+    /// putting the word in place of `{WORD}`. Other tokens in the template
+    /// (and in the word) are expanded as usual.
+    pub fn set_templates(&mut self, templates: Vec<String>) -> io::Result<()> {
+        self.templates = templates;
+        self.rescan()
+    }
+
+    /// Words bound to `{WORD2}` in templates, so a template such as
+    /// `{WORD1}.{WORD2}.bak` combines every base word (`{WORD1}`, same as
+    /// `{WORD}`) with every one of these, the second list varying fastest.
+    pub fn set_word2(&mut self, words: Vec<String>) -> io::Result<()> {
+        self.word2 = words;
+        self.rescan()
+    }
+
+    /// Follow every base word with its variants under each of `mutations`
+    /// in turn, before any affixes are added (see [`mutate`]).
+    pub fn set_mutations(&mut self, mutations: Vec<Mutation>) -> io::Result<()> {
+        self.mutations = mutations;
+        self.rescan()
+    }
+
+    /// Rewrite every base word, variants included, with `rules` in order
+    /// before any affixes are added (see [`mutate::substitute`]).
+    pub fn set_subs(&mut self, rules: Vec<SubRule>) -> io::Result<()> {
+        self.subs = rules;
+        self.rescan()
+    }
+
+    /// Add substitution rules while candidates are being generated; they
+    /// apply from the next base word read on. Unlike
+    /// [`Wordlist::set_subs`], nothing is recounted, so the keyspace and
+    /// candidate indexes only account for the rules set before.
+    pub fn add_subs(&mut self, rules: impl IntoIterator<Item = SubRule>) {
+        self.subs.extend(rules);
+    }
+
+    /// Put every string of the `prepend` mask before and every string of
+    /// the `append` mask after each base word, e.g. `?d` and `?d?d` for
+    /// `0word00` to `9word99`, as hashcat's hybrid modes do but on both
+    /// sides at once (see [`mutate::expand_mask`]). The word is no longer
+    /// emitted on its own.
+    ///
+    /// The masks are expanded up front and every combination for a word is
+    /// held in memory, so keep them to a few million strings.
+    pub fn set_hybrid_masks(
+        &mut self,
+        prepend: Option<&str>,
+        append: Option<&str>,
+    ) -> io::Result<()> {
+        let expand = |mask: Option<&str>| {
+            mutate::expand_mask(mask.unwrap_or(""))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+        };
+        self.hybrid = match (prepend, append) {
+            (None, None) => None,
+            _ => Some((expand(prepend)?, expand(append)?)),
+        };
+        self.recount();
+        Ok(())
+    }
+
+    /// The `n` most common lengths of the base words, most common first
+    /// (shorter on ties), to restrict [`Wordlist::lengths`] to lengths a
+    /// target's password policy likely allows.
+    pub fn top_length_buckets(&self, n: usize) -> io::Result<Vec<RangeInclusive<usize>>> {
+        let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+        self.reader.scan(|_, line| {
+            let word = self.split_line(line).0;
+            *counts.entry(word.chars().count()).or_default() += 1;
+        })?;
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        Ok(counts
+            .into_iter()
+            .take(n)
+            .map(|(len, _)| len..=len)
+            .collect())
+    }
+
+    /// Replace every base word with each of `wraps` around it, e.g.
+    /// `("<script>", "</script>")`, turning a list of names into payloads
+    /// (see [`presets::wrap_preset`]). An empty list leaves words as-is.
+    pub fn set_wraps(&mut self, wraps: Vec<(String, String)>) {
+        self.wraps = wraps;
+        self.recount();
+    }
+
+    /// Put candidates through every encoder of `chain` in turn, either
+    /// whole or just their base words (see [`EncodeScope`]).
+    pub fn set_encoders(&mut self, chain: Vec<Encoder>, scope: EncodeScope) {
+        self.encoders = chain;
+        self.encode_scope = scope;
+    }
+
+    /// Words each base word becomes through the hybrid masks and wraps.
+    fn hybrid_fanout(&self) -> u128 {
+        let masks = self.hybrid.as_ref().map_or(1, |(prepend, append)| {
+            (prepend.len() as u128).saturating_mul(append.len() as u128)
+        });
+        masks.saturating_mul(self.wraps.len().max(1) as u128)
+    }
+
+    /// Pick base words out of wordlist files in `format`, such as one
+    /// column of a CSV export. Preloaded words are used as they are.
+    ///
+    /// Call before iterating: reading starts over from the first line.
+    pub fn set_input_format(&mut self, format: InputFormat) -> io::Result<()> {
+        self.reader.set_format(format)?;
+        self.rescan()
+    }
+
+    /// Read lines as `word<TAB>columns…`, such as `admin<TAB>source_url`,
+    /// and copy the columns after every candidate made from the word,
+    /// tab separated, so each row can be traced back to its source.
+    pub fn set_metadata_columns(&mut self, enabled: bool) -> io::Result<()> {
+        self.metadata_columns = enabled;
+        self.rescan()
+    }
+
+    /// The base word, disabled affixes and metadata columns of `line`.
+    fn split_line<'a>(&self, line: &'a str) -> (&'a str, Vec<Affix>, &'a str) {
+        let (line, metadata) = match line.split_once('\t') {
+            Some(split) if self.metadata_columns => split,
+            _ => (line, ""),
+        };
+        let (word, disabled) = if self.directives {
+            predicate::split_directives(line)
+        } else {
+            (line, vec![])
+        };
+        (word, disabled, metadata)
+    }
+
+    /// Whether base words must be looked at one by one to count them.
+    fn counts_each_word(&self) -> bool {
+        !self.mutations.is_empty() || !self.subs.is_empty()
+    }
+
+    /// Re-expand the swap values after a change to what tokens resolve to.
+    fn rebind(&mut self) {
+        template::bind_values(
+            &mut self.bindings,
+            &self.swap,
+            &self.syntax,
+            self.nest_depth,
+        );
+    }
+
+    /// Count the words token lines expand to, then recount.
+    fn rescan(&mut self) -> io::Result<()> {
+        self.rebind();
+        let (syntax, bindings) = (&self.syntax, &self.bindings);
+        let metadata_columns = self.metadata_columns;
+        let (mut lines, mut bases, mut words) = (0usize, 0u128, 0usize);
+        let mut inputs = Fnv64::default();
+        self.reader.scan(|source, word| {
+            words += 1;
+            inputs.write(&(source as u64).to_le_bytes());
+            inputs.write(word.as_bytes());
+            inputs.write(b"\n");
+            let word = match word.split_once('\t') {
+                Some((word, _)) if metadata_columns => word,
+                _ => word,
+            };
+            let segments = template::parse(word, syntax);
+            if template::has_tokens(&segments) {
+                lines += 1;
+                bases = bases.saturating_add(template::fanout(&segments, bindings));
+            }
+        })?;
+        self.token_lines = lines;
+        self.token_bases = bases;
+        self.input_hash = inputs.finish();
+        if *self.reader.format() != InputFormat::Lines {
+            // rows without a word are skipped, so lines can't be counted raw
+            self.base_count = words;
+        }
+        let fanout = |line: &str| template::fanout(&template::parse(line, syntax), bindings);
+        let word2_fanout = self
+            .word2
+            .iter()
+            .fold(0u128, |total, word| total.saturating_add(fanout(word)));
+        self.template_fanout = if self.templates.is_empty() {
+            1
+        } else {
+            self.templates.iter().fold(0u128, |total, line| {
+                let mut words = fanout(line);
+                if !self.word2.is_empty() && line.contains(&syntax.word2) {
+                    words = words.saturating_mul(word2_fanout);
                 }
-                Err(_) => return None,
+                total.saturating_add(words)
+            })
+        };
+        if self.counts_each_word() {
+            // variant counts depend on each word, so every line is expanded
+            let mut cursor = self.restarted()?;
+            cursor.reverse = false;
+            let mut bases = 0u128;
+            while let Some(line) = cursor.next_base() {
+                let base_word = cursor.split_line(&line).0;
+                bases = bases.saturating_add(cursor.line_words(base_word));
+            }
+            self.mutated_bases = bases;
+        }
+        self.recount();
+        Ok(())
+    }
+
+    /// Identifies the sequence of candidates this wordlist yields: the same
+    /// value means the same candidates in the same order, across releases
+    /// sharing an [`ORDERING_VERSION`].
+    ///
+    /// Covers the contents of the inputs and every option affecting the
+    /// output, but not `{ENV:name}` values read from the environment.
+    pub fn ordering_fingerprint(&self) -> u64 {
+        let mut h = Fnv64::default();
+        let num = |h: &mut Fnv64, n: u64| h.write(&n.to_le_bytes());
+        let text = |h: &mut Fnv64, s: &str| {
+            h.write(&(s.len() as u64).to_le_bytes());
+            h.write(s.as_bytes());
+        };
+
+        num(&mut h, ORDERING_VERSION as u64);
+        num(&mut h, self.input_hash);
+        for values in [
+            &self.prepend,
+            &self.append,
+            &self.swap,
+            &self.extensions,
+            &self.templates,
+            &self.word2,
+        ] {
+            num(&mut h, values.len() as u64);
+            values.iter().for_each(|value| text(&mut h, value));
+        }
+        for flag in [
+            self.likelihood_order,
+            self.interleave,
+            self.skip_degenerate,
+            self.ext_strip,
+            self.directives,
+        ] {
+            num(&mut h, flag as u64);
+        }
+        // only hashed when set, so forward fingerprints are unchanged
+        if self.reverse {
+            text(&mut h, "reverse");
+        }
+        if !self.mutations.is_empty() {
+            let mutations = serde_json::to_string(&self.mutations).unwrap_or_default();
+            text(&mut h, &mutations);
+        }
+        if let Some((prepend, append)) = &self.hybrid {
+            for values in [prepend, append] {
+                num(&mut h, values.len() as u64);
+                values.iter().for_each(|value| text(&mut h, value));
             }
         }
-        self.word_perms.pop_front()
+        if !self.wraps.is_empty() {
+            num(&mut h, self.wraps.len() as u64);
+            for (before, after) in &self.wraps {
+                text(&mut h, before);
+                text(&mut h, after);
+            }
+        }
+        if self.metadata_columns {
+            text(&mut h, "metadata-columns");
+        }
+        if !self.lengths.is_empty() {
+            text(&mut h, "lengths");
+            for range in &self.lengths {
+                num(&mut h, *range.start() as u64);
+                num(&mut h, *range.end() as u64);
+            }
+        }
+        if !self.encoders.is_empty() {
+            let scope = match self.encode_scope {
+                EncodeScope::Each => "each",
+                EncodeScope::Final => "final",
+            };
+            text(&mut h, scope);
+            for encoder in &self.encoders {
+                text(&mut h, &encoder.to_string());
+            }
+        }
+        if !self.subs.is_empty() {
+            num(&mut h, self.subs.len() as u64);
+            for rule in &self.subs {
+                text(&mut h, &rule.to_string());
+            }
+        }
+        num(&mut h, self.apply.len() as u64);
+        for rule in &self.apply {
+            text(&mut h, &rule.to_string());
+        }
+
+        let syntax = &self.syntax;
+        for marker in [
+            &syntax.swap,
+            &syntax.optional,
+            &syntax.word,
+            &syntax.word1,
+            &syntax.word2,
+            &syntax.open,
+            &syntax.close,
+        ] {
+            text(&mut h, marker);
+        }
+        num(&mut h, self.nest_depth as u64);
+        num(&mut h, self.bindings.random_count as u64);
+        num(&mut h, self.bindings.seed);
+        let mut vars: Vec<_> = self.bindings.vars.iter().collect();
+        vars.sort();
+        num(&mut h, vars.len() as u64);
+        for (name, value) in vars {
+            text(&mut h, name);
+            text(&mut h, value);
+        }
+        h.finish()
+    }
+
+    /// Exact number of candidates, saturating at `u128::MAX`.
+    ///
+    /// Filters that drop candidates ([`Wordlist::skip_degenerate`],
+    /// [`Wordlist::lengths`], [`Wordlist::apply`] rules,
+    /// [`Wordlist::directives`]) are not
+    /// accounted for, making this an upper bound when they are in use.
+    pub fn keyspace(&self) -> u128 {
+        self.keyspace
+    }
+
+    fn recount(&mut self) {
+        // token lines become one word per combination of token values, each
+        // word is put through every template, and every resulting base word
+        // fans out into (1 + P)(1 + A)(1 + E) candidates
+        let bases = if !self.counts_each_word() {
+            (self.base_count.saturating_sub(self.token_lines) as u128)
+                .saturating_add(self.token_bases)
+                .saturating_mul(self.template_fanout)
+        } else {
+            self.mutated_bases
+        }
+        .saturating_mul(self.hybrid_fanout());
+        let fanout = [&self.prepend, &self.append, &self.extensions]
+            .iter()
+            .fold(1u128, |total, values| {
+                total.saturating_mul(1 + values.len() as u128)
+            });
+        self.keyspace = bases.saturating_mul(fanout);
+        self.total_count = usize::try_from(self.keyspace).unwrap_or(usize::MAX);
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use ctor;
-    use std::io::Write;
+impl Wordlist {
+    /// Rough size in bytes of the inflated output, newlines included.
+    ///
+    /// Assumes base words of average length and affixes spread evenly, so it
+    /// is only meant for display.
+    pub fn estimated_bytes(&self) -> u64 {
+        let file_size = self.reader.byte_len();
+        if self.base_count == 0 {
+            return 0;
+        }
+        let avg_base =
+            file_size.saturating_sub(self.base_count as u64) as f64 / self.base_count as f64;
+        // share of the candidates carrying each affix, times its mean length
+        let affix = |values: &Vec<String>| {
+            let total: usize = values.iter().map(|v| v.len()).sum();
+            total as f64 / (1 + values.len()) as f64
+        };
+        let per_word =
+            avg_base + affix(&self.prepend) + affix(&self.append) + affix(&self.extensions) + 1.0;
+        (per_word * self.keyspace as f64) as u64
+    }
+}
 
-    // Note this useful idiom: importing names from outer (for mod tests) scope.
-    use super::*;
+impl Wordlist {
+    /// Render an origin as a provenance tag such as `swap:dev,prepend:-,ext:.bak`.
+    ///
+    /// Only transforms that have values configured are listed; `-` marks a
+    /// transform that was not applied to this candidate.
+    pub fn describe(&self, origin: &Origin) -> String {
+        let parts = [
+            ("swap", &self.swap, origin.swap),
+            ("prepend", &self.prepend, origin.prepend),
+            ("append", &self.append, origin.append),
+            ("ext", &self.extensions, origin.extension),
+        ];
+        parts
+            .iter()
+            .filter(|(_, values, _)| !values.is_empty())
+            .map(|(name, values, idx)| match idx {
+                Some(i) => format!("{}:{}", name, values[*i]),
+                None => format!("{}:-", name),
+            })
+            .collect::<Vec<String>>()
+            .join(",")
+    }
 
-    const WL_PATH: &str = "/tmp/rustbuster_test_wordlist.txt";
+    /// Index of the next candidate to be emitted.
+    pub fn position(&self) -> usize {
+        self.position
+    }
 
-    #[ctor::ctor]
-    fn gen_test_wordlist() {
-        let lines_vec = vec!["test", "line2", "{SWAP}stest"];
-        let tfile = std::fs::File::create(WL_PATH).unwrap();
-        let mut writer = std::io::BufWriter::new(tfile);
+    /// Advance so the next candidate emitted is the one at `index`.
+    ///
+    /// Base lines lying wholly before `index` are counted rather than
+    /// expanded, so seeking costs about as much as reading the input up to
+    /// that point. Lines whose count depends on the words they expand to
+    /// ([`Wordlist::skip_degenerate`], [`Wordlist::lengths`],
+    /// [`Wordlist::apply`] rules) are still expanded.
+    ///
+    /// Seeking backwards is not supported; the position is left unchanged
+    /// if `index` is behind it. Returns the new position, which is short of
+    /// `index` if the wordlist ran out first.
+    pub fn seek_to(&mut self, index: usize) -> usize {
+        loop {
+            let queued = self
+                .word_perms
+                .len()
+                .min(index.saturating_sub(self.position));
+            if self.reverse {
+                self.word_perms.truncate(self.word_perms.len() - queued);
+            } else {
+                self.word_perms.drain(..queued);
+            }
+            self.position += queued;
+            if self.position >= index {
+                break;
+            }
 
-        for l in lines_vec {
-            writer.write_all(l.as_bytes()).unwrap();
-            writer.write(b"\n").unwrap();
+            let line = match self.next_base() {
+                Some(line) => line,
+                None if self.next_pass() => continue,
+                None => break,
+            };
+            match self.line_candidates(&line) {
+                Some(count) if self.position.saturating_add(count) <= index => {
+                    self.position += count;
+                }
+                _ => self.expand(&line),
+            }
         }
-        writer.flush().unwrap();
+        self.position
     }
 
-    #[ctor::dtor]
-    fn cleanup_wordlist() {
-        std::fs::remove_file(WL_PATH).unwrap()
+    /// The candidate at `index`, or `None` past the end, without moving
+    /// this wordlist's position.
+    ///
+    /// The inputs are read again from the start, skipping whole lines as
+    /// [`Wordlist::seek_to`] does, so the cost grows with the input read
+    /// rather than the number of candidates before `index`.
+    pub fn nth_candidate(&self, index: usize) -> Option<String> {
+        let mut cursor = self.restarted().ok()?;
+        if cursor.seek_to(index) < index {
+            return None;
+        }
+        cursor.next()
     }
 
-    fn do_vecs_match<T: PartialEq>(a: &Vec<T>, b: &Vec<T>) -> bool {
-        let matching = a.iter().zip(b.iter()).filter(|&(a, b)| a == b).count();
-        matching == a.len() && matching == b.len()
+    /// A copy of this wordlist positioned at the first candidate.
+    fn restarted(&self) -> io::Result<Self> {
+        Ok(self.with_reader(self.reader.reopen()?))
     }
 
-    #[test]
-    fn test_count_lines() {
-        let handle = std::fs::File::open(WL_PATH).unwrap();
-        let count = count_lines(handle);
+    /// A copy of this wordlist's configuration with no input, for
+    /// expanding lines read elsewhere with [`Wordlist::expand_line`].
+    fn expander(&self) -> Self {
+        self.with_reader(Inputs::from_words(vec![]))
+    }
 
-        println!("count_lines: {}", count);
+    /// A copy of this wordlist's configuration reading from `reader`.
+    fn with_reader(&self, reader: Inputs) -> Self {
+        Self {
+            path: self.path.clone(),
+            base_count: self.base_count,
+            total_count: self.total_count,
+            reader,
+            prepend: self.prepend.clone(),
+            append: self.append.clone(),
+            swap: self.swap.clone(),
+            extensions: self.extensions.clone(),
+            likelihood_order: self.likelihood_order,
+            interleave: self.interleave,
+            skip_degenerate: self.skip_degenerate,
+            lengths: self.lengths.clone(),
+            ext_strip: self.ext_strip,
+            apply: self.apply.clone(),
+            directives: self.directives,
+            reverse: self.reverse,
+            reversed: None,
+            syntax: self.syntax.clone(),
+            nest_depth: self.nest_depth,
+            bindings: self.bindings.clone(),
+            templates: self.templates.clone(),
+            word2: self.word2.clone(),
+            mutations: self.mutations.clone(),
+            subs: self.subs.clone(),
+            hybrid: self.hybrid.clone(),
+            wraps: self.wraps.clone(),
+            encoders: self.encoders.clone(),
+            encode_scope: self.encode_scope,
+            metadata_columns: self.metadata_columns,
+            metadata: String::new(),
+            mutated_bases: self.mutated_bases,
+            template_fanout: self.template_fanout,
+            token_lines: self.token_lines,
+            token_bases: self.token_bases,
+            keyspace: self.keyspace,
+            input_hash: self.input_hash,
+            bases: Vec::new(),
+            word_perms: VecDeque::new(),
+            scratch: String::new(),
+            pass: 0,
+            position: 0,
+        }
+    }
 
-        assert!(count == 3);
+    /// Next base line and the likelihood pass it belongs to. Only valid
+    /// while no candidates are queued.
+    fn next_line(&mut self) -> Option<(String, usize)> {
+        loop {
+            match self.next_base() {
+                Some(line) => return Some((line, self.pass)),
+                None if self.next_pass() => continue,
+                None => return None,
+            }
+        }
+    }
+
+    /// Queue the candidates `line` yields in likelihood pass `pass`.
+    fn expand_line(&mut self, line: &str, pass: usize) {
+        self.pass = pass;
+        self.expand(line);
+    }
+
+    /// Number of base words `base_word` becomes through templates, tokens
+    /// and mutations, before affixes.
+    fn line_words(&self, base_word: &str) -> u128 {
+        let syntax = &self.syntax;
+        let fanout = |line: &str| {
+            let segments = template::parse(line, syntax);
+            if !self.counts_each_word() {
+                return template::fanout(&segments, &self.bindings);
+            }
+            let mut words = 0u128;
+            let mut add = |word: &str| {
+                if self.subs.is_empty() {
+                    let variants = self.mutations.iter().map(|m| m.count(word));
+                    words = variants.fold(words.saturating_add(1), u128::saturating_add);
+                    return;
+                }
+                let mut forms = |word: &str| {
+                    mutate::substitute(&self.subs, word, |_| words = words.saturating_add(1))
+                };
+                forms(word);
+                for mutation in &self.mutations {
+                    mutation.variants(word, |variant| forms(&variant));
+                }
+            };
+            if template::has_tokens(&segments) {
+                template::render(line, &segments, &self.bindings, |word, _| add(&word));
+            } else {
+                add(line);
+            }
+            words
+        };
+        if self.templates.is_empty() {
+            fanout(base_word)
+        } else {
+            self.templates.iter().fold(0u128, |total, template| {
+                let line = template
+                    .replace(&syntax.word, base_word)
+                    .replace(&syntax.word1, base_word);
+                let words = if self.word2.is_empty() || !line.contains(&syntax.word2) {
+                    fanout(&line)
+                } else {
+                    self.word2.iter().fold(0u128, |total, word2| {
+                        total.saturating_add(fanout(&line.replace(&syntax.word2, word2)))
+                    })
+                };
+                total.saturating_add(words)
+            })
+        }
+    }
+
+    /// Number of candidates `line` yields in the current pass, if it can
+    /// be known without expanding the line.
+    fn line_candidates(&self, line: &str) -> Option<usize> {
+        if self.skip_degenerate || !self.apply.is_empty() || !self.lengths.is_empty() {
+            return None;
+        }
+        let (base_word, disabled, _) = self.split_line(line);
+
+        let words = self
+            .line_words(base_word)
+            .saturating_mul(self.hybrid_fanout());
+
+        let affixes = [
+            (Affix::Prepend, self.prepend.len()),
+            (Affix::Append, self.append.len()),
+            (Affix::Extension, self.extensions.len()),
+        ]
+        .map(|(affix, n)| {
+            if disabled.contains(&affix) {
+                0
+            } else {
+                n as u128
+            }
+        });
+        let per_word = if self.likelihood_order {
+            // ways to pick exactly `pass` of the affix stages, one value each
+            let mut ways = [1u128, 0, 0, 0];
+            for n in affixes {
+                for k in (1..ways.len()).rev() {
+                    ways[k] = ways[k].saturating_add(ways[k - 1].saturating_mul(n));
+                }
+            }
+            ways[self.pass]
+        } else {
+            affixes
+                .iter()
+                .fold(1u128, |total, &n| total.saturating_mul(1 + n))
+        };
+        usize::try_from(words.saturating_mul(per_word)).ok()
+    }
+
+    /// Yield the next candidate along with the transforms that produced it.
+    pub fn next_with_origin(&mut self) -> Option<(String, Origin)> {
+        let (base, origin) = self.next_perm()?;
+        let mut word = String::new();
+        self.render_into(base, &origin, &mut word);
+        Some((word, origin))
+    }
+
+    /// Write every remaining candidate to `w`, each followed by
+    /// `terminator`, and return the number of bytes written.
+    ///
+    /// Candidates are formatted into one reused buffer rather than
+    /// allocated one by one, so this is the fastest way to drain a
+    /// wordlist. `w` sees small writes, so wrap it in a `BufWriter` if
+    /// those are costly.
+    pub fn write_to<W: Write>(&mut self, mut w: W, terminator: &[u8]) -> io::Result<u64> {
+        let mut written = 0u64;
+        if terminator == b"\n" && self.is_passthrough() {
+            while let Some((_, len)) = self.write_block(&mut w, usize::MAX)? {
+                written += len as u64;
+            }
+            return Ok(written);
+        }
+        while let Some((len, _)) = self.write_next(&mut w, terminator)? {
+            written += len as u64;
+        }
+        Ok(written)
+    }
+
+    /// Whether every input line comes out unchanged, one candidate each:
+    /// no affixes, templates or tokens, nothing reordering the input and
+    /// no length filter.
+    pub fn is_passthrough(&self) -> bool {
+        self.prepend.is_empty()
+            && self.append.is_empty()
+            && self.extensions.is_empty()
+            && self.templates.is_empty()
+            && self.token_lines == 0
+            && !self.counts_each_word()
+            && self.hybrid.is_none()
+            && self.wraps.is_empty()
+            && self.encoders.is_empty()
+            && !self.directives
+            && !self.reverse
+            && !self.interleave
+            && self.lengths.is_empty()
+    }
+
+    /// Write up to `limit` (at least one) newline-terminated candidates
+    /// to `w`, returning how many were written and their size in bytes, or
+    /// `None` once the wordlist is exhausted.
+    ///
+    /// For a [passthrough](Wordlist::is_passthrough) wordlist read from
+    /// files, whole buffered blocks of input are copied as they are.
+    /// Otherwise, and for lines that need normalizing (`\r\n` endings, a
+    /// missing final newline), a single candidate is written as by
+    /// [`Wordlist::write_next`].
+    pub fn write_block<W: Write>(
+        &mut self,
+        mut w: W,
+        limit: usize,
+    ) -> io::Result<Option<(usize, usize)>> {
+        let passthrough = self.is_passthrough() && self.word_perms.is_empty();
+        if let Some(reader) = self.reader.current_file().filter(|_| passthrough) {
+            let buf = reader.fill_buf().unwrap_or_default();
+            let (mut lines, mut end) = (0, 0);
+            for (i, _) in buf.iter().enumerate().filter(|(_, &b)| b == b'\n') {
+                lines += 1;
+                end = i + 1;
+                if lines == limit {
+                    break;
+                }
+            }
+            let block = &buf[..end];
+            if lines > 0 && !block.contains(&b'\r') && std::str::from_utf8(block).is_ok() {
+                w.write_all(block)?;
+                reader.consume(end);
+                self.position += lines;
+                return Ok(Some((lines, end)));
+            }
+        }
+        Ok(self.write_next(w, b"\n")?.map(|(len, _)| (1, len)))
+    }
+
+    /// Write the next candidate and `terminator` to `w` as
+    /// [`Wordlist::write_to`] does, returning the bytes written and the
+    /// candidate's origin, or `None` once the wordlist is exhausted.
+    pub fn write_next<W: Write>(
+        &mut self,
+        mut w: W,
+        terminator: &[u8],
+    ) -> io::Result<Option<(usize, Origin)>> {
+        let (base, origin) = match self.next_perm() {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+        let mut word = std::mem::take(&mut self.scratch);
+        word.clear();
+        self.render_into(base, &origin, &mut word);
+        let written = w
+            .write_all(word.as_bytes())
+            .and_then(|_| w.write_all(terminator));
+        let len = word.len() + terminator.len();
+        self.scratch = word;
+        written.map(|_| Some((len, origin)))
+    }
+
+    /// Pop the next queued candidate, expanding base lines as needed.
+    fn next_perm(&mut self) -> Option<(usize, Origin)> {
+        while self.word_perms.is_empty() {
+            let (line, _) = self.next_line()?;
+            self.expand(&line);
+        }
+        self.pop_queued()
+    }
+
+    /// Pop the next candidate queued from the current base line.
+    fn pop_queued(&mut self) -> Option<(usize, Origin)> {
+        let next = if self.reverse {
+            self.word_perms.pop_back()
+        } else {
+            self.word_perms.pop_front()
+        };
+        if next.is_some() {
+            self.position += 1;
+        }
+        next
+    }
+
+    /// Next base word to expand, from the end of the input when reversed.
+    fn next_base(&mut self) -> Option<String> {
+        if !self.reverse {
+            return self.reader.next_line(self.interleave);
+        }
+        if self.reversed.is_none() {
+            let mut lines = vec![];
+            while let Some(line) = self.reader.next_line(self.interleave) {
+                lines.push(line);
+            }
+            let left = lines.len();
+            self.reversed = Some((lines, left));
+            if self.likelihood_order {
+                self.pass = self.max_pass();
+            }
+        }
+        let (lines, left) = self.reversed.as_mut()?;
+        *left = left.checked_sub(1)?;
+        Some(lines[*left].clone())
+    }
+
+    /// Number of the last likelihood pass.
+    fn max_pass(&self) -> usize {
+        [&self.prepend, &self.append, &self.extensions]
+            .iter()
+            .filter(|values| !values.is_empty())
+            .count()
+    }
+
+    /// Start the next likelihood pass over the input, if there is one.
+    fn next_pass(&mut self) -> bool {
+        if self.reverse {
+            return match &mut self.reversed {
+                Some((lines, left)) if self.likelihood_order && self.pass > 0 => {
+                    *left = lines.len();
+                    self.pass -= 1;
+                    true
+                }
+                _ => false,
+            };
+        }
+        if !self.likelihood_order || self.pass >= self.max_pass() {
+            return false;
+        }
+        match self.reader.rewind() {
+            Ok(()) => {
+                self.pass += 1;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Whether `origin` doubles up the base word or an affix, e.g. the
+    /// prepend "admin" on the base word "admin".
+    fn is_degenerate(&self, base: &str, origin: &Origin) -> bool {
+        let prepend = origin.prepend.map(|i| self.prepend[i].as_str());
+        let append = origin.append.map(|i| self.append[i].as_str());
+        let extension = origin.extension.map(|i| self.extensions[i].as_str());
+
+        if prepend == Some(base) || append == Some(base) {
+            return true;
+        }
+        let affixes = [prepend, append, extension];
+        affixes
+            .iter()
+            .enumerate()
+            .any(|(i, a)| a.is_some() && affixes[i + 1..].iter().any(|b| b == a))
+    }
+
+    /// Whether the apply rules let `affix` apply to `base`.
+    fn applies(&self, affix: Affix, base: &str) -> bool {
+        self.apply
+            .iter()
+            .filter(|rule| rule.affix == affix)
+            .all(|rule| rule.allows(base))
+    }
+
+    /// Queue every word the tokens in `line` expand to.
+    fn expand_tokens(&mut self, line: &str) {
+        let segments = template::parse(line, &self.syntax);
+        if template::has_tokens(&segments) {
+            let (bases, word_perms) = (&mut self.bases, &mut self.word_perms);
+            template::render(line, &segments, &self.bindings, |word, swap| {
+                let origin = Origin {
+                    swap,
+                    ..Default::default()
+                };
+                word_perms.push_back((bases.len(), origin));
+                bases.push(word);
+            });
+        } else {
+            self.word_perms
+                .push_back((self.bases.len(), Origin::default()));
+            self.bases.push(line.to_string());
+        }
+    }
+
+    /// Queue every permutation of a single base word.
+    fn expand(&mut self, line: &str) {
+        let (base_word, disabled, metadata) = self.split_line(line);
+        self.metadata.clear();
+        self.metadata.push_str(metadata);
+        self.bases.clear();
+
+        // handle templates, swap and base word
+        // words with swap are ignored if no swap keys provided
+        if self.templates.is_empty() {
+            self.expand_tokens(base_word);
+        } else {
+            for i in 0..self.templates.len() {
+                let line = self.templates[i]
+                    .replace(&self.syntax.word, base_word)
+                    .replace(&self.syntax.word1, base_word);
+                if self.word2.is_empty() || !line.contains(&self.syntax.word2) {
+                    self.expand_tokens(&line);
+                    continue;
+                }
+                for j in 0..self.word2.len() {
+                    let line = line.replace(&self.syntax.word2, &self.word2[j]);
+                    self.expand_tokens(&line);
+                }
+            }
+        }
+
+        // handle mutations, each word followed by its variants
+        if !self.mutations.is_empty() {
+            let words = std::mem::take(&mut self.word_perms);
+            let mut variants = vec![];
+            for (base, origin) in words {
+                self.word_perms.push_back((base, origin));
+                for mutation in &self.mutations {
+                    mutation.variants(&self.bases[base], |word| variants.push(word));
+                }
+                for word in variants.drain(..) {
+                    self.word_perms.push_back((self.bases.len(), origin));
+                    self.bases.push(word);
+                }
+            }
+        }
+
+        // handle substitutions, each word replaced by its rewritten forms
+        if !self.subs.is_empty() {
+            let words = std::mem::take(&mut self.word_perms);
+            let mut forms = vec![];
+            for (base, origin) in words {
+                mutate::substitute(&self.subs, &self.bases[base], |form| forms.push(form));
+                for form in forms.drain(..) {
+                    self.word_perms.push_back((self.bases.len(), origin));
+                    self.bases.push(form);
+                }
+            }
+        }
+
+        // handle hybrid masks, each word replaced by every pair around it
+        if let Some((prepend, append)) = &self.hybrid {
+            let words = std::mem::take(&mut self.word_perms);
+            for (base, origin) in words {
+                for before in prepend {
+                    for after in append {
+                        let word = format!("{}{}{}", before, self.bases[base], after);
+                        self.word_perms.push_back((self.bases.len(), origin));
+                        self.bases.push(word);
+                    }
+                }
+            }
+        }
+
+        // handle wraps, each word replaced by every wrapped form
+        if !self.wraps.is_empty() {
+            let words = std::mem::take(&mut self.word_perms);
+            for (base, origin) in words {
+                for (before, after) in &self.wraps {
+                    let word = format!("{}{}{}", before, self.bases[base], after);
+                    self.word_perms.push_back((self.bases.len(), origin));
+                    self.bases.push(word);
+                }
+            }
+        }
+
+        if self.encode_scope == EncodeScope::Each && !self.encoders.is_empty() {
+            for word in self.bases.iter_mut() {
+                *word = encode::encode_chain(&self.encoders, word);
+            }
+        }
+
+        // handle prepends
+        for i in 0..self.word_perms.len() {
+            let (base, origin) = self.word_perms[i];
+            if disabled.contains(&Affix::Prepend)
+                || !self.applies(Affix::Prepend, &self.bases[base])
+            {
+                continue;
+            }
+            for j in 0..self.prepend.len() {
+                let origin = Origin {
+                    prepend: Some(j),
+                    ..origin
+                };
+                self.word_perms.push_back((base, origin));
+            }
+        }
+
+        // handle appends
+        for i in 0..self.word_perms.len() {
+            let (base, origin) = self.word_perms[i];
+            if disabled.contains(&Affix::Append) || !self.applies(Affix::Append, &self.bases[base])
+            {
+                continue;
+            }
+            for j in 0..self.append.len() {
+                let origin = Origin {
+                    append: Some(j),
+                    ..origin
+                };
+                self.word_perms.push_back((base, origin));
+            }
+        }
+
+        // handle extensions
+        for i in 0..self.word_perms.len() {
+            let (base, origin) = self.word_perms[i];
+            if disabled.contains(&Affix::Extension)
+                || !self.applies(Affix::Extension, &self.bases[base])
+            {
+                continue;
+            }
+            for j in 0..self.extensions.len() {
+                let origin = Origin {
+                    extension: Some(j),
+                    ..origin
+                };
+                self.word_perms.push_back((base, origin));
+            }
+        }
+
+        if self.skip_degenerate {
+            let perms = std::mem::take(&mut self.word_perms);
+            self.word_perms = perms
+                .into_iter()
+                .filter(|(base, origin)| !self.is_degenerate(&self.bases[*base], origin))
+                .collect();
+        }
+
+        if !self.lengths.is_empty() {
+            let mut word = String::new();
+            let perms = std::mem::take(&mut self.word_perms);
+            self.word_perms = perms
+                .into_iter()
+                .filter(|(base, origin)| {
+                    word.clear();
+                    self.render_into(*base, origin, &mut word);
+                    let candidate = match word.split_once('\t') {
+                        Some((candidate, _)) if self.metadata_columns => candidate,
+                        _ => &word,
+                    };
+                    let len = candidate.chars().count();
+                    self.lengths.iter().any(|range| range.contains(&len))
+                })
+                .collect();
+        }
+
+        // likelihood passes only keep candidates with exactly `pass` affixes
+        if self.likelihood_order {
+            let pass = self.pass;
+            self.word_perms
+                .retain(|(_, origin)| origin.mutations() == pass);
+        }
+    }
+
+    /// Append the candidate built from `bases[base]` and `origin` to `buf`.
+    fn render_into(&self, base: usize, origin: &Origin, buf: &mut String) {
+        let start = buf.len();
+        if let Some(i) = origin.prepend {
+            buf.push_str(&self.prepend[i]);
+        }
+        buf.push_str(&self.bases[base]);
+        if let Some(i) = origin.append {
+            buf.push_str(&self.append[i]);
+        }
+        if let Some(i) = origin.extension {
+            if self.ext_strip {
+                let stem = strip_extension(&buf[start..]).len();
+                buf.truncate(start + stem);
+            }
+            buf.push_str(&self.extensions[i]);
+        }
+        if self.encode_scope == EncodeScope::Final && !self.encoders.is_empty() {
+            let encoded = encode::encode_chain(&self.encoders, &buf[start..]);
+            buf.truncate(start);
+            buf.push_str(&encoded);
+        }
+        if !self.metadata.is_empty() {
+            buf.push('\t');
+            buf.push_str(&self.metadata);
+        }
+    }
+}
+
+impl Wordlist {
+    /// Iterate from the last candidate to the first (see
+    /// [`Wordlist::reverse`]).
+    pub fn rev_words(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+}
+
+impl Iterator for Wordlist {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_origin().map(|(word, _)| word)
+    }
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::single_component_path_imports,
+    clippy::unused_io_amount,
+    clippy::ptr_arg,
+    clippy::cmp_owned
+)]
+mod tests {
+    use ctor;
+    use std::io::Write;
+
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    const WL_PATH: &str = "/tmp/rustbuster_test_wordlist.txt";
+
+    #[ctor::ctor]
+    fn gen_test_wordlist() {
+        let lines_vec = vec!["test", "line2", "{SWAP}stest"];
+        let tfile = std::fs::File::create(WL_PATH).unwrap();
+        let mut writer = std::io::BufWriter::new(tfile);
+
+        for l in lines_vec {
+            writer.write_all(l.as_bytes()).unwrap();
+            writer.write(b"\n").unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    #[ctor::dtor]
+    fn cleanup_wordlist() {
+        std::fs::remove_file(WL_PATH).unwrap()
+    }
+
+    fn do_vecs_match<T: PartialEq>(a: &Vec<T>, b: &Vec<T>) -> bool {
+        let matching = a.iter().zip(b.iter()).filter(|&(a, b)| a == b).count();
+        matching == a.len() && matching == b.len()
+    }
+
+    /// A file name in the temporary directory no other test run uses.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("wlinflate_test_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_count_lines() {
+        let handle = std::fs::File::open(WL_PATH).unwrap();
+        let count = count_lines(handle).unwrap();
+
+        println!("count_lines: {}", count);
+
+        assert!(count == 3);
+    }
+
+    #[test]
+    fn test_total_count() {
+        let pb = std::path::PathBuf::from(WL_PATH);
+        let wl = Wordlist::new(
+            &pb,
+            Some(String::from("test1,test2,test3")),
+            Some(String::from("test1,test2,test3")),
+            Some(String::from("dev,prod")),
+            Some(String::from(".txt,.bak,.file")),
+        );
+
+        let total = wl.total_count;
+        assert!(total as u128 == wl.keyspace());
+        assert!(total == wl.count());
+    }
+
+    #[test]
+    fn test_keyspace_beyond_u64() {
+        let path = &temp_path("keyspace.txt");
+        std::fs::write(path, format!("{}\nplain\n", "{l}".repeat(14))).unwrap();
+        let wl = Wordlist::new(
+            &PathBuf::from(path),
+            Some(String::from("a,b")),
+            None,
+            None,
+            None,
+        );
+        std::fs::remove_file(path).unwrap();
+
+        assert!(wl.keyspace() == (26u128.pow(14) + 1) * 3);
+        assert!(wl.keyspace() > u64::MAX as u128);
+        assert!(wl.total_count == usize::MAX);
+    }
+
+    #[test]
+    fn test_origin() {
+        let pb = std::path::PathBuf::from(WL_PATH);
+        let mut wl = Wordlist::new(
+            &pb,
+            Some(String::from("pre")),
+            None,
+            Some(String::from("dev,prod")),
+            Some(String::from(".bak")),
+        );
+
+        let mut origins = vec![];
+        while let Some((word, origin)) = wl.next_with_origin() {
+            if word.ends_with("stest.bak") {
+                origins.push((word, origin));
+            }
+        }
+
+        println!("test_origin: {:?}", origins);
+        assert!(wl.describe(&origins[3].1) == "swap:prod,prepend:pre,ext:.bak");
+        assert!(origins.len() == 4);
+        assert!(origins[3].0 == "preprodstest.bak");
+        assert!(
+            origins[3].1
+                == Origin {
+                    swap: Some(1),
+                    prepend: Some(0),
+                    append: None,
+                    extension: Some(0),
+                }
+        );
+    }
+
+    #[test]
+    fn test_likelihood_order() {
+        let pb = std::path::PathBuf::from(WL_PATH);
+        let mut wl = Wordlist::new(
+            &pb,
+            Some(String::from("pre")),
+            None,
+            None,
+            Some(String::from(".bak")),
+        );
+        wl.likelihood_order = true;
+
+        let words = wl.collect::<Vec<String>>();
+        let answer = vec![
+            "test".to_string(),
+            "line2".to_string(),
+            "pretest".to_string(),
+            "test.bak".to_string(),
+            "preline2".to_string(),
+            "line2.bak".to_string(),
+            "pretest.bak".to_string(),
+            "preline2.bak".to_string(),
+        ];
+
+        println!("test_likelihood_order: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_with_words() {
+        let words = ["admin", "{SWAP}-api", "login"].map(String::from);
+        let wl = Wordlist::with_words(
+            words,
+            vec![],
+            vec!["1".to_string()],
+            vec!["v2".to_string()],
+            vec![],
+        );
+        assert!(wl.total_count == 6);
+        assert!(wl.estimated_bytes() > 0);
+        let answer = ["admin", "admin1", "v2-api", "v2-api1", "login", "login1"];
+        assert!(wl.collect::<Vec<String>>() == answer);
+
+        let mut wl = Wordlist::with_words(
+            ["rare\t1", "common\t9"].map(String::from),
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+        );
+        wl.load_weighted(0).unwrap();
+        assert!(wl.collect::<Vec<String>>() == ["common", "rare"]);
+    }
+
+    #[test]
+    fn test_weighted() {
+        let path = &temp_path("weighted.txt");
+        std::fs::write(path, "rare\t1\ncommon\t50\nmid\t7\nother\t7\n").unwrap();
+
+        let pb = std::path::PathBuf::from(path);
+        let mut wl = Wordlist::new(&pb, None, Some(String::from("1")), None, None);
+        wl.load_weighted(2).unwrap();
+        assert!(wl.base_count == 3);
+        assert!(wl.total_count == 6);
+
+        let words = wl.collect::<Vec<String>>();
+        std::fs::remove_file(path).unwrap();
+        let answer = vec![
+            "common".to_string(),
+            "common1".to_string(),
+            "mid".to_string(),
+            "mid1".to_string(),
+            "other".to_string(),
+            "other1".to_string(),
+        ];
+
+        println!("test_weighted: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_weighted_top() {
+        let first = &temp_path("weighted_top1.txt");
+        let second = &temp_path("weighted_top2.txt");
+        std::fs::write(first, "rare\t1\nmid\t7\n").unwrap();
+        std::fs::write(second, "common\t50\nother\t7\nlast\t2\n").unwrap();
+
+        let mut wl = Wordlist::new(&PathBuf::from(first), None, None, None, None);
+        wl.add_wordlist(&PathBuf::from(second)).unwrap();
+        wl.load_weighted_top(0, 2).unwrap();
+        assert!(wl.base_count == 2);
+        let words = wl.collect::<Vec<String>>();
+        println!("test_weighted_top: {:?}", words);
+        assert!(words == ["mid", "common"]);
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn test_interleave() {
+        let path = &temp_path("interleave.txt");
+        std::fs::write(path, "one\ntwo\nthree\nfour\n").unwrap();
+
+        let pb = std::path::PathBuf::from(WL_PATH);
+        let mut wl = Wordlist::new(&pb, None, None, None, None);
+        wl.add_wordlist(&std::path::PathBuf::from(path)).unwrap();
+        wl.interleave = true;
+        assert!(wl.base_count == 7);
+
+        let words = wl.collect::<Vec<String>>();
+        std::fs::remove_file(path).unwrap();
+        let answer = vec![
+            "test".to_string(),
+            "one".to_string(),
+            "line2".to_string(),
+            "two".to_string(),
+            "three".to_string(),
+            "four".to_string(),
+        ];
+
+        println!("test_interleave: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_seek_to() {
+        let pb = std::path::PathBuf::from(WL_PATH);
+        let full = Wordlist::new(&pb, Some(String::from("a,b")), None, None, None)
+            .collect::<Vec<String>>();
+
+        let mut wl = Wordlist::new(&pb, Some(String::from("a,b")), None, None, None);
+        assert!(wl.seek_to(4) == 4);
+        assert!(wl.position() == 4);
+        let rest = wl.collect::<Vec<String>>();
+
+        println!("test_seek_to: {:?}", rest);
+        assert!(do_vecs_match(&rest, &full[4..].to_vec()));
+    }
+
+    #[test]
+    fn test_seek_to_skips_lines() {
+        let path = &temp_path("seek.txt");
+        std::fs::write(path, "a{N:1-3}\nb #!noext\n{SWAP?}c\nd{d}\n").unwrap();
+        let build = |likelihood_order| {
+            let mut wl = Wordlist::new(
+                &PathBuf::from(path),
+                Some(String::from("p,q")),
+                Some(String::from("-x")),
+                Some(String::from("s")),
+                Some(String::from(".bak")),
+            );
+            wl.directives = true;
+            wl.likelihood_order = likelihood_order;
+            wl
+        };
+
+        for likelihood_order in [false, true] {
+            let full = build(likelihood_order).collect::<Vec<String>>();
+            for index in [0, 1, 7, 30, 53, full.len() - 1] {
+                let mut wl = build(likelihood_order);
+                assert!(wl.seek_to(index) == index);
+                assert!(wl.next().as_ref() == Some(&full[index]));
+            }
+            let mut wl = build(likelihood_order);
+            assert!(wl.seek_to(full.len() + 5) == full.len());
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_reverse() {
+        let pb = std::path::PathBuf::from(WL_PATH);
+        for likelihood_order in [false, true] {
+            let build = || {
+                let mut wl = Wordlist::new(
+                    &pb,
+                    Some(String::from("a,b")),
+                    Some(String::from("1")),
+                    Some(String::from("x,y")),
+                    None,
+                );
+                wl.likelihood_order = likelihood_order;
+                wl
+            };
+            let mut full = build().collect::<Vec<String>>();
+            full.reverse();
+            let reversed = build().rev_words().collect::<Vec<String>>();
+            assert!(do_vecs_match(&reversed, &full));
+
+            let mut wl = build().rev_words();
+            assert!(wl.seek_to(5) == 5);
+            assert!(wl.next().as_ref() == Some(&full[5]));
+            assert!(build().rev_words().nth_candidate(9).as_ref() == Some(&full[9]));
+        }
+    }
+
+    #[test]
+    fn test_write_to() {
+        let pb = std::path::PathBuf::from(WL_PATH);
+        for reverse in [false, true] {
+            let build = || {
+                let mut wl = Wordlist::new(
+                    &pb,
+                    Some(String::from("a,b")),
+                    Some(String::from(".php")),
+                    Some(String::from("x,y")),
+                    Some(String::from(".bak")),
+                );
+                wl.ext_strip = true;
+                wl.reverse = reverse;
+                wl
+            };
+            let words = build().collect::<Vec<String>>();
+            let mut expected = words.join("\0");
+            expected.push('\0');
+
+            let mut out = vec![];
+            let written = build().write_to(&mut out, b"\0").unwrap();
+            assert!(out == expected.as_bytes());
+            assert!(written == out.len() as u64);
+        }
+    }
+
+    #[test]
+    fn test_write_block() {
+        let path = temp_path("passthrough.txt");
+        std::fs::write(&path, "a\nb\r\n\nc\nd").unwrap();
+        let wl = Wordlist::new(&path, None, None, None, None);
+        assert!(wl.is_passthrough());
+        let expected = wl.collect::<Vec<String>>().join("\n") + "\n";
+
+        let mut wl = Wordlist::new(&path, None, None, None, None);
+        let mut out = vec![];
+        assert!(wl.write_block(&mut out, 1).unwrap() == Some((1, 2)));
+        while wl.write_block(&mut out, 2).unwrap().is_some() {}
+        assert!(out == expected.as_bytes());
+        assert!(wl.position() == 5);
+
+        let mut out = vec![];
+        Wordlist::new(&path, None, None, None, None)
+            .write_to(&mut out, b"\n")
+            .unwrap();
+        assert!(out == expected.as_bytes());
+        assert!(!Wordlist::new(&path, None, Some(String::from("1")), None, None).is_passthrough());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_block_lengths() {
+        let path = temp_path("write_block_lengths.txt");
+        std::fs::write(&path, "ab\nabcdef\nabcdefgh\n").unwrap();
+        let mut wl = Wordlist::new(&path, None, None, None, None);
+        wl.lengths = parse_lengths("6").unwrap();
+        assert!(!wl.is_passthrough());
+        let mut out = vec![];
+        while wl.write_block(&mut out, 10).unwrap().is_some() {}
+        assert!(out == b"abcdef\n");
+
+        let mut wl = Wordlist::new(&path, None, None, None, None);
+        wl.lengths = wl.top_length_buckets(1).unwrap();
+        let mut out = vec![];
+        while wl.write_block(&mut out, 10).unwrap().is_some() {}
+        assert!(out == b"ab\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_nth_candidate() {
+        let pb = std::path::PathBuf::from(WL_PATH);
+        let mut wl = Wordlist::new(
+            &pb,
+            Some(String::from("a,b")),
+            None,
+            Some(String::from("x,y")),
+            Some(String::from(".1")),
+        );
+        wl.likelihood_order = true;
+        let full = wl.restarted().unwrap().collect::<Vec<String>>();
+
+        wl.seek_to(3);
+        for (i, word) in full.iter().enumerate() {
+            assert!(wl.nth_candidate(i).as_ref() == Some(word));
+        }
+        assert!(wl.nth_candidate(full.len()).is_none());
+        assert!(wl.position() == 3);
+    }
+
+    #[test]
+    fn test_ordering_fingerprint() {
+        let build = || {
+            Wordlist::new(
+                &PathBuf::from(WL_PATH),
+                Some("pre".to_string()),
+                None,
+                Some("dev".to_string()),
+                Some(".bak".to_string()),
+            )
+        };
+        let wl = build();
+        // pinned: a change here changes the meaning of saved indexes, and
+        // must come with a new ORDERING_VERSION
+        assert!(wl.ordering_fingerprint() == 0x69329f6be307ee29);
+        assert!(build().ordering_fingerprint() == wl.ordering_fingerprint());
+        assert!(
+            build().collect::<Vec<_>>()
+                == [
+                    "test",
+                    "pretest",
+                    "test.bak",
+                    "pretest.bak",
+                    "line2",
+                    "preline2",
+                    "line2.bak",
+                    "preline2.bak",
+                    "devstest",
+                    "predevstest",
+                    "devstest.bak",
+                    "predevstest.bak",
+                ]
+        );
+
+        let mut changed = build();
+        changed.interleave = true;
+        assert!(changed.ordering_fingerprint() != wl.ordering_fingerprint());
+        let mut changed = build();
+        changed.set_var("HOST", "example.com");
+        assert!(changed.ordering_fingerprint() != wl.ordering_fingerprint());
+        let mut changed = build();
+        changed.set_random(1, 7).unwrap();
+        assert!(changed.ordering_fingerprint() != wl.ordering_fingerprint());
+    }
+
+    #[test]
+    fn test_skip_degenerate() {
+        let pb = std::path::PathBuf::from(WL_PATH);
+        let mut wl = Wordlist::new(
+            &pb,
+            Some(String::from("test,x")),
+            Some(String::from("x")),
+            None,
+            None,
+        );
+        wl.skip_degenerate = true;
+
+        let words = wl.collect::<Vec<String>>();
+        let answer = vec![
+            "test".to_string(),
+            "xtest".to_string(),
+            "testx".to_string(),
+            "line2".to_string(),
+            "testline2".to_string(),
+            "xline2".to_string(),
+            "line2x".to_string(),
+            "testline2x".to_string(),
+        ];
+
+        println!("test_skip_degenerate: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_split_values() {
+        assert!(split_values("a,b,c", ",") == vec!["a", "b", "c"]);
+        assert!(split_values("1\\,2,3", ",") == vec!["1,2", "3"]);
+        assert!(split_values("a\\\\,b", ",") == vec!["a\\", "b"]);
+        assert!(split_values(", ;x", ";") == vec![", ", "x"]);
+        assert!(split_values("a::b", "::") == vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_swap_token() {
+        let path = &temp_path("swap_token.txt");
+        std::fs::write(path, "FUZZ-a\n{SWAP}\n").unwrap();
+
+        let pb = std::path::PathBuf::from(path);
+        let mut wl = Wordlist::new(&pb, None, None, Some(String::from("x,y")), None);
+        wl.set_swap_token("FUZZ").unwrap();
+        assert!(wl.total_count == 3);
+
+        let words = wl.collect::<Vec<String>>();
+        std::fs::remove_file(path).unwrap();
+        let answer = vec!["x-a".to_string(), "y-a".to_string(), "{SWAP}".to_string()];
+
+        println!("test_swap_token: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_cartesian_swap() {
+        let path = &temp_path("cartesian.txt");
+        std::fs::write(path, "{SWAP}-{SWAP}\nplain\n").unwrap();
+
+        let pb = std::path::PathBuf::from(path);
+        let wl = Wordlist::new(&pb, None, None, Some(String::from("a,b")), None);
+        assert!(wl.total_count == 5);
+
+        let words = wl.collect::<Vec<String>>();
+        std::fs::remove_file(path).unwrap();
+        let answer = vec![
+            "a-a".to_string(),
+            "a-b".to_string(),
+            "b-a".to_string(),
+            "b-b".to_string(),
+            "plain".to_string(),
+        ];
+
+        println!("test_cartesian_swap: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_optional_swap() {
+        let path = &temp_path("optional_swap.txt");
+        std::fs::write(path, "admin{SWAP?}\n").unwrap();
+
+        let pb = std::path::PathBuf::from(path);
+        let wl = Wordlist::new(&pb, None, None, Some(String::from("-dev,-qa")), None);
+        assert!(wl.total_count == 3);
+
+        let words = wl.collect::<Vec<String>>();
+        std::fs::remove_file(path).unwrap();
+        let answer = vec![
+            "admin".to_string(),
+            "admin-dev".to_string(),
+            "admin-qa".to_string(),
+        ];
+
+        println!("test_optional_swap: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_nested_swap() {
+        let path = &temp_path("nested_swap.txt");
+        std::fs::write(path, "{SWAP}\n").unwrap();
+
+        let pb = std::path::PathBuf::from(path);
+        let mut wl = Wordlist::new(
+            &pb,
+            None,
+            None,
+            Some(String::from("dev,stage-{SWAP}")),
+            None,
+        );
+        wl.set_nest_depth(1).unwrap();
+        assert!(wl.total_count == 3);
+
+        let words = wl.collect::<Vec<String>>();
+        std::fs::remove_file(path).unwrap();
+        let answer = vec![
+            "dev".to_string(),
+            "stage-dev".to_string(),
+            "stage-stage-{SWAP}".to_string(),
+        ];
+
+        println!("test_nested_swap: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_vars() {
+        let path = &temp_path("vars.txt");
+        std::fs::write(
+            path,
+            "{ENV:WLINFLATE_TEST_HOST}/{ENV:WLINFLATE_TEST_UNSET}\n",
+        )
+        .unwrap();
+
+        let pb = std::path::PathBuf::from(path);
+        let mut wl = Wordlist::new(&pb, None, None, None, None);
+        wl.set_var("WLINFLATE_TEST_HOST", "example.com");
+
+        let words = wl.collect::<Vec<String>>();
+        std::fs::remove_file(path).unwrap();
+        let answer = vec!["example.com/{ENV:WLINFLATE_TEST_UNSET}".to_string()];
+
+        println!("test_vars: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_templates() {
+        let pb = std::path::PathBuf::from(WL_PATH);
+        let mut wl = Wordlist::new(&pb, None, None, Some(String::from("dev")), None);
+        wl.set_templates(vec![
+            "{WORD}.bak".to_string(),
+            "old/{WORD}{N:1-2}".to_string(),
+        ])
+        .unwrap();
+        assert!(wl.total_count == 9);
+
+        let words = wl.collect::<Vec<String>>();
+        let answer = vec![
+            "test.bak".to_string(),
+            "old/test1".to_string(),
+            "old/test2".to_string(),
+            "line2.bak".to_string(),
+            "old/line21".to_string(),
+            "old/line22".to_string(),
+            "devstest.bak".to_string(),
+            "old/devstest1".to_string(),
+            "old/devstest2".to_string(),
+        ];
+
+        println!("test_templates: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_leet_mutation() {
+        let path = &temp_path("leet.txt");
+        std::fs::write(path, "sea\n{SWAP}\n").unwrap();
+        let mut map = mutate::LeetMap::default();
+        map.map.insert('e', vec!["3".to_string()]);
+        map.map.insert('a', vec!["4".to_string()]);
+        let mut wl = Wordlist::new(
+            &PathBuf::from(path),
+            None,
+            Some("1".to_string()),
+            Some("ace,x".to_string()),
+            None,
+        );
+        wl.set_mutations(vec![Mutation::Leet(map)]).unwrap();
+        assert!(wl.keyspace() == 2 * (4 + 4 + 1));
+
+        let words: Vec<String> = wl.restarted().unwrap().collect();
+        println!("test_leet_mutation: {:?}", words);
+        assert!(words[..5] == ["sea", "se4", "s3a", "s34", "sea1"]);
+        assert!(words[8..11] == ["ace", "ac3", "4ce"]);
+        assert!(words.len() == 18);
+
+        let mut seeked = wl.restarted().unwrap();
+        assert!(seeked.seek_to(10) == 10);
+        assert!(seeked.next().as_deref() == Some(words[10].as_str()));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_subs() {
+        let path = &temp_path("subs.txt");
+        std::fs::write(path, "admin\nlogs\n").unwrap();
+        let wordlist = || {
+            let mut wl = Wordlist::new(
+                &PathBuf::from(path),
+                Some("x".to_string()),
+                None,
+                None,
+                None,
+            );
+            let rules = ["s/o/0/k", "s/^admin$/root/"].map(|r| r.parse().unwrap());
+            wl.set_subs(rules.to_vec()).unwrap();
+            wl
+        };
+        let wl = wordlist();
+        assert!(wl.keyspace() == 6);
+        let words: Vec<String> = wl.collect();
+        println!("test_subs: {:?}", words);
+        assert!(words == ["root", "xroot", "logs", "l0gs", "xlogs", "xl0gs"]);
+
+        // added rules apply from the next base word
+        let mut wl = wordlist();
+        let first: Vec<String> = wl.by_ref().take(2).collect();
+        assert!(first == ["root", "xroot"]);
+        wl.add_subs(["s/s$/z/".parse().unwrap()]);
+        let rest: Vec<String> = wl.collect();
+        assert!(rest == ["logz", "l0gz", "xlogz", "xl0gz"]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_follow() {
+        let path = temp_path("follow.txt");
+        std::fs::write(&path, "a\nb").unwrap();
+        let mut wl = Wordlist::new(&path, None, Some("1".to_string()), None, None);
+        wl.set_follow(true);
+        let words: Vec<String> = wl.by_ref().collect();
+        assert!(words == ["a", "a1"]);
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"c\nd\n").unwrap();
+        let words: Vec<String> = wl.by_ref().collect();
+        assert!(words == ["bc", "bc1", "d", "d1"]);
+        assert!(wl.next().is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_try_new() {
+        let path = temp_path("try_new.txt");
+        std::fs::write(&path, "admin\nkey-{N:1-3}\nbad-{RAND:x}\n").unwrap();
+        let value = |s: &str| Some(s.to_string());
+        let missing = temp_path("missing.txt");
+        assert!(matches!(
+            Wordlist::try_new(&missing, None, None, None, None),
+            Err(WlinflateError::MissingFile(p)) if p == missing
+        ));
+        let dir = std::env::temp_dir();
+        assert!(matches!(
+            Wordlist::try_new(&dir, None, None, None, None),
+            Err(WlinflateError::Io(p, _)) if p == dir
+        ));
+        assert!(matches!(
+            Wordlist::try_new(&path, None, value("1,,2"), None, None),
+            Err(WlinflateError::EmptyValue("append"))
+        ));
+        match Wordlist::try_new(&path, value("x"), None, None, None) {
+            Err(WlinflateError::InvalidToken { line, token }) => {
+                assert!(line == 3 && token == "{RAND:x}")
+            }
+            _ => panic!("malformed token accepted"),
+        }
+        std::fs::write(&path, "admin\nkey-{N:1-3}\n").unwrap();
+        let wl = Wordlist::try_new(&path, value("x"), None, None, None).unwrap();
+        assert!(wl.count() == 8);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_hybrid_masks() {
+        let pb = std::path::PathBuf::from(WL_PATH);
+        let mut wl = Wordlist::new(&pb, None, Some("!".to_string()), None, None);
+        wl.set_hybrid_masks(Some("?d"), Some("x?d?d")).unwrap();
+        assert!(wl.keyspace() == 2 * 10 * 100 * 2);
+        let words: Vec<String> = wl.take(3).collect();
+        assert!(words == ["0testx00", "0testx01", "0testx02"]);
+        assert!(Wordlist::new(&pb, None, None, None, None)
+            .set_hybrid_masks(None, Some("?"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_metadata_columns() {
+        let path = temp_path("metadata.txt");
+        std::fs::write(&path, "a{N:1-2}\tsrc\t{d}\nb\n").unwrap();
+        let mut wl = Wordlist::new(&path, None, None, None, Some(".x".to_string()));
+        wl.set_metadata_columns(true).unwrap();
+        assert!(wl.keyspace() == 6);
+        let words: Vec<String> = wl.collect();
+        println!("test_metadata_columns: {:?}", words);
+        assert!(words[..2] == ["a1\tsrc\t{d}", "a2\tsrc\t{d}"]);
+        assert!(words[4..] == ["b", "b.x"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_input_format() {
+        let path = temp_path("input_format.csv");
+        std::fs::write(&path, "type,name\nA,\"a,b\"\nMX\nA,c\n").unwrap();
+        let mut wl = Wordlist::new(&path, None, Some("1".to_string()), None, None);
+        wl.set_input_format(InputFormat::Delimited {
+            delimiter: ',',
+            column: 2,
+            header: true,
+        })
+        .unwrap();
+        assert!(wl.keyspace() == 4);
+        let words: Vec<String> = wl.collect();
+        println!("test_input_format: {:?}", words);
+        assert!(words == ["a,b", "a,b1", "c", "c1"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_lengths() {
+        let path = temp_path("lengths.txt");
+        std::fs::write(&path, "abc\nabcd\nxyz\nabcdefgh\n").unwrap();
+        let mut wl = Wordlist::new(&path, None, Some("1,22".to_string()), None, None);
+        assert!(wl.top_length_buckets(2).unwrap() == [3..=3, 4..=4]);
+        wl.lengths = parse_lengths("5,9-").unwrap();
+        let words: Vec<String> = wl.collect();
+        println!("test_lengths: {:?}", words);
+        assert!(words == ["abc22", "abcd1", "xyz22", "abcdefgh1", "abcdefgh22"]);
+        assert!(parse_lengths("8-6").is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_multibyte_affixes() {
+        let path = temp_path("multibyte.txt");
+        std::fs::write(&path, "café.txt\n🔥\n").unwrap();
+        let mut wl = Wordlist::new(
+            &path,
+            Some("🔥".to_string()),
+            Some("❤️".to_string()),
+            None,
+            Some(".😀".to_string()),
+        );
+        wl.ext_strip = true;
+        wl.skip_degenerate = true;
+        wl.reverse = true;
+        let keyspace = wl.keyspace();
+        let mut words: Vec<String> = wl.collect();
+        words.reverse();
+        println!("test_multibyte_affixes: {:?}", words);
+        assert!(
+            words[..5]
+                == [
+                    "café.txt",
+                    "🔥café.txt",
+                    "café.txt❤️",
+                    "🔥café.txt❤️",
+                    "café.😀"
+                ]
+        );
+        assert!(!words.contains(&"🔥🔥".to_string()));
+        assert!(words.len() as u128 <= keyspace);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_word2() {
+        let path = &temp_path("word2.txt");
+        std::fs::write(path, "www\napi\n").unwrap();
+
+        let pb = std::path::PathBuf::from(path);
+        let mut wl = Wordlist::new(&pb, None, None, None, None);
+        wl.set_templates(vec![
+            "{WORD1}.{WORD2}.bak".to_string(),
+            "{WORD}".to_string(),
+        ])
+        .unwrap();
+        wl.set_word2(vec!["acme".to_string(), "corp".to_string()])
+            .unwrap();
+        assert!(wl.total_count == 6);
+
+        let words = wl.collect::<Vec<String>>();
+        std::fs::remove_file(path).unwrap();
+        let answer = vec![
+            "www.acme.bak".to_string(),
+            "www.corp.bak".to_string(),
+            "www".to_string(),
+            "api.acme.bak".to_string(),
+            "api.corp.bak".to_string(),
+            "api".to_string(),
+        ];
+
+        println!("test_word2: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_apply_rules() {
+        let path = &temp_path("apply.txt");
+        std::fs::write(path, "admin\nindex.html\n/etc\n").unwrap();
+
+        let pb = std::path::PathBuf::from(path);
+        let mut wl = Wordlist::new(
+            &pb,
+            Some(String::from("old")),
+            None,
+            None,
+            Some(String::from(".bak")),
+        );
+        wl.apply = vec![
+            "ext: if not contains \".\"".parse().unwrap(),
+            "prepend: if not starts-with \"/\"".parse().unwrap(),
+        ];
+
+        let words = wl.collect::<Vec<String>>();
+        std::fs::remove_file(path).unwrap();
+        let answer = vec![
+            "admin".to_string(),
+            "oldadmin".to_string(),
+            "admin.bak".to_string(),
+            "oldadmin.bak".to_string(),
+            "index.html".to_string(),
+            "oldindex.html".to_string(),
+            "/etc".to_string(),
+            "/etc.bak".to_string(),
+        ];
+
+        println!("test_apply_rules: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_directives() {
+        let path = &temp_path("directives.txt");
+        std::fs::write(path, "index.php #!noext\nadmin #!swaponly\n").unwrap();
+
+        let pb = std::path::PathBuf::from(path);
+        let mut wl = Wordlist::new(
+            &pb,
+            Some(String::from("old")),
+            None,
+            None,
+            Some(String::from(".bak")),
+        );
+        wl.directives = true;
+
+        let words = wl.collect::<Vec<String>>();
+        std::fs::remove_file(path).unwrap();
+        let answer = vec![
+            "index.php".to_string(),
+            "oldindex.php".to_string(),
+            "admin".to_string(),
+        ];
+
+        println!("test_directives: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
+    }
+
+    #[test]
+    fn test_ext_strip() {
+        assert!(strip_extension("index.html") == "index");
+        assert!(strip_extension("a.tar.gz") == "a.tar");
+        assert!(strip_extension(".htaccess") == ".htaccess");
+        assert!(strip_extension("v1.2/admin") == "v1.2/admin");
+        assert!(strip_extension("admin") == "admin");
+
+        let pb = std::path::PathBuf::from(WL_PATH);
+        let mut wl = Wordlist::new(
+            &pb,
+            None,
+            Some(String::from(".php")),
+            None,
+            Some(String::from(".bak")),
+        );
+        wl.ext_strip = true;
+
+        let words = wl.collect::<Vec<String>>();
+        let answer = vec![
+            "test".to_string(),
+            "test.php".to_string(),
+            "test.bak".to_string(),
+            "test.bak".to_string(),
+            "line2".to_string(),
+            "line2.php".to_string(),
+            "line2.bak".to_string(),
+            "line2.bak".to_string(),
+        ];
+
+        println!("test_ext_strip: {:?}", words);
+        assert!(do_vecs_match(&words, &answer));
     }
 
     #[test]
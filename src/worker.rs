@@ -0,0 +1,177 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    net::TcpStream,
+    sync::atomic::Ordering,
+    thread,
+    time::Duration,
+};
+
+use log::{debug, info};
+use structopt::StructOpt;
+use wlinflate::parallel::ParallelWordlist;
+
+use crate::{
+    coordinator, error::Error, sink::Sink, validate, Args, DEFAULT_BUFFER_SIZE, INTERRUPTED,
+};
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct WorkArgs {
+    #[structopt(long = "coordinator", help = "host:port of the coordinator")]
+    coordinator: String,
+    #[structopt(
+        long = "forward",
+        help = "send candidates to the coordinator instead of writing them locally"
+    )]
+    forward: bool,
+}
+
+/// Deduplication needs every word before a candidate, which a chunk
+/// generated on its own doesn't see.
+pub fn check_chunked(args: &Args) -> Result<(), Error> {
+    if args.unique || args.unique_insensitive || args.unique_window.is_some() {
+        return Err(Error::InvalidConfig(
+            "--unique and --unique-window can't be used when chunks are generated separately"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Write candidates `start..end` of the configured sequence to `out`,
+/// through the same filters as a local run.
+pub fn generate<W: Write>(args: &Args, start: usize, end: usize, out: &mut W) -> Result<(), Error> {
+    let mut wl = args.wordlist_at(&args.wordlist)?;
+    wl.seek_to(start);
+    let mut sink = Sink::new(args, &wl, out);
+    sink.configure(args)?;
+    let plain = sink.is_plain();
+    if let Some(threads) = args.threads.filter(|&threads| threads > 1) {
+        let mut pool = ParallelWordlist::new(wl, threads);
+        while pool.position() < end {
+            if INTERRUPTED.load(Ordering::Relaxed) {
+                return Err(Error::Interrupted);
+            }
+            let mut chunk = match pool.next() {
+                Some(chunk) => chunk,
+                None => break,
+            };
+            chunk.truncate(end - chunk.start);
+            if plain {
+                sink.write_chunk(&chunk)?;
+                continue;
+            }
+            for (word, origin) in chunk.iter() {
+                sink.emit(pool.wordlist(), word, origin)?;
+            }
+        }
+        wl = pool.into_inner();
+    } else {
+        while wl.position() < end {
+            if INTERRUPTED.load(Ordering::Relaxed) {
+                return Err(Error::Interrupted);
+            }
+            let more = if plain {
+                sink.write_next(&mut wl)?
+            } else {
+                match wl.next_with_origin() {
+                    Some((word, origin)) => {
+                        sink.emit(&wl, &word, &origin)?;
+                        true
+                    }
+                    None => false,
+                }
+            };
+            if !more {
+                break;
+            }
+        }
+    }
+    sink.finish(&wl)?;
+    Ok(sink.writer.flush()?)
+}
+
+/// Pull chunks from a coordinator until it has none left.
+pub fn run(args: &Args, work: &WorkArgs) -> Result<(), Error> {
+    validate(args)?;
+    check_chunked(args)?;
+    let fingerprint = args.sequence_fingerprint()?;
+
+    let network = |e: io::Error| Error::Network(work.coordinator.clone(), e);
+    let stream = TcpStream::connect(&work.coordinator).map_err(network)?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(network)?);
+    let mut conn = &stream;
+    let mut reply = |conn: &mut &TcpStream, message: String| -> Result<String, Error> {
+        conn.write_all(message.as_bytes()).map_err(network)?;
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(network)? == 0 {
+            return Err(network(io::ErrorKind::UnexpectedEof.into()));
+        }
+        Ok(line.trim_end().to_string())
+    };
+
+    let hello = reply(&mut conn, format!("HELLO {:016x}\n", fingerprint))?;
+    if let Some(reason) = hello.strip_prefix("ERR ") {
+        return Err(Error::InvalidConfig(reason.to_string()));
+    }
+    let seed = hello
+        .strip_prefix("OK ")
+        .and_then(|seed| u64::from_str_radix(seed, 16).ok())
+        .ok_or_else(|| Error::InvalidConfig(format!("coordinator replied '{}'", hello)))?;
+    let args = &Args {
+        seed: Some(seed),
+        ..args.clone()
+    };
+    info!("Connected to coordinator {}", work.coordinator);
+
+    let buffer_size = args.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
+    let _lock = args.claim_output()?;
+    let mut out: Box<dyn Write> = match &args.outfile {
+        None => Box::new(BufWriter::with_capacity(buffer_size, io::stdout().lock())),
+        Some(path) => Box::new(BufWriter::with_capacity(buffer_size, File::create(path)?)),
+    };
+
+    loop {
+        let next = reply(&mut conn, "NEXT\n".to_string())?;
+        let words: Vec<&str> = next.split_whitespace().collect();
+        match words.as_slice() {
+            ["CHUNK", start, end] => {
+                let (start, end): (usize, usize) = match (start.parse(), end.parse()) {
+                    (Ok(start), Ok(end)) => (start, end),
+                    _ => return Err(Error::InvalidConfig(format!("bad chunk '{}'", next))),
+                };
+                debug!("Generating chunk {}..{}", start, end);
+                if work.forward {
+                    let mut data = vec![];
+                    generate(args, start, end, &mut data)?;
+                    for piece in data.chunks(coordinator::MAX_DATA) {
+                        conn.write_all(format!("DATA {}\n", piece.len()).as_bytes())
+                            .map_err(network)?;
+                        conn.write_all(piece).map_err(network)?;
+                    }
+                } else {
+                    generate(args, start, end, &mut out)?;
+                    out.flush()?;
+                }
+                let done = reply(&mut conn, format!("COMPLETE {} {}\n", start, end))?;
+                if done != "OK" {
+                    return Err(Error::InvalidConfig(format!(
+                        "coordinator replied '{}'",
+                        done
+                    )));
+                }
+            }
+            ["WAIT"] => thread::sleep(Duration::from_secs(1)),
+            ["DONE"] => break,
+            _ => {
+                return Err(Error::InvalidConfig(format!(
+                    "coordinator replied '{}'",
+                    next
+                )))
+            }
+        }
+    }
+    out.flush()?;
+    info!("Coordinator has no work left");
+    Ok(())
+}
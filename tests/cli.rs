@@ -0,0 +1,283 @@
+//! The `wlinflate` binary as scripts see it: what reaches stdout and
+//! stderr, how it exits, and how it stops when interrupted.
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A directory of inputs and outputs for one test, removed when dropped.
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!(
+            "wlinflate_test_cli_{}_{}",
+            std::process::id(),
+            name
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+
+    fn write(&self, name: &str, text: &str) -> PathBuf {
+        let path = self.path(name);
+        fs::write(&path, text).unwrap();
+        path
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The binary, kept away from the config and `WLINFLATE_*` variables of
+/// whoever runs the tests.
+fn wlinflate(scratch: &Scratch) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_wlinflate"));
+    for (key, _) in env::vars_os() {
+        if key.to_string_lossy().starts_with("WLINFLATE_") {
+            command.env_remove(key);
+        }
+    }
+    command
+        .env("XDG_CONFIG_HOME", scratch.path("config"))
+        .env_remove("RUST_LOG")
+        .stdin(Stdio::null());
+    command
+}
+
+fn run(scratch: &Scratch, args: &[&str]) -> Output {
+    wlinflate(scratch).args(args).output().unwrap()
+}
+
+fn text(bytes: &[u8]) -> &str {
+    std::str::from_utf8(bytes).unwrap()
+}
+
+#[test]
+fn test_interrupt() {
+    let scratch = Scratch::new("interrupt");
+    let out = scratch.path("out.txt");
+    // far more words than could be written before the signal
+    let child = wlinflate(&scratch)
+        .args(["--pattern", "aaaaaaaaaa", "-o"])
+        .arg(&out)
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let partial = scratch.path(&format!(".out.txt.{}.partial", child.id()));
+    let started = Instant::now();
+    while fs::metadata(&partial).map_or(true, |metadata| metadata.len() == 0) {
+        assert!(started.elapsed() < Duration::from_secs(10));
+        thread::sleep(Duration::from_millis(10));
+    }
+    let killed = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.code() == Some(130));
+    let stderr = text(&output.stderr);
+    assert!(stderr.contains("Interrupted after"));
+    assert!(stderr.contains("--resume-from-output"));
+    // flushed up to a whole word, and never mistaken for a finished list
+    let words = fs::read_to_string(&partial).unwrap();
+    assert!(words.starts_with("aaaaaaaaaa\n") && words.ends_with('\n'));
+    assert!(!out.exists());
+}
+
+#[test]
+fn test_log_levels() {
+    let scratch = Scratch::new("log_levels");
+    let words = scratch.write("words.txt", "admin\nlogin\n");
+    let words = words.to_str().unwrap();
+
+    let quiet = run(&scratch, &["-w", words]);
+    assert!(quiet.status.success() && quiet.stderr.is_empty());
+    assert!(text(&quiet.stdout) == "admin\nlogin\n");
+
+    // diagnostics go to stderr, more of them for each -v
+    let info = run(&scratch, &["-w", words, "-v"]);
+    assert!(text(&info.stdout) == "admin\nlogin\n");
+    assert!(text(&info.stderr).contains("[*] Orginal Wordlist Size"));
+    assert!(!text(&info.stderr).contains("[-] "));
+    let debug = run(&scratch, &["-w", words, "-vv"]);
+    assert!(text(&debug.stdout) == "admin\nlogin\n");
+    assert!(text(&debug.stderr).contains("[-] "));
+}
+
+#[test]
+fn test_summary_json() {
+    let scratch = Scratch::new("summary_json");
+    let words = scratch.write("words.txt", "admin\nlogin\n");
+    let (report, out) = (scratch.path("report.json"), scratch.path("out.txt"));
+    let output = wlinflate(&scratch)
+        .arg("-w")
+        .arg(&words)
+        .args(["-a", "1", "-a", "1", "--unique", "--summary-json"])
+        .arg(&report)
+        .arg("-o")
+        .arg(&out)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let summary: serde_json::Value = serde_json::from_slice(&fs::read(&report).unwrap()).unwrap();
+    assert!(summary["input_words"] == 2 && summary["keyspace"] == 6);
+    assert!(summary["emitted"] == 4 && summary["duplicates_removed"] == 2);
+    assert!(summary["wall_time_secs"].is_f64() && summary["interrupted"] == false);
+
+    // without a path the report is the only thing on stderr
+    let output = wlinflate(&scratch)
+        .arg("-w")
+        .arg(&words)
+        .arg("--summary-json")
+        .output()
+        .unwrap();
+    assert!(text(&output.stdout) == "admin\nlogin\n");
+    let summary: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert!(summary["emitted"] == 2);
+}
+
+#[test]
+fn test_quiet() {
+    let scratch = Scratch::new("quiet");
+    let words = scratch.write("words.txt", "admin\nlogin\n");
+    let words = words.to_str().unwrap();
+
+    // silent even when RUST_LOG asks for more, or when failing
+    let output = wlinflate(&scratch)
+        .args(["-w", words, "--quiet"])
+        .env("RUST_LOG", "trace")
+        .output()
+        .unwrap();
+    assert!(output.status.success() && output.stderr.is_empty());
+    assert!(text(&output.stdout) == "admin\nlogin\n");
+    let missing = scratch.path("missing.txt");
+    let output = run(&scratch, &["-w", missing.to_str().unwrap(), "--quiet"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty() && output.stderr.is_empty());
+}
+
+#[test]
+fn test_exit_codes() {
+    let scratch = Scratch::new("exit_codes");
+    let words = scratch.write("words.txt", "admin\n");
+    let words = words.to_str().unwrap();
+    let code = |args: &[&str]| run(&scratch, args).status.code();
+
+    assert!(code(&["-w", words]) == Some(0));
+    assert!(code(&["-w", words, "--buffer-size", "lots"]) == Some(2));
+    let missing = scratch.path("missing.txt");
+    assert!(code(&["-w", missing.to_str().unwrap()]) == Some(3));
+    let unwritable = scratch.path("missing/out.txt");
+    assert!(code(&["-w", words, "-o", unwritable.to_str().unwrap()]) == Some(4));
+    // 26^16 candidates can't be counted out
+    assert!(code(&["--pattern", "aaaaaaaaaaaaaaaa"]) == Some(5));
+}
+
+#[test]
+fn test_defaults() {
+    let scratch = Scratch::new("defaults");
+    let words = scratch.write("words.txt", "admin\n");
+    let words = words.to_str().unwrap();
+    fs::create_dir_all(scratch.path("config/wlinflate")).unwrap();
+    scratch.write("config/wlinflate/config.toml", "prepend = \"x\"\n");
+
+    let output = run(&scratch, &["-w", words]);
+    assert!(text(&output.stdout) == "admin\nxadmin\n");
+    // the environment and then the command line take precedence
+    let output = wlinflate(&scratch)
+        .args(["-w", words])
+        .env("WLINFLATE_PREPEND", "y")
+        .output()
+        .unwrap();
+    assert!(text(&output.stdout) == "admin\nyadmin\n");
+    let output = wlinflate(&scratch)
+        .args(["-w", words, "-p", "z"])
+        .env("WLINFLATE_PREPEND", "y")
+        .output()
+        .unwrap();
+    assert!(text(&output.stdout) == "admin\nzadmin\n");
+
+    scratch.write("config/wlinflate/config.toml", "prepnd = \"x\"\n");
+    assert!(run(&scratch, &["-w", words]).status.code() == Some(2));
+}
+
+#[test]
+fn test_bench() {
+    let scratch = Scratch::new("bench");
+    let words = scratch.write("words.txt", "admin\nlogin\n");
+    let output = wlinflate(&scratch)
+        .arg("-w")
+        .arg(&words)
+        .args(["-a", "1", "bench", "--duration", "0.2"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    // a report, not the words generated
+    let report = text(&output.stdout);
+    assert!(report.contains("words/sec:") && report.contains("MB/sec:"));
+    assert!(!report.contains("admin1"));
+
+    let output = run(&scratch, &["bench", "--duration", "0"]);
+    assert!(output.status.code() == Some(2));
+}
+
+#[test]
+fn test_confirm_over() {
+    let scratch = Scratch::new("confirm_over");
+    let words = scratch.write("words.txt", "admin\nlogin\n");
+    let words = words.to_str().unwrap();
+
+    // no one to ask, so a keyspace over the threshold is refused
+    let output = run(&scratch, &["-w", words, "-a", "1", "--confirm-over", "3"]);
+    assert!(output.status.code() == Some(5) && output.stdout.is_empty());
+    assert!(text(&output.stderr).contains("Keyspace is 4 words"));
+
+    let output = run(
+        &scratch,
+        &["-w", words, "-a", "1", "--confirm-over", "3", "--yes"],
+    );
+    assert!(output.status.success());
+    assert!(text(&output.stdout).lines().count() == 4);
+    let output = run(&scratch, &["-w", words, "-a", "1", "--confirm-over", "4"]);
+    assert!(output.status.success() && output.stderr.is_empty());
+}
+
+#[test]
+fn test_resume_carried_columns() {
+    let scratch = Scratch::new("resume_carried_columns");
+    let words = scratch.write("words.txt", "admin\thttp://a\tx\nlogin\thttp://b\n");
+    let words = words.to_str().unwrap();
+    let full = run(&scratch, &["-w", words, "-a", "1", "--carry-columns"]).stdout;
+    let lines: Vec<&str> = text(&full).lines().collect();
+    assert!(lines[2] == "login\thttp://b");
+
+    let out = scratch.write("out.txt", &format!("{}\n", lines[..3].join("\n")));
+    let resume = |append: &str| {
+        let out = out.to_str().unwrap();
+        let args = ["-w", words, "-a", append, "--carry-columns"];
+        run(
+            &scratch,
+            &[&args[..], &["--resume-from-output", out]].concat(),
+        )
+    };
+    assert!(resume("1").status.success());
+    assert!(fs::read(&out).unwrap() == full);
+    // a different configuration is still caught
+    fs::write(&out, format!("{}\n", lines[..2].join("\n"))).unwrap();
+    assert!(resume("2").status.code() == Some(2));
+}